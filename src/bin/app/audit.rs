@@ -0,0 +1,149 @@
+use super::*;
+use ct_codecs::{Encoder, Hex};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions};
+use std::io::prelude::*;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// A line of the audit log. `prev` is the SHA-256 hash of the previous line,
+/// so that removing or altering an entry breaks the chain.
+#[derive(Serialize, Deserialize)]
+struct AuditEntry {
+    time: String,
+    input: Option<String>,
+    output: Option<String>,
+    input_digest: Option<String>,
+    output_digest: Option<String>,
+    key_fingerprint: Option<String>,
+    algorithm: Option<String>,
+    ad_digest: Option<String>,
+    host: String,
+    user: String,
+    prev: String,
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+    Hex::encode_to_string(hmac_sha256::Hash::hash(data)).unwrap()
+}
+
+fn file_digest(path: Option<&Path>) -> Result<Option<String>, WError> {
+    match path {
+        None => Ok(None),
+        Some(path) => Ok(Some(sha256_hex(&fs::read(path)?))),
+    }
+}
+
+fn host() -> String {
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|host| host.trim().to_string())
+        .filter(|host| !host.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+fn user() -> String {
+    ["USER", "USERNAME", "LOGNAME"]
+        .iter()
+        .find_map(|name| std::env::var(name).ok())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+fn now() -> Result<String, WError> {
+    let unix_time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|_| WError::InternalError("clock"))?;
+    let datetime = der::DateTime::from_unix_duration(Duration::from_secs(unix_time.as_secs()))
+        .map_err(|_| WError::InternalError("clock"))?;
+    Ok(datetime.to_string())
+}
+
+/// Checks the hash chain of an audit log, and returns the number of entries
+/// and the hash of the last one.
+pub fn check_chain(audit_log: &str) -> Result<(usize, String), WError> {
+    let mut prev = GENESIS_HASH.to_string();
+    let mut count = 0;
+    for (i, line) in audit_log.lines().enumerate() {
+        let entry: AuditEntry = serde_json::from_str(line)
+            .map_err(|e| WError::ParseError(format!("Invalid audit log entry #{}: {}", i, e)))?;
+        if entry.prev != prev {
+            return Err(WError::ParseError(format!(
+                "The audit log chain is broken at entry #{}",
+                i
+            )));
+        }
+        prev = sha256_hex(line.as_bytes());
+        count += 1;
+    }
+    Ok((count, prev))
+}
+
+/// An audit log, locked for exclusive use until it is dropped, so that
+/// concurrent signing operations are chained one after the other.
+///
+/// The chain only detects changes before its last entry: removing entries at
+/// the end of the log is undetectable, unless the hash of the last entry is
+/// also recorded somewhere else.
+pub struct AuditLog {
+    file: File,
+    prev: String,
+}
+
+impl AuditLog {
+    /// Opens and locks an audit log, creating it if it doesn't exist, and
+    /// checks its hash chain.
+    pub fn open(audit_log_path: &Path) -> Result<Self, WError> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(audit_log_path)?;
+        file.lock()?;
+        let mut audit_log = String::new();
+        file.read_to_string(&mut audit_log)?;
+        let (_, prev) = check_chain(&audit_log)?;
+        Ok(AuditLog { file, prev })
+    }
+
+    /// Appends an entry describing a signing operation.
+    pub fn record_signing(
+        mut self,
+        config: &Config,
+        input_digest: String,
+        signer: Option<&dyn Signer>,
+        ad: &AdditionalData,
+    ) -> Result<(), WError> {
+        let output_path = match (&config.envelope, &config.signature_path) {
+            (Some(_), Some(signature_path)) => Some(signature_path.as_path()),
+            _ => config.output_path.as_deref(),
+        };
+        let pk = signer.and_then(|signer| signer.public_key());
+        let entry = AuditEntry {
+            time: now()?,
+            input: config
+                .input_path
+                .as_ref()
+                .map(|path| path.display().to_string()),
+            output: output_path.map(|path| path.display().to_string()),
+            input_digest: Some(input_digest),
+            output_digest: file_digest(output_path)?,
+            key_fingerprint: pk.map(|pk| Hex::encode_to_string(pk.key_id()).unwrap()),
+            algorithm: signer.map(|signer| format!("0x{:08x}", signer.alg_id())),
+            ad_digest: match ad.is_empty() {
+                true => None,
+                false => Some(sha256_hex(ad.as_bytes())),
+            },
+            host: host(),
+            user: user(),
+            prev: self.prev,
+        };
+        let mut line = serde_json::to_string(&entry).map_err(|_| WError::InternalError("json"))?;
+        line.push('\n');
+        self.file.write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
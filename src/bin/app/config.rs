@@ -1,5 +1,6 @@
 use super::*;
 use clap::Arg;
+use std::ffi::OsString;
 use std::path::PathBuf;
 
 #[derive(Default, Clone, Debug)]
@@ -7,13 +8,83 @@ pub struct Config {
     pub keygen: bool,
     pub sign: bool,
     pub verify: bool,
+    pub inspect: bool,
+    pub convert: bool,
+    pub migrate: bool,
+    pub attest: bool,
+    pub timestamp: bool,
+    pub log: bool,
+    pub audit: bool,
+    pub serve: bool,
+    pub prepare: bool,
+    pub sign_digest: bool,
+    pub finalize: bool,
+    pub frost_keygen: bool,
+    pub frost_commit: bool,
+    pub frost_sign: bool,
+    pub frost_aggregate: bool,
+    pub key_split: bool,
+    pub key_combine: bool,
     pub input_path: Option<PathBuf>,
     pub output_path: Option<PathBuf>,
     pub pk_path: Option<PathBuf>,
     pub sk_path: Option<PathBuf>,
     pub ad: Option<Vec<u8>>,
+    pub ad_path: Option<PathBuf>,
+    pub ad_hex: Option<String>,
+    pub ad_base64: Option<String>,
+    pub ad_env: Option<String>,
+    pub ad_fields: Vec<String>,
+    pub ad_from_signature: bool,
+    pub ad_embedding: AdEmbedding,
     pub symbol_name: String,
     pub custom_section_name: Option<String>,
+    pub sections: SectionSelection,
+    pub merkle: bool,
+    pub wasmsign2: bool,
+    pub cose: bool,
+    pub envelope: Option<EnvelopeFormat>,
+    pub statement_path: Option<PathBuf>,
+    pub builder_id: Option<String>,
+    pub build_type: Option<String>,
+    pub build_params: Vec<String>,
+    pub sigstore_bundle_path: Option<PathBuf>,
+    pub trusted_root_path: Option<PathBuf>,
+    pub certificate_identity: Option<String>,
+    pub certificate_oidc_issuer: Option<String>,
+    pub certificate_chain_path: Option<PathBuf>,
+    pub ca_path: Option<PathBuf>,
+    pub timestamp_request_path: Option<PathBuf>,
+    pub timestamp_response_path: Option<PathBuf>,
+    pub tsa_ca_path: Option<PathBuf>,
+    pub log_dir: Option<PathBuf>,
+    pub log_head: Option<tlog::TreeHead>,
+    pub audit_log_path: Option<PathBuf>,
+    pub ssh_agent: bool,
+    pub pkcs11_uri: Option<String>,
+    pub signer_plugin: Option<PathBuf>,
+    pub signer_plugin_args: Vec<OsString>,
+    pub listen_address: String,
+    pub auth_token_path: Option<PathBuf>,
+    pub remote_url: Option<String>,
+    pub remote_whole_module: bool,
+    pub allow_non_loopback: bool,
+    pub request_path: Option<PathBuf>,
+    pub signature_path: Option<PathBuf>,
+    pub threshold: Option<u16>,
+    pub signers: Option<u16>,
+    pub nonces_path: Option<PathBuf>,
+    pub commitments_paths: Vec<PathBuf>,
+    pub signature_share_paths: Vec<PathBuf>,
+    pub key_share_paths: Vec<PathBuf>,
+    pub seed: Option<String>,
+    pub seed_path: Option<PathBuf>,
+    pub passphrase_path: Option<PathBuf>,
+    pub derivation_path: Option<String>,
+    pub deterministic: bool,
+    pub hash_alg: HashAlg,
+    pub required_hash_alg: Option<HashAlg>,
+    pub context: Option<String>,
 }
 
 impl Config {
@@ -40,6 +111,247 @@ impl Config {
                     .takes_value(false)
                     .help("Verify a file"),
             )
+            .arg(
+                Arg::with_name("inspect")
+                    .long("inspect")
+                    .takes_value(false)
+                    .help("Show the parameters of a signature, without verifying it"),
+            )
+            .arg(
+                Arg::with_name("convert")
+                    .long("convert")
+                    .takes_value(false)
+                    .help("Convert a signature stored in a Custom Section to the wasmsign2 format"),
+            )
+            .arg(
+                Arg::with_name("migrate")
+                    .long("migrate")
+                    .takes_value(false)
+                    .help("Move a signature stored in a data segment to a Custom Section"),
+            )
+            .arg(
+                Arg::with_name("attest")
+                    .long("attest")
+                    .takes_value(false)
+                    .help("Add a signed in-toto attestation to a module; with --verify or --inspect, check or show it"),
+            )
+            .arg(
+                Arg::with_name("statement-path")
+                    .long("statement")
+                    .value_name("statement_file")
+                    .takes_value(true)
+                    .required(false)
+                    .help("in-toto statement to attest"),
+            )
+            .arg(
+                Arg::with_name("builder-id")
+                    .long("builder-id")
+                    .value_name("uri")
+                    .takes_value(true)
+                    .required(false)
+                    .conflicts_with("statement-path")
+                    .help("Builder identifier of a generated SLSA provenance"),
+            )
+            .arg(
+                Arg::with_name("build-type")
+                    .long("build-type")
+                    .value_name("uri")
+                    .takes_value(true)
+                    .required(false)
+                    .conflicts_with("statement-path")
+                    .help("Build type of a generated SLSA provenance"),
+            )
+            .arg(
+                Arg::with_name("build-param")
+                    .long("build-param")
+                    .value_name("name=value")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .required(false)
+                    .conflicts_with("statement-path")
+                    .help("External parameter of a generated SLSA provenance (can be repeated)"),
+            )
+            .arg(
+                Arg::with_name("sigstore-bundle-path")
+                    .long("sigstore-bundle")
+                    .value_name("bundle_file")
+                    .takes_value(true)
+                    .required(false)
+                    .requires_all(&["trusted-root-path", "certificate-identity", "certificate-oidc-issuer"])
+                    .help("Verify using a Sigstore bundle instead of a public key"),
+            )
+            .arg(
+                Arg::with_name("trusted-root-path")
+                    .long("trusted-root")
+                    .value_name("trusted_root_file")
+                    .takes_value(true)
+                    .required(false)
+                    .help("Sigstore trusted root to verify bundles with"),
+            )
+            .arg(
+                Arg::with_name("certificate-identity")
+                    .long("certificate-identity")
+                    .value_name("identity")
+                    .takes_value(true)
+                    .required(false)
+                    .help("Identity (e-mail address or URI) the signing certificate must be issued to (required with --sigstore-bundle)"),
+            )
+            .arg(
+                Arg::with_name("certificate-oidc-issuer")
+                    .long("certificate-oidc-issuer")
+                    .value_name("issuer")
+                    .takes_value(true)
+                    .required(false)
+                    .help("OIDC issuer that must have authenticated the signer (required with --sigstore-bundle)"),
+            )
+            .arg(
+                Arg::with_name("certificate-chain-path")
+                    .long("certificate-chain")
+                    .value_name("pem_file")
+                    .takes_value(true)
+                    .required(false)
+                    .conflicts_with_all(&["wasmsign2", "cose", "envelope", "remote-whole-module"])
+                    .help("Certificate of the signing key, followed by intermediate certificates, to include in the signature"),
+            )
+            .arg(
+                Arg::with_name("ca-path")
+                    .long("ca")
+                    .value_name("pem_file")
+                    .takes_value(true)
+                    .required(false)
+                    .conflicts_with_all(&["wasmsign2", "cose", "envelope", "attest"])
+                    .help("Root certificates to verify certified signatures with, instead of a public key"),
+            )
+            .arg(
+                Arg::with_name("timestamp")
+                    .long("timestamp")
+                    .takes_value(false)
+                    .help("Write an RFC 3161 time-stamp request for a signature, or add the token of a TSA response to it"),
+            )
+            .arg(
+                Arg::with_name("timestamp-request-path")
+                    .long("timestamp-request")
+                    .value_name("tsq_file")
+                    .takes_value(true)
+                    .required(false)
+                    .help("Time-stamp request file to write"),
+            )
+            .arg(
+                Arg::with_name("timestamp-response-path")
+                    .long("timestamp-response")
+                    .value_name("tsr_file")
+                    .takes_value(true)
+                    .required(false)
+                    .conflicts_with("timestamp-request-path")
+                    .help("TSA response to add to the signature"),
+            )
+            .arg(
+                Arg::with_name("tsa-ca-path")
+                    .long("tsa-ca")
+                    .value_name("pem_file")
+                    .takes_value(true)
+                    .required(false)
+                    .help("Root certificates to verify time-stamp tokens with"),
+            )
+            .arg(
+                Arg::with_name("log")
+                    .long("log")
+                    .takes_value(false)
+                    .help("Show the tree head of a transparency log, and check that it is consistent with --log-head"),
+            )
+            .arg(
+                Arg::with_name("log-dir")
+                    .long("log-dir")
+                    .value_name("dir")
+                    .takes_value(true)
+                    .required(false)
+                    .conflicts_with_all(&["wasmsign2", "cose", "envelope", "remote-whole-module"])
+                    .help("Transparency log to record signatures in, or to check them against"),
+            )
+            .arg(
+                Arg::with_name("log-head")
+                    .long("log-head")
+                    .value_name("size:root_hash")
+                    .takes_value(true)
+                    .required(false)
+                    .conflicts_with_all(&["wasmsign2", "cose", "envelope"])
+                    .help("Trusted tree head of a transparency log"),
+            )
+            .arg(
+                Arg::with_name("audit")
+                    .long("audit")
+                    .takes_value(false)
+                    .help("Check the hash chain of an audit log"),
+            )
+            .arg(
+                Arg::with_name("audit-log-path")
+                    .long("audit-log")
+                    .value_name("jsonl_file")
+                    .takes_value(true)
+                    .required(false)
+                    .help("Audit log to record signing operations in"),
+            )
+            .arg(
+                Arg::with_name("serve")
+                    .long("serve")
+                    .takes_value(false)
+                    .help("Run a signing server"),
+            )
+            .arg(
+                Arg::with_name("prepare")
+                    .long("prepare")
+                    .takes_value(false)
+                    .help("Prepare a module for offline signing, and write a signing request"),
+            )
+            .arg(
+                Arg::with_name("sign-digest")
+                    .long("sign-digest")
+                    .takes_value(false)
+                    .help("Sign a signing request"),
+            )
+            .arg(
+                Arg::with_name("finalize")
+                    .long("finalize")
+                    .takes_value(false)
+                    .help("Add an offline signature to a prepared module"),
+            )
+            .arg(
+                Arg::with_name("frost-keygen")
+                    .long("frost-keygen")
+                    .takes_value(false)
+                    .help("Generate a threshold key, split into FROST key shares"),
+            )
+            .arg(
+                Arg::with_name("frost-commit")
+                    .long("frost-commit")
+                    .takes_value(false)
+                    .help("FROST round 1: generate nonces and commitments"),
+            )
+            .arg(
+                Arg::with_name("frost-sign")
+                    .long("frost-sign")
+                    .takes_value(false)
+                    .help("FROST round 2: sign a signing request with a key share"),
+            )
+            .arg(
+                Arg::with_name("frost-aggregate")
+                    .long("frost-aggregate")
+                    .takes_value(false)
+                    .help("Aggregate FROST signature shares into a signature"),
+            )
+            .arg(
+                Arg::with_name("key-split")
+                    .long("key-split")
+                    .takes_value(false)
+                    .help("Split a secret key into shares for backup"),
+            )
+            .arg(
+                Arg::with_name("key-combine")
+                    .long("key-combine")
+                    .takes_value(false)
+                    .help("Reconstruct a secret key from its shares"),
+            )
             .arg(
                 Arg::with_name("input-path")
                     .short("i")
@@ -62,7 +374,7 @@ impl Config {
                     .long("sk-path")
                     .takes_value(true)
                     .required(false)
-                    .help("Path to the secret key file"),
+                    .help("Path to the secret key file (raw or OpenSSH format)"),
             )
             .arg(
                 Arg::with_name("pk-path")
@@ -70,7 +382,7 @@ impl Config {
                     .long("pk-path")
                     .takes_value(true)
                     .required(false)
-                    .help("Path to the public key file"),
+                    .help("Path to the public key file (raw, OpenSSH or authorized_keys format)"),
             )
             .arg(
                 Arg::with_name("ad")
@@ -80,6 +392,62 @@ impl Config {
                     .required(false)
                     .help("Additional content to authenticate"),
             )
+            .arg(
+                Arg::with_name("ad-file")
+                    .long("ad-file")
+                    .value_name("ad-path")
+                    .takes_value(true)
+                    .required(false)
+                    .help("Path to a file containing the additional content to authenticate"),
+            )
+            .arg(
+                Arg::with_name("ad-hex")
+                    .long("ad-hex")
+                    .takes_value(true)
+                    .required(false)
+                    .help("Hex-encoded additional content to authenticate"),
+            )
+            .arg(
+                Arg::with_name("ad-base64")
+                    .long("ad-base64")
+                    .takes_value(true)
+                    .required(false)
+                    .help("Base64-encoded additional content to authenticate"),
+            )
+            .arg(
+                Arg::with_name("ad-env")
+                    .long("ad-env")
+                    .value_name("variable")
+                    .takes_value(true)
+                    .required(false)
+                    .help("Environment variable containing the additional content to authenticate"),
+            )
+            .arg(
+                Arg::with_name("ad-field")
+                    .long("ad-field")
+                    .value_name("label=value")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .required(false)
+                    .help("Labeled additional content to authenticate (can be repeated)"),
+            )
+            .arg(
+                Arg::with_name("ad-from-signature")
+                    .long("ad-from-signature")
+                    .takes_value(false)
+                    .conflicts_with_all(&["sign", "ad", "ad-file", "ad-hex", "ad-base64", "ad-env", "ad-field"])
+                    .help("Verify with the additional content embedded in the signature"),
+            )
+            .arg(
+                Arg::with_name("embed-ad")
+                    .long("embed-ad")
+                    .value_name("embedding")
+                    .takes_value(true)
+                    .required(false)
+                    .default_value("none")
+                    .help("Store the additional content in the signature (none, data or hash)"),
+            )
             .arg(
                 Arg::with_name("symbol-name")
                     .short("n")
@@ -105,28 +473,468 @@ impl Config {
                     .default_value_if("use-custom-section", None, DEFAULT_CUSTOM_SECTION_NAME)
                     .help("Name of the Custom Section containing the signature"),
             )
+            .arg(
+                Arg::with_name("selective")
+                    .long("selective")
+                    .takes_value(false)
+                    .help("Only sign the non-custom sections, and the listed Custom Sections"),
+            )
+            .arg(
+                Arg::with_name("wasmsign2")
+                    .long("wasmsign2")
+                    .takes_value(false)
+                    .help("Use the wasmsign2 key and signature formats"),
+            )
+            .arg(
+                Arg::with_name("cose")
+                    .long("cose")
+                    .takes_value(false)
+                    .conflicts_with("wasmsign2")
+                    .help("Store the signature as a COSE_Sign1 structure in a Custom Section"),
+            )
+            .arg(
+                Arg::with_name("envelope")
+                    .long("envelope")
+                    .value_name("format")
+                    .takes_value(true)
+                    .possible_values(&["dsse", "jws"])
+                    .conflicts_with_all(&["wasmsign2", "cose", "merkle", "selective"])
+                    .help("Sign the module digest in a DSSE envelope or a JWS, stored in the file set with --signature, or in a Custom Section"),
+            )
+            .arg(
+                Arg::with_name("merkle")
+                    .long("merkle")
+                    .takes_value(false)
+                    .help("Sign a Merkle tree of per-section and per-function hashes"),
+            )
+            .arg(
+                Arg::with_name("include-custom-section")
+                    .long("include-custom-section")
+                    .value_name("name")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .required(false)
+                    .help("Custom Section to sign in selective mode (can be repeated)"),
+            )
+            .arg(
+                Arg::with_name("ssh-agent")
+                    .long("ssh-agent")
+                    .takes_value(false)
+                    .help("Sign using an Ed25519 key held by ssh-agent (SSH_AUTH_SOCK)"),
+            )
+            .arg(
+                Arg::with_name("pkcs11")
+                    .long("pkcs11")
+                    .takes_value(true)
+                    .required(false)
+                    .conflicts_with("ssh-agent")
+                    .help("Sign using a key stored on a PKCS#11 token (pkcs11: URI)"),
+            )
+            .arg(
+                Arg::with_name("signer-plugin")
+                    .long("signer-plugin")
+                    .takes_value(true)
+                    .required(false)
+                    .conflicts_with_all(&["ssh-agent", "pkcs11"])
+                    .help("Sign using an external signer executable"),
+            )
+            .arg(
+                Arg::with_name("signer-plugin-arg")
+                    .long("signer-plugin-arg")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .allow_hyphen_values(true)
+                    .requires("signer-plugin")
+                    .help("Argument to pass to the external signer (can be repeated)"),
+            )
+            .arg(
+                Arg::with_name("listen")
+                    .long("listen")
+                    .takes_value(true)
+                    .default_value("127.0.0.1:8780")
+                    .help("Address the signing server listens on"),
+            )
+            .arg(
+                Arg::with_name("auth-token-file")
+                    .long("auth-token-file")
+                    .takes_value(true)
+                    .required(false)
+                    .help("File containing the signing server authentication token (or set WASMSIGN_AUTH_TOKEN)"),
+            )
+            .arg(
+                Arg::with_name("remote")
+                    .long("remote")
+                    .takes_value(true)
+                    .required(false)
+                    .conflicts_with_all(&["ssh-agent", "pkcs11", "signer-plugin"])
+                    .help("Sign using a remote signing server (http://host:port)"),
+            )
+            .arg(
+                Arg::with_name("remote-whole-module")
+                    .long("remote-whole-module")
+                    .takes_value(false)
+                    .requires("remote")
+                    .help("Send the whole module to the signing server instead of its digest"),
+            )
+            .arg(
+                Arg::with_name("allow-non-loopback")
+                    .long("allow-non-loopback")
+                    .takes_value(false)
+                    .help("Allow the signing server and its clients to use non-loopback addresses (traffic is not encrypted)"),
+            )
+            .arg(
+                Arg::with_name("request-path")
+                    .long("request")
+                    .takes_value(true)
+                    .required(false)
+                    .help("Path to the signing request file"),
+            )
+            .arg(
+                Arg::with_name("signature-path")
+                    .long("signature")
+                    .takes_value(true)
+                    .required(false)
+                    .help("Path to the signature file"),
+            )
+            .arg(
+                Arg::with_name("threshold")
+                    .long("threshold")
+                    .takes_value(true)
+                    .required(false)
+                    .help("Minimum number of signers required to produce a signature"),
+            )
+            .arg(
+                Arg::with_name("signers")
+                    .long("signers")
+                    .takes_value(true)
+                    .required(false)
+                    .help("Total number of key shares"),
+            )
+            .arg(
+                Arg::with_name("nonces-path")
+                    .long("nonces")
+                    .takes_value(true)
+                    .required(false)
+                    .help("Path to the secret FROST nonces file"),
+            )
+            .arg(
+                Arg::with_name("commitments-path")
+                    .long("commitments")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .help("Path to a FROST commitments file (can be repeated)"),
+            )
+            .arg(
+                Arg::with_name("signature-share-path")
+                    .long("signature-share")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .help("Path to a FROST signature share file (can be repeated)"),
+            )
+            .arg(
+                Arg::with_name("key-share-path")
+                    .long("key-share")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .help("Path to a secret key share file (can be repeated)"),
+            )
+            .arg(
+                Arg::with_name("seed")
+                    .long("seed")
+                    .takes_value(true)
+                    .required(false)
+                    .help("Hex-encoded seed to deterministically derive the key pair from"),
+            )
+            .arg(
+                Arg::with_name("seed-file")
+                    .long("seed-file")
+                    .takes_value(true)
+                    .required(false)
+                    .conflicts_with("seed")
+                    .help("File containing the seed (raw or hex-encoded)"),
+            )
+            .arg(
+                Arg::with_name("passphrase-file")
+                    .long("passphrase-file")
+                    .takes_value(true)
+                    .required(false)
+                    .conflicts_with_all(&["seed", "seed-file"])
+                    .help("File containing a passphrase to derive the master seed from"),
+            )
+            .arg(
+                Arg::with_name("derivation-path")
+                    .long("derivation-path")
+                    .takes_value(true)
+                    .required(false)
+                    .help("Hierarchical derivation path of the key (e.g. m/0'/1')"),
+            )
+            .arg(
+                Arg::with_name("deterministic")
+                    .long("deterministic")
+                    .takes_value(false)
+                    .help("Produce reproducible signatures, without added noise"),
+            )
+            .arg(
+                Arg::with_name("hash")
+                    .long("hash")
+                    .value_name("hash")
+                    .takes_value(true)
+                    .required(false)
+                    .default_value("sha512")
+                    .help("Hash function (sha256, sha512, sha3-256 or blake3); when verifying, the signature must use it"),
+            )
+            .arg(
+                Arg::with_name("context")
+                    .long("context")
+                    .value_name("context")
+                    .takes_value(true)
+                    .required(false)
+                    .help("Domain separation context, e.g. the deployment name"),
+            )
             .get_matches();
         let keygen = matches.is_present("keygen");
         let sign = matches.is_present("sign");
         let verify = matches.is_present("verify");
+        let inspect = matches.is_present("inspect");
+        let convert = matches.is_present("convert");
+        let migrate = matches.is_present("migrate");
+        let attest = matches.is_present("attest");
+        let timestamp = matches.is_present("timestamp");
+        let log = matches.is_present("log");
+        let audit = matches.is_present("audit");
+        let serve = matches.is_present("serve");
+        let prepare = matches.is_present("prepare");
+        let sign_digest = matches.is_present("sign-digest");
+        let finalize = matches.is_present("finalize");
+        let frost_keygen = matches.is_present("frost-keygen");
+        let frost_commit = matches.is_present("frost-commit");
+        let frost_sign = matches.is_present("frost-sign");
+        let frost_aggregate = matches.is_present("frost-aggregate");
+        let key_split = matches.is_present("key-split");
+        let key_combine = matches.is_present("key-combine");
         let input_path = matches.value_of("input-path").map(PathBuf::from);
         let output_path = matches.value_of("output-path").map(PathBuf::from);
         let pk_path = matches.value_of("pk-path").map(PathBuf::from);
         let sk_path = matches.value_of("sk-path").map(PathBuf::from);
         let ad = matches.value_of("ad").map(|s| s.as_bytes().to_vec());
+        let ad_path = matches.value_of("ad-file").map(PathBuf::from);
+        let ad_hex = matches.value_of("ad-hex").map(String::from);
+        let ad_base64 = matches.value_of("ad-base64").map(String::from);
+        let ad_env = matches.value_of("ad-env").map(String::from);
+        let ad_fields = matches
+            .values_of("ad-field")
+            .map(|values| values.map(String::from).collect())
+            .unwrap_or_default();
+        let ad_from_signature = matches.is_present("ad-from-signature");
+        let ad_embedding = AdEmbedding::from_name(matches.value_of("embed-ad").unwrap())?;
         let symbol_name = matches.value_of("symbol-name").unwrap().to_string();
         let custom_section_name = matches.value_of("custom-section-name").map(String::from);
+        let included_custom_sections: Vec<&str> = matches
+            .values_of("include-custom-section")
+            .map(|values| values.collect())
+            .unwrap_or_default();
+        let sections =
+            match matches.is_present("selective") || !included_custom_sections.is_empty() {
+                false => SectionSelection::All,
+                true if custom_section_name.is_none() => {
+                    return Err(WError::UsageError(
+                        "Selective signatures must be stored in a Custom Section",
+                    ))
+                }
+                true => SectionSelection::standard(&included_custom_sections),
+            };
+        let merkle = matches.is_present("merkle");
+        let wasmsign2 = matches.is_present("wasmsign2");
+        let cose = matches.is_present("cose");
+        let statement_path = matches.value_of("statement-path").map(PathBuf::from);
+        let builder_id = matches.value_of("builder-id").map(String::from);
+        let build_type = matches.value_of("build-type").map(String::from);
+        let build_params = matches
+            .values_of("build-param")
+            .map(|values| values.map(String::from).collect())
+            .unwrap_or_default();
+        let sigstore_bundle_path = matches.value_of("sigstore-bundle-path").map(PathBuf::from);
+        let trusted_root_path = matches.value_of("trusted-root-path").map(PathBuf::from);
+        let certificate_identity = matches.value_of("certificate-identity").map(String::from);
+        let certificate_oidc_issuer = matches
+            .value_of("certificate-oidc-issuer")
+            .map(String::from);
+        let envelope = match matches.value_of("envelope") {
+            None => None,
+            Some(format) => Some(EnvelopeFormat::from_name(format)?),
+        };
+        if merkle && custom_section_name.is_none() {
+            return Err(WError::UsageError(
+                "Merkle signatures must be stored in a Custom Section",
+            ));
+        }
+        let certificate_chain_path = matches.value_of("certificate-chain-path").map(PathBuf::from);
+        let ca_path = matches.value_of("ca-path").map(PathBuf::from);
+        if (certificate_chain_path.is_some() || ca_path.is_some()) && custom_section_name.is_none() {
+            return Err(WError::UsageError(
+                "Certified signatures must be stored in a Custom Section",
+            ));
+        }
+        let timestamp_request_path = matches.value_of("timestamp-request-path").map(PathBuf::from);
+        let timestamp_response_path = matches
+            .value_of("timestamp-response-path")
+            .map(PathBuf::from);
+        let tsa_ca_path = matches.value_of("tsa-ca-path").map(PathBuf::from);
+        if (timestamp || tsa_ca_path.is_some()) && custom_section_name.is_none() {
+            return Err(WError::UsageError(
+                "Time-stamped signatures must be stored in a Custom Section",
+            ));
+        }
+        let log_dir = matches.value_of("log-dir").map(PathBuf::from);
+        let log_head = match matches.value_of("log-head") {
+            None => None,
+            Some(head) => Some(head.parse()?),
+        };
+        if (log_dir.is_some() || log_head.is_some())
+            && (sign || verify || !log)
+            && custom_section_name.is_none()
+        {
+            return Err(WError::UsageError(
+                "Logged signatures must be stored in a Custom Section",
+            ));
+        }
+        let audit_log_path = matches.value_of("audit-log-path").map(PathBuf::from);
+        let ssh_agent = matches.is_present("ssh-agent");
+        let pkcs11_uri = matches.value_of("pkcs11").map(String::from);
+        let signer_plugin = matches.value_of_os("signer-plugin").map(PathBuf::from);
+        let signer_plugin_args = matches
+            .values_of_os("signer-plugin-arg")
+            .map(|values| values.map(OsString::from).collect())
+            .unwrap_or_default();
+        let listen_address = matches.value_of("listen").unwrap().to_string();
+        let auth_token_path = matches.value_of("auth-token-file").map(PathBuf::from);
+        let remote_url = matches.value_of("remote").map(String::from);
+        let remote_whole_module = matches.is_present("remote-whole-module");
+        let allow_non_loopback = matches.is_present("allow-non-loopback");
+        let request_path = matches.value_of("request-path").map(PathBuf::from);
+        let signature_path = matches.value_of("signature-path").map(PathBuf::from);
+        let threshold = match matches.value_of("threshold") {
+            None => None,
+            Some(t) => Some(t.parse().map_err(|_| WError::UsageError("Invalid threshold"))?),
+        };
+        let signers = match matches.value_of("signers") {
+            None => None,
+            Some(n) => Some(
+                n.parse()
+                    .map_err(|_| WError::UsageError("Invalid number of signers"))?,
+            ),
+        };
+        let nonces_path = matches.value_of("nonces-path").map(PathBuf::from);
+        let commitments_paths = matches
+            .values_of("commitments-path")
+            .map(|values| values.map(PathBuf::from).collect())
+            .unwrap_or_default();
+        let signature_share_paths = matches
+            .values_of("signature-share-path")
+            .map(|values| values.map(PathBuf::from).collect())
+            .unwrap_or_default();
+        let seed = matches.value_of("seed").map(String::from);
+        let seed_path = matches.value_of("seed-file").map(PathBuf::from);
+        let passphrase_path = matches.value_of("passphrase-file").map(PathBuf::from);
+        let derivation_path = matches.value_of("derivation-path").map(String::from);
+        let deterministic = matches.is_present("deterministic");
+        let hash_alg = HashAlg::from_name(matches.value_of("hash").unwrap())?;
+        let required_hash_alg = match matches.occurrences_of("hash") {
+            0 => None,
+            _ => Some(hash_alg),
+        };
+        let context = matches.value_of("context").map(String::from);
+        check_context(context.as_deref())?;
+        let key_share_paths = matches
+            .values_of("key-share-path")
+            .map(|values| values.map(PathBuf::from).collect())
+            .unwrap_or_default();
         Ok(Config {
             keygen,
             sign,
             verify,
+            inspect,
+            convert,
+            migrate,
+            attest,
+            timestamp,
+            log,
+            audit,
+            serve,
+            prepare,
+            sign_digest,
+            finalize,
+            frost_keygen,
+            frost_commit,
+            frost_sign,
+            frost_aggregate,
+            key_split,
+            key_combine,
             input_path,
             output_path,
             pk_path,
             sk_path,
             ad,
+            ad_path,
+            ad_hex,
+            ad_base64,
+            ad_env,
+            ad_fields,
+            ad_from_signature,
+            ad_embedding,
             symbol_name,
             custom_section_name,
+            sections,
+            merkle,
+            wasmsign2,
+            cose,
+            envelope,
+            statement_path,
+            builder_id,
+            build_type,
+            build_params,
+            sigstore_bundle_path,
+            trusted_root_path,
+            certificate_identity,
+            certificate_oidc_issuer,
+            certificate_chain_path,
+            ca_path,
+            timestamp_request_path,
+            timestamp_response_path,
+            tsa_ca_path,
+            log_dir,
+            log_head,
+            audit_log_path,
+            ssh_agent,
+            pkcs11_uri,
+            signer_plugin,
+            signer_plugin_args,
+            listen_address,
+            auth_token_path,
+            remote_url,
+            remote_whole_module,
+            allow_non_loopback,
+            request_path,
+            signature_path,
+            threshold,
+            signers,
+            nonces_path,
+            commitments_paths,
+            signature_share_paths,
+            key_share_paths,
+            seed,
+            seed_path,
+            passphrase_path,
+            derivation_path,
+            deterministic,
+            hash_alg,
+            required_hash_alg,
+            context,
         })
     }
 }
@@ -1,4 +1,5 @@
 pub mod actions;
+pub mod audit;
 pub mod config;
 
 pub(crate) use self::config::*;
@@ -17,6 +17,57 @@ fn main() -> Result<(), WError> {
     if config.verify {
         return actions::verify(&config);
     }
+    if config.inspect {
+        return actions::inspect(&config);
+    }
+    if config.convert {
+        return actions::convert(&config);
+    }
+    if config.migrate {
+        return actions::migrate(&config);
+    }
+    if config.attest {
+        return actions::attest(&config);
+    }
+    if config.timestamp {
+        return actions::timestamp(&config);
+    }
+    if config.log {
+        return actions::log(&config);
+    }
+    if config.audit {
+        return actions::audit(&config);
+    }
+    if config.prepare {
+        return actions::prepare(&config);
+    }
+    if config.sign_digest {
+        return actions::sign_digest(&config);
+    }
+    if config.finalize {
+        return actions::finalize(&config);
+    }
+    if config.frost_keygen {
+        return actions::frost_keygen(&config);
+    }
+    if config.frost_commit {
+        return actions::frost_commit(&config);
+    }
+    if config.frost_sign {
+        return actions::frost_sign(&config);
+    }
+    if config.frost_aggregate {
+        return actions::frost_aggregate(&config);
+    }
+    if config.key_split {
+        return actions::key_split(&config);
+    }
+    if config.key_combine {
+        return actions::key_combine(&config);
+    }
+    if config.serve {
+        return actions::serve(&config);
+    }
     eprintln!("No action specified on the command-line");
     Ok(())
 }
@@ -18,6 +18,16 @@ pub enum WError {
     SignatureError(&'static str),
     #[error("{0}")]
     EdDSASignatureError(#[from] ed25519_compact::Error),
+    #[error("Signer plugin error: {0}")]
+    PluginError(String),
+    #[error("Signing server error: {0}")]
+    RemoteError(String),
+    #[error("Additional data mismatch")]
+    AdMismatch,
+    #[error("Certificate error: {0}")]
+    CertificateError(String),
+    #[error("Signature mismatch in the {0}")]
+    SectionMismatch(String),
     #[error("Unsupported")]
     Unsupported,
 }
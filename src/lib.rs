@@ -1,39 +1,178 @@
 pub mod errors;
+pub mod remote;
 pub mod signature;
 mod wasm_signature;
 
 pub use self::errors::*;
 pub use self::signature::*;
+pub use coset;
+use ct_codecs::{Encoder, Hex};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 pub const DEFAULT_SYMBOL_NAME: &str = "___SIGNATURE";
 pub const DEFAULT_CUSTOM_SECTION_NAME: &str = "signature_wasmsign";
+pub const DEFAULT_COSE_SECTION_NAME: &str = "signature_cose";
+pub const DEFAULT_DSSE_SECTION_NAME: &str = "signature_dsse";
+pub const DEFAULT_JWS_SECTION_NAME: &str = "signature_jws";
+pub const DEFAULT_ATTESTATION_SECTION_NAME: &str = "attestation_intoto";
+pub const WASMSIGN2_SECTION_NAME: &str = wasm_signature::WASMSIGN2_SECTION_NAME;
 
 pub fn keygen(signature_alg: &dyn SignatureAlg) -> KeyPair {
     signature_alg.keygen()
 }
 
+pub fn keygen_from_seed(
+    signature_alg: &dyn SignatureAlg,
+    master_seed: &[u8],
+    derivation_path: Option<&str>,
+) -> Result<KeyPair, WError> {
+    match derivation_path {
+        None => signature_alg.keygen_from_seed(master_seed),
+        Some(path) => signature_alg.keygen_from_seed(&derivation::derive_seed(master_seed, path)?),
+    }
+}
+
 pub fn sign(
     module_bytes: &[u8],
-    key_pair: &KeyPair,
-    ad: Option<&[u8]>,
+    signer: &dyn Signer,
+    ad: &AdditionalData,
+    hash_alg: HashAlg,
+    context: Option<&str>,
     symbol_name: &str,
 ) -> Result<Vec<u8>, WError> {
-    let signature_alg = key_pair.sk.to_alg()?;
-    wasm_signature::attach_signature(module_bytes, &signature_alg, ad, key_pair, symbol_name)
+    wasm_signature::attach_signature(module_bytes, signer, ad, hash_alg, context, symbol_name)
 }
 
 pub fn sign_custom_section(
     module_bytes: &[u8],
-    key_pair: &KeyPair,
-    ad: Option<&[u8]>,
+    signer: &dyn Signer,
+    ad: &AdditionalData,
+    hash_alg: HashAlg,
+    context: Option<&str>,
+    sections: &SectionSelection,
     custom_section_name: &str,
 ) -> Result<Vec<u8>, WError> {
-    let signature_alg = key_pair.sk.to_alg()?;
     wasm_signature::attach_signature_in_custom_section(
         module_bytes,
-        &signature_alg,
+        signer,
+        ad,
+        hash_alg,
+        context,
+        sections,
+        custom_section_name,
+    )
+}
+
+pub fn sign_custom_section_merkle(
+    module_bytes: &[u8],
+    signer: &dyn Signer,
+    ad: &AdditionalData,
+    hash_alg: HashAlg,
+    context: Option<&str>,
+    sections: &SectionSelection,
+    custom_section_name: &str,
+) -> Result<Vec<u8>, WError> {
+    wasm_signature::attach_merkle_signature_in_custom_section(
+        module_bytes,
+        signer,
         ad,
-        key_pair,
+        hash_alg,
+        context,
+        sections,
+        custom_section_name,
+    )
+}
+
+pub fn prepare(
+    module_bytes: &[u8],
+    alg_id: u32,
+    ad: &AdditionalData,
+    hash_alg: HashAlg,
+    context: Option<&str>,
+    symbol_name: &str,
+) -> Result<(Vec<u8>, SigningRequest), WError> {
+    let signature_alg = alg_from_id(alg_id)?;
+    let prepared_module_bytes = wasm_signature::prepare_signature(
+        module_bytes,
+        signature_alg.as_ref(),
+        hash_alg,
+        context,
+        ad,
+        symbol_name,
+    )?;
+    let digest = signature_alg.prehash(&prepared_module_bytes, ad, hash_alg, context)?;
+    let request = SigningRequest::new(alg_id, digest)
+        .with_hash_alg(hash_alg)
+        .with_context(context)
+        .with_ad(ad);
+    Ok((prepared_module_bytes, request))
+}
+
+pub fn prepare_custom_section(
+    module_bytes: &[u8],
+    alg_id: u32,
+    ad: &AdditionalData,
+    hash_alg: HashAlg,
+    context: Option<&str>,
+    sections: &SectionSelection,
+    custom_section_name: &str,
+) -> Result<(Vec<u8>, SigningRequest), WError> {
+    let signature_alg = alg_from_id(alg_id)?;
+    wasm_signature::prepare_signature_in_custom_section(module_bytes, custom_section_name)?;
+    let data = wasm_signature::signed_data(module_bytes, sections, custom_section_name)?;
+    let digest = signature_alg.prehash(&data, ad, hash_alg, context)?;
+    let request = SigningRequest::new(alg_id, digest)
+        .with_hash_alg(hash_alg)
+        .with_context(context)
+        .with_ad(ad)
+        .with_sections(sections);
+    Ok((module_bytes.to_vec(), request))
+}
+
+pub fn prepare_custom_section_merkle(
+    module_bytes: &[u8],
+    alg_id: u32,
+    ad: &AdditionalData,
+    hash_alg: HashAlg,
+    context: Option<&str>,
+    sections: &SectionSelection,
+    custom_section_name: &str,
+) -> Result<(Vec<u8>, SigningRequest), WError> {
+    let signature_alg = alg_from_id(alg_id)?;
+    wasm_signature::prepare_signature_in_custom_section(module_bytes, custom_section_name)?;
+    let leaves =
+        wasm_signature::merkle_leaves(module_bytes, hash_alg, sections, custom_section_name)?;
+    let data = wasm_signature::merkle_signed_data(hash_alg, sections, &leaves);
+    let digest = signature_alg.prehash(&data, ad, hash_alg, context)?;
+    let request = SigningRequest::new(alg_id, digest)
+        .with_hash_alg(hash_alg)
+        .with_context(context)
+        .with_ad(ad)
+        .with_sections(sections)
+        .with_merkle_leaves(&leaves);
+    Ok((module_bytes.to_vec(), request))
+}
+
+pub fn sign_digest(request: &SigningRequest, signer: &dyn Signer) -> Result<Signature, WError> {
+    signer.sign_request(request)
+}
+
+pub fn finalize(
+    prepared_module_bytes: &[u8],
+    signature: &Signature,
+    symbol_name: &str,
+) -> Result<Vec<u8>, WError> {
+    wasm_signature::store_signature(prepared_module_bytes, signature, symbol_name)
+}
+
+pub fn finalize_custom_section(
+    prepared_module_bytes: &[u8],
+    signature: &Signature,
+    custom_section_name: &str,
+) -> Result<Vec<u8>, WError> {
+    wasm_signature::store_signature_in_custom_section(
+        prepared_module_bytes,
+        signature,
         custom_section_name,
     )
 }
@@ -41,19 +180,344 @@ pub fn sign_custom_section(
 pub fn verify(
     module_bytes: &[u8],
     pk: &PublicKey,
-    ad: Option<&[u8]>,
+    ad: &AdditionalData,
+    context: Option<&str>,
     symbol_name: &str,
 ) -> Result<(), WError> {
     pk.to_alg()?;
-    wasm_signature::verify_signature(module_bytes, ad, pk, symbol_name)
+    wasm_signature::verify_signature(module_bytes, ad, context, pk, symbol_name)
 }
 
 pub fn verify_custom_section(
     module_bytes: &[u8],
     pk: &PublicKey,
-    ad: Option<&[u8]>,
+    ad: &AdditionalData,
+    context: Option<&str>,
+    custom_section_name: &str,
+) -> Result<(), WError> {
+    pk.to_alg()?;
+    wasm_signature::verify_signature_in_custom_section(
+        module_bytes,
+        ad,
+        context,
+        pk,
+        custom_section_name,
+    )
+}
+
+/// Verifies a signature stored in a Custom Section using the certificate chain
+/// it carries, and returns the public key of the signer.
+/// If TSA roots are given, the certificates are checked at the time certified
+/// by the time-stamp token of the signature, rather than at the current time.
+pub fn verify_custom_section_with_roots(
+    module_bytes: &[u8],
+    roots: &x509::TrustedRoots,
+    tsa_roots: Option<&x509::TrustedRoots>,
+    ad: &AdditionalData,
+    context: Option<&str>,
+    custom_section_name: &str,
+) -> Result<PublicKey, WError> {
+    let signature = inspect_custom_section(module_bytes, custom_section_name)?;
+    let time = match tsa_roots {
+        Some(tsa_roots) => wasm_signature::verify_timestamp(&signature, tsa_roots.roots())?,
+        None => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| WError::InternalError("clock"))?
+            .as_secs(),
+    };
+    let pk = roots.signer_public_key(&signature, time)?;
+    verify_custom_section(module_bytes, &pk, ad, context, custom_section_name)?;
+    Ok(pk)
+}
+
+/// Creates an RFC 3161 time-stamp request over a signature stored in a Custom Section.
+pub fn timestamp_request(module_bytes: &[u8], custom_section_name: &str) -> Result<Vec<u8>, WError> {
+    wasm_signature::timestamp_request_for_custom_section(module_bytes, custom_section_name)
+}
+
+/// Adds the time-stamp token of a TSA response to a signature stored in a Custom Section.
+pub fn attach_timestamp(
+    module_bytes: &[u8],
+    response: &[u8],
+    custom_section_name: &str,
+) -> Result<Vec<u8>, WError> {
+    let token = tsp::token_from_response(response)?;
+    wasm_signature::attach_timestamp_in_custom_section(module_bytes, &token, custom_section_name)
+}
+
+/// Verifies the time-stamp token of a signature stored in a Custom Section,
+/// and returns the time it certifies, in seconds since the UNIX epoch.
+pub fn verify_timestamp(
+    module_bytes: &[u8],
+    tsa_roots: &x509::TrustedRoots,
+    custom_section_name: &str,
+) -> Result<u64, WError> {
+    let signature = inspect_custom_section(module_bytes, custom_section_name)?;
+    wasm_signature::verify_timestamp(&signature, tsa_roots.roots())
+}
+
+/// Adds the inclusion proof of a transparency log entry to a signature stored in a Custom Section.
+pub fn attach_log_proof(
+    module_bytes: &[u8],
+    log_proof: &tlog::InclusionProof,
+    custom_section_name: &str,
+) -> Result<Vec<u8>, WError> {
+    wasm_signature::attach_log_proof_in_custom_section(module_bytes, log_proof, custom_section_name)
+}
+
+/// Verifies that the signature stored in a Custom Section was recorded in a
+/// transparency log with the given head, by the key with the given ID if one
+/// is provided. Returns the log entry and its inclusion proof.
+pub fn verify_log_proof(
+    module_bytes: &[u8],
+    key_id: Option<&[u8]>,
+    head: &tlog::TreeHead,
+    consistency_proof: &[Vec<u8>],
+    custom_section_name: &str,
+) -> Result<tlog::InclusionProof, WError> {
+    wasm_signature::verify_log_proof_in_custom_section(
+        module_bytes,
+        key_id,
+        head,
+        consistency_proof,
+        custom_section_name,
+    )
+}
+
+/// Verifies a Merkle signature, only hashing the parts of the module accepted by `filter`.
+pub fn verify_custom_section_partial(
+    module_bytes: &[u8],
+    pk: &PublicKey,
+    ad: &AdditionalData,
+    context: Option<&str>,
     custom_section_name: &str,
+    filter: &dyn Fn(&LeafKind) -> bool,
 ) -> Result<(), WError> {
     pk.to_alg()?;
-    wasm_signature::verify_signature_in_custom_section(module_bytes, ad, pk, custom_section_name)
+    wasm_signature::verify_signature_in_custom_section_partial(
+        module_bytes,
+        ad,
+        context,
+        pk,
+        custom_section_name,
+        filter,
+    )
+}
+
+pub fn sign_cose(
+    module_bytes: &[u8],
+    signer: &dyn Signer,
+    ad: &AdditionalData,
+    context: Option<&str>,
+    key_id: Option<&[u8]>,
+    custom_section_name: &str,
+) -> Result<Vec<u8>, WError> {
+    wasm_signature::attach_cose_signature(
+        module_bytes,
+        signer,
+        ad,
+        context,
+        key_id,
+        custom_section_name,
+    )
+}
+
+pub fn verify_cose(
+    module_bytes: &[u8],
+    pk: &PublicKey,
+    ad: &AdditionalData,
+    context: Option<&str>,
+    custom_section_name: &str,
+) -> Result<(), WError> {
+    pk.to_alg()?;
+    wasm_signature::verify_cose_signature(module_bytes, ad, context, pk, custom_section_name)
+}
+
+pub fn inspect_cose(
+    module_bytes: &[u8],
+    custom_section_name: &str,
+) -> Result<coset::CoseSign1, WError> {
+    wasm_signature::extract_cose_signature(module_bytes, custom_section_name)
+}
+
+pub fn sign_envelope(
+    module_bytes: &[u8],
+    signer: &dyn Signer,
+    hash_alg: HashAlg,
+    key_id: Option<&[u8]>,
+    format: EnvelopeFormat,
+) -> Result<Vec<u8>, WError> {
+    wasm_signature::sign_envelope(module_bytes, signer, hash_alg, key_id, format)
+}
+
+pub fn verify_envelope(
+    module_bytes: &[u8],
+    pk: &PublicKey,
+    envelope: &[u8],
+) -> Result<(), WError> {
+    wasm_signature::verify_envelope(module_bytes, pk, envelope)
+}
+
+pub fn sign_envelope_custom_section(
+    module_bytes: &[u8],
+    signer: &dyn Signer,
+    hash_alg: HashAlg,
+    key_id: Option<&[u8]>,
+    format: EnvelopeFormat,
+    custom_section_name: &str,
+) -> Result<Vec<u8>, WError> {
+    wasm_signature::attach_envelope_in_custom_section(
+        module_bytes,
+        signer,
+        hash_alg,
+        key_id,
+        format,
+        custom_section_name,
+    )
+}
+
+pub fn verify_envelope_custom_section(
+    module_bytes: &[u8],
+    pk: &PublicKey,
+    custom_section_name: &str,
+) -> Result<(), WError> {
+    wasm_signature::verify_envelope_in_custom_section(module_bytes, pk, custom_section_name)
+}
+
+pub fn attest(
+    module_bytes: &[u8],
+    signer: &dyn Signer,
+    statement: &intoto::Statement,
+    subject_name: &str,
+    hash_alg: HashAlg,
+    key_id: Option<&[u8]>,
+    attestation_section_name: &str,
+) -> Result<Vec<u8>, WError> {
+    wasm_signature::attach_attestation(
+        module_bytes,
+        signer,
+        statement,
+        subject_name,
+        hash_alg,
+        key_id,
+        attestation_section_name,
+    )
+}
+
+pub fn verify_attestation(
+    module_bytes: &[u8],
+    pk: &PublicKey,
+    attestation_section_name: &str,
+) -> Result<intoto::Statement, WError> {
+    wasm_signature::verify_attestation(module_bytes, pk, attestation_section_name)
+}
+
+pub fn inspect_attestation(
+    module_bytes: &[u8],
+    attestation_section_name: &str,
+) -> Result<intoto::Statement, WError> {
+    wasm_signature::extract_attestation(module_bytes, attestation_section_name)
+}
+
+/// Verifies a module using a Sigstore bundle and a trusted root, without
+/// network access. The signing certificate must have been issued to `identity`,
+/// authenticated by the OIDC `issuer`.
+pub fn verify_sigstore_bundle(
+    module_bytes: &[u8],
+    bundle: &[u8],
+    trusted_root: &[u8],
+    identity: &str,
+    issuer: &str,
+) -> Result<sigstore::VerifiedBundle, WError> {
+    let verified = sigstore::verify_bundle(module_bytes, bundle, trusted_root)?;
+    verified.check_identity(identity, issuer)?;
+    Ok(verified)
+}
+
+pub fn sign_wasmsign2(
+    module_bytes: &[u8],
+    signer: &dyn Signer,
+    key_id: Option<&[u8]>,
+) -> Result<Vec<u8>, WError> {
+    wasm_signature::attach_wasmsign2_signature(module_bytes, signer, key_id)
+}
+
+pub fn verify_wasmsign2(module_bytes: &[u8], pk: &PublicKey) -> Result<(), WError> {
+    wasm_signature::verify_wasmsign2_signature(module_bytes, pk)
+}
+
+/// Verifies a signature stored in a Custom Section, removes it, and signs the
+/// module again using the wasmsign2 format.
+pub fn convert_to_wasmsign2(
+    module_bytes: &[u8],
+    pk: &PublicKey,
+    ad: &AdditionalData,
+    context: Option<&str>,
+    custom_section_name: &str,
+    signer: &dyn Signer,
+) -> Result<Vec<u8>, WError> {
+    if !ad.is_empty() || context.filter(|&context| context != DEFAULT_CONTEXT).is_some() {
+        return Err(WError::UsageError(
+            "wasmsign2 signatures cannot include additional data or a context",
+        ));
+    }
+    verify_custom_section(module_bytes, pk, ad, context, custom_section_name)?;
+    let module_bytes = wasm_signature::strip_custom_section(module_bytes, custom_section_name)?;
+    sign_wasmsign2(&module_bytes, signer, None)
+}
+
+/// Moves a signature stored in a data segment to a Custom Section: the signature
+/// is verified, the global, export and data segments holding it are removed, and
+/// the module is signed again, with the same parameters. The signature is verified
+/// with the given context, or the default context if `None`.
+/// Returns the migrated module, and a description of each step.
+pub fn migrate(
+    module_bytes: &[u8],
+    pk: &PublicKey,
+    ad: &AdditionalData,
+    context: Option<&str>,
+    symbol_name: &str,
+    signer: &dyn Signer,
+    custom_section_name: &str,
+) -> Result<(Vec<u8>, Vec<String>), WError> {
+    let signature = inspect(module_bytes, symbol_name)?;
+    verify(module_bytes, pk, ad, context, symbol_name)?;
+    let mut steps = vec![format!(
+        "Verified the signature stored in {} with key {}",
+        symbol_name,
+        Hex::encode_to_string(pk.key_id()).unwrap()
+    )];
+    let ad = wasm_signature::resolve_ad(&signature, ad)?.embed(match signature.embedded_ad() {
+        None => ad.embedding(),
+        Some(EmbeddedAd::Data(_)) => AdEmbedding::Data,
+        Some(EmbeddedAd::Hash(..)) => AdEmbedding::Hash,
+    });
+    let module_bytes = wasm_signature::strip_signature(module_bytes, symbol_name, &mut |step| {
+        steps.push(step.to_string())
+    })?;
+    let signed_module_bytes = sign_custom_section(
+        &module_bytes,
+        signer,
+        &ad,
+        signature.hash_alg(),
+        context,
+        &SectionSelection::All,
+        custom_section_name,
+    )?;
+    steps.push(format!(
+        "Signed the module, signature stored in Custom Section {}",
+        custom_section_name
+    ));
+    Ok((signed_module_bytes, steps))
+}
+
+pub fn inspect(module_bytes: &[u8], symbol_name: &str) -> Result<Signature, WError> {
+    wasm_signature::extract_signature(module_bytes, symbol_name)
+}
+
+pub fn inspect_custom_section(
+    module_bytes: &[u8],
+    custom_section_name: &str,
+) -> Result<Signature, WError> {
+    wasm_signature::extract_signature_from_custom_section(module_bytes, custom_section_name)
 }
@@ -0,0 +1,164 @@
+use super::super::errors::*;
+use super::super::signature::*;
+use super::http::*;
+use super::*;
+use ct_codecs::{Decoder, Hex};
+use std::net::TcpStream;
+
+pub struct RemoteClient {
+    host: String,
+    token: String,
+    allow_non_loopback: bool,
+}
+
+impl RemoteClient {
+    pub fn new(url: &str, token: &str) -> Result<Self, WError> {
+        let host = url
+            .strip_prefix("http://")
+            .ok_or(WError::UsageError(
+                "The signing server URL must start with http://",
+            ))?
+            .trim_end_matches('/');
+        if host.is_empty() || host.contains('/') {
+            return Err(WError::UsageError("Invalid signing server URL"));
+        }
+        Ok(RemoteClient {
+            host: host.to_string(),
+            token: token.to_string(),
+            allow_non_loopback: false,
+        })
+    }
+
+    /// Connects to servers on non-loopback addresses. Requests, including the
+    /// token, are not encrypted, so the network must be trusted.
+    pub fn allow_non_loopback(mut self, allow: bool) -> Self {
+        self.allow_non_loopback = allow;
+        self
+    }
+
+    fn request(
+        &self,
+        method: &str,
+        path: &str,
+        headers: &[(&str, String)],
+        body: &[u8],
+    ) -> Result<Message, WError> {
+        if !self.allow_non_loopback {
+            check_loopback(&self.host)?;
+        }
+        let stream = TcpStream::connect(&self.host)?;
+        let mut all_headers = vec![
+            ("Host", self.host.clone()),
+            ("Authorization", format!("Bearer {}", self.token)),
+        ];
+        all_headers.extend_from_slice(headers);
+        write_message(
+            &stream,
+            &format!("{} {} HTTP/1.1", method, path),
+            &all_headers,
+            body,
+        )?;
+        let response = read_message(&stream)?;
+        let status = response.start_line.split_whitespace().nth(1).unwrap_or("");
+        if status != "200" {
+            return Err(WError::RemoteError(format!(
+                "{}: {}",
+                response.start_line,
+                String::from_utf8_lossy(&response.body).trim()
+            )));
+        }
+        Ok(response)
+    }
+
+    /// Returns the signature scheme of the server, and its public key if the server reports it.
+    pub fn info(&self) -> Result<(u32, Option<PublicKey>), WError> {
+        let response = self.request("GET", PATH_INFO, &[], &[])?;
+        let alg_id = response
+            .header(HEADER_ALG_ID)
+            .and_then(|alg_id| u32::from_str_radix(alg_id, 16).ok())
+            .ok_or_else(|| WError::RemoteError("Missing algorithm identifier".to_string()))?;
+        let pk = match response.header(HEADER_PUBLIC_KEY) {
+            None => None,
+            Some(pk) => {
+                let pk = Hex::decode_to_vec(pk, None)
+                    .map_err(|_| WError::RemoteError("Invalid public key encoding".to_string()))?;
+                Some(PublicKey::from_bytes(&pk)?)
+            }
+        };
+        if pk.as_ref().is_some_and(|pk| pk.alg_id() != alg_id) {
+            return Err(WError::RemoteError(
+                "The public key doesn't match the algorithm".to_string(),
+            ));
+        }
+        Ok((alg_id, pk))
+    }
+
+    pub fn sign_digest(&self, alg_id: u32, prehashed: &[u8]) -> Result<Vec<u8>, WError> {
+        let headers = [(HEADER_ALG_ID, format!("{:08x}", alg_id))];
+        let response = self.request("POST", PATH_SIGN_DIGEST, &headers, prehashed)?;
+        Ok(response.body)
+    }
+
+    pub fn sign_module(
+        &self,
+        module_bytes: &[u8],
+        ad: &AdditionalData,
+        hash_alg: HashAlg,
+        context: Option<&str>,
+        custom_section: Option<(&str, &SectionSelection, bool)>,
+        symbol_name: &str,
+    ) -> Result<Vec<u8>, WError> {
+        check_context(context)?;
+        let mut headers = vec![(HEADER_HASH, hash_alg.name().to_string())];
+        let mut sections_bytes = vec![];
+        match custom_section {
+            None => headers.push((HEADER_SYMBOL, symbol_name.to_string())),
+            Some((n, sections, merkle)) => {
+                headers.push((HEADER_CUSTOM_SECTION, n.to_string()));
+                if merkle {
+                    headers.push((HEADER_MERKLE, "1".to_string()));
+                }
+                sections_bytes = sections.to_bytes();
+            }
+        }
+        if !ad.is_empty() {
+            headers.push((HEADER_AD_EMBEDDING, ad.embedding().name().to_string()));
+        }
+        let body = encode_sign_module_body(
+            ad.as_bytes(),
+            context.unwrap_or_default().as_bytes(),
+            &sections_bytes,
+            module_bytes,
+        );
+        let response = self.request("POST", PATH_SIGN_MODULE, &headers, &body)?;
+        Ok(response.body)
+    }
+}
+
+pub struct RemoteSigner {
+    client: RemoteClient,
+    alg_id: u32,
+    pk: Option<PublicKey>,
+}
+
+impl RemoteSigner {
+    pub fn new(client: RemoteClient) -> Result<Self, WError> {
+        let (alg_id, pk) = client.info()?;
+        alg_from_id(alg_id)?;
+        Ok(RemoteSigner { client, alg_id, pk })
+    }
+}
+
+impl Signer for RemoteSigner {
+    fn alg_id(&self) -> u32 {
+        self.alg_id
+    }
+
+    fn sign_prehashed(&self, prehashed: &[u8]) -> Result<Vec<u8>, WError> {
+        self.client.sign_digest(self.alg_id, prehashed)
+    }
+
+    fn public_key(&self) -> Option<PublicKey> {
+        self.pk.clone()
+    }
+}
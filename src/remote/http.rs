@@ -0,0 +1,152 @@
+use super::super::errors::*;
+use std::io::prelude::*;
+use std::io::{self, BufReader};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+pub const MAX_BODY_LENGTH: usize = 64 * 1024 * 1024;
+const MAX_HEADER_LENGTH: usize = 8192;
+const MESSAGE_TIMEOUT: Duration = Duration::from_secs(60);
+
+pub struct Message {
+    pub start_line: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Message {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// A stream that must be read before a deadline, however slowly data arrives.
+struct DeadlineReader<'t> {
+    stream: &'t TcpStream,
+    deadline: Instant,
+}
+
+impl Read for DeadlineReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "HTTP message timeout",
+            ));
+        }
+        self.stream.set_read_timeout(Some(remaining))?;
+        let mut stream = self.stream;
+        stream.read(buf)
+    }
+}
+
+/// Reads a message in two steps, so that its headers can be checked before
+/// the body is allocated and read.
+pub struct MessageReader<'t> {
+    reader: BufReader<DeadlineReader<'t>>,
+}
+
+impl<'t> MessageReader<'t> {
+    pub fn new(stream: &'t TcpStream) -> Self {
+        MessageReader {
+            reader: BufReader::new(DeadlineReader {
+                stream,
+                deadline: Instant::now() + MESSAGE_TIMEOUT,
+            }),
+        }
+    }
+
+    /// Reads the start line and the headers. The body is left empty.
+    pub fn read_head(&mut self) -> Result<Message, WError> {
+        let mut lines = vec![];
+        let mut header_length = 0;
+        loop {
+            let mut line = String::new();
+            if self
+                .reader
+                .by_ref()
+                .take((MAX_HEADER_LENGTH + 1 - header_length) as u64)
+                .read_line(&mut line)?
+                == 0
+            {
+                return Err(WError::ParseError("Truncated HTTP message".to_string()));
+            }
+            header_length += line.len();
+            if header_length > MAX_HEADER_LENGTH {
+                return Err(WError::ParseError("HTTP headers too long".to_string()));
+            }
+            let line = line.trim_end_matches(&['\r', '\n'][..]).to_string();
+            if line.is_empty() {
+                break;
+            }
+            lines.push(line);
+        }
+        if lines.is_empty() {
+            return Err(WError::ParseError("Empty HTTP message".to_string()));
+        }
+        let start_line = lines.remove(0);
+        let mut headers = vec![];
+        for line in lines {
+            let i = line
+                .find(':')
+                .ok_or_else(|| WError::ParseError("Invalid HTTP header".to_string()))?;
+            headers.push((
+                line[..i].trim().to_string(),
+                line[i + 1..].trim().to_string(),
+            ));
+        }
+        Ok(Message {
+            start_line,
+            headers,
+            body: vec![],
+        })
+    }
+
+    /// Reads the body announced by the headers of `message`.
+    pub fn read_body(&mut self, message: &mut Message) -> Result<(), WError> {
+        let body_length = match message.header("Content-Length") {
+            None => 0,
+            Some(len) => len
+                .parse::<usize>()
+                .map_err(|_| WError::ParseError("Invalid Content-Length".to_string()))?,
+        };
+        if body_length > MAX_BODY_LENGTH {
+            return Err(WError::ParseError("HTTP body too large".to_string()));
+        }
+        message.body = vec![0u8; body_length];
+        self.reader.read_exact(&mut message.body)?;
+        Ok(())
+    }
+}
+
+pub fn read_message(stream: &TcpStream) -> Result<Message, WError> {
+    let mut reader = MessageReader::new(stream);
+    let mut message = reader.read_head()?;
+    reader.read_body(&mut message)?;
+    Ok(message)
+}
+
+pub fn write_message(
+    mut stream: &TcpStream,
+    start_line: &str,
+    headers: &[(&str, String)],
+    body: &[u8],
+) -> Result<(), WError> {
+    stream.set_write_timeout(Some(MESSAGE_TIMEOUT))?;
+    let mut head = format!("{}\r\n", start_line);
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str(&format!(
+        "Content-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    ));
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(body)?;
+    stream.flush()?;
+    Ok(())
+}
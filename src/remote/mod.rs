@@ -0,0 +1,71 @@
+//! A remote signing server and its client.
+//!
+//! Requests are plain HTTP/1.1, authenticated with a bearer token. Since
+//! nothing is encrypted, both ends only accept loopback addresses by default.
+//!
+//! Remote hosts, such as CI runners, can reach a server in two ways:
+//!
+//! - through a tunnel, with the server listening on a loopback address, e.g.
+//!   `ssh -N -L 8780:127.0.0.1:8780 signing-host` on the runner;
+//! - over a trusted network, such as a VPN, or through TLS tunnels (e.g.
+//!   stunnel on both hosts), with `allow_non_loopback` enabled on both ends.
+
+mod client;
+mod http;
+mod server;
+
+pub use self::client::*;
+pub use self::server::*;
+
+use super::errors::*;
+use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
+use std::net::ToSocketAddrs;
+
+const PATH_INFO: &str = "/v1/info";
+const PATH_SIGN_DIGEST: &str = "/v1/sign-digest";
+const PATH_SIGN_MODULE: &str = "/v1/sign-module";
+
+const HEADER_ALG_ID: &str = "X-Wasmsign-Alg-Id";
+const HEADER_PUBLIC_KEY: &str = "X-Wasmsign-Public-Key";
+const HEADER_AD_EMBEDDING: &str = "X-Wasmsign-Ad-Embedding";
+const HEADER_HASH: &str = "X-Wasmsign-Hash";
+const HEADER_MERKLE: &str = "X-Wasmsign-Merkle";
+const HEADER_CUSTOM_SECTION: &str = "X-Wasmsign-Custom-Section";
+const HEADER_SYMBOL: &str = "X-Wasmsign-Symbol";
+
+fn check_loopback(address: impl ToSocketAddrs) -> Result<(), WError> {
+    let mut addresses = address.to_socket_addrs()?.peekable();
+    if addresses.peek().is_none() || !addresses.all(|address| address.ip().is_loopback()) {
+        return Err(WError::UsageError(
+            "The signing server can only be used over a loopback address",
+        ));
+    }
+    Ok(())
+}
+
+/// The body of a module signing request: the additional data, the context
+/// and the section selection, each prefixed with its length, then the module.
+fn encode_sign_module_body(ad: &[u8], context: &[u8], sections: &[u8], module: &[u8]) -> Vec<u8> {
+    let mut body = vec![];
+    for field in [ad, context, sections] {
+        body.write_u32::<LittleEndian>(field.len() as u32)
+            .expect("Unable to serialize");
+        body.extend_from_slice(field);
+    }
+    body.extend_from_slice(module);
+    body
+}
+
+fn decode_sign_module_body(mut body: &[u8]) -> Result<[&[u8]; 4], WError> {
+    let mut fields = [&[][..]; 4];
+    for field in fields.iter_mut().take(3) {
+        if body.len() < 4 || body.len() - 4 < LittleEndian::read_u32(body) as usize {
+            return Err(WError::ParseError("Truncated signing request".to_string()));
+        }
+        let len = LittleEndian::read_u32(body) as usize;
+        *field = &body[4..4 + len];
+        body = &body[4 + len..];
+    }
+    fields[3] = body;
+    Ok(fields)
+}
@@ -0,0 +1,234 @@
+use super::super::errors::*;
+use super::super::signature::*;
+use super::http::*;
+use super::*;
+use ct_codecs::{Encoder, Hex};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+use std::thread;
+
+pub struct Server {
+    signer: Box<dyn Signer>,
+    token: String,
+    allow_non_loopback: bool,
+}
+
+/// Number of connections handled concurrently.
+const WORKERS: usize = 4;
+
+struct Response {
+    status_line: &'static str,
+    headers: Vec<(&'static str, String)>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn new(status_line: &'static str, body: Vec<u8>) -> Self {
+        Response {
+            status_line,
+            headers: vec![],
+            body,
+        }
+    }
+}
+
+/// A request read by a worker, and the channel its response is sent back on.
+type Job = (Message, Sender<Response>);
+
+fn authorized(expected: &str, request: &Message) -> bool {
+    let token = match request
+        .header("Authorization")
+        .and_then(|v| v.strip_prefix("Bearer "))
+    {
+        None => return false,
+        Some(token) => token.as_bytes(),
+    };
+    let expected = expected.as_bytes();
+    if token.len() != expected.len() {
+        return false;
+    }
+    token.iter().zip(expected).fold(0, |d, (a, b)| d | (a ^ b)) == 0
+}
+
+/// Reads a request, and only reads its body if it is authorized.
+fn read_request(stream: &TcpStream, token: &str) -> Result<Option<Message>, WError> {
+    let mut reader = MessageReader::new(stream);
+    let mut request = reader.read_head()?;
+    if !authorized(token, &request) {
+        return Ok(None);
+    }
+    reader.read_body(&mut request)?;
+    Ok(Some(request))
+}
+
+/// Accepts connections and reads requests, so that slow clients only hold up
+/// one worker. Stops when accepting a connection fails, or when the server is
+/// gone.
+fn work(listener: TcpListener, token: Arc<str>, jobs: Sender<Result<Job, WError>>) {
+    loop {
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                let _ = jobs.send(Err(e.into()));
+                return;
+            }
+        };
+        let response = match read_request(&stream, &token) {
+            Err(e) => Response::new("HTTP/1.1 400 Bad Request", e.to_string().into_bytes()),
+            Ok(None) => Response::new("HTTP/1.1 401 Unauthorized", b"Unauthorized".to_vec()),
+            Ok(Some(request)) => {
+                let (response_tx, response) = mpsc::channel();
+                if jobs.send(Ok((request, response_tx))).is_err() {
+                    return;
+                }
+                match response.recv() {
+                    Ok(response) => response,
+                    Err(_) => return,
+                }
+            }
+        };
+        let _ = write_message(
+            &stream,
+            response.status_line,
+            &response.headers,
+            &response.body,
+        );
+    }
+}
+
+impl Server {
+    pub fn new(signer: Box<dyn Signer>, token: &str) -> Result<Self, WError> {
+        if token.len() < 16 {
+            return Err(WError::UsageError(
+                "The authentication token must be at least 16 characters long",
+            ));
+        }
+        Ok(Server {
+            signer,
+            token: token.to_string(),
+            allow_non_loopback: false,
+        })
+    }
+
+    /// Accepts connections from non-loopback addresses. Requests are not
+    /// encrypted, so the network must be trusted, or traffic must go through
+    /// a tunnel.
+    pub fn allow_non_loopback(mut self, allow: bool) -> Self {
+        self.allow_non_loopback = allow;
+        self
+    }
+
+    /// Serves requests until accepting a connection fails. At most `WORKERS`
+    /// connections are handled concurrently, and signatures are computed one
+    /// at a time, on the current thread.
+    pub fn serve(&self, listener: &TcpListener) -> Result<(), WError> {
+        if !self.allow_non_loopback {
+            check_loopback(listener.local_addr()?)?;
+        }
+        let token: Arc<str> = Arc::from(self.token.as_str());
+        let (jobs_tx, jobs) = mpsc::channel();
+        for _ in 0..WORKERS {
+            let (listener, token, jobs_tx) =
+                (listener.try_clone()?, token.clone(), jobs_tx.clone());
+            thread::spawn(move || work(listener, token, jobs_tx));
+        }
+        drop(jobs_tx);
+        for job in jobs {
+            let (request, response_tx) = job?;
+            let response = self.handle(&request).unwrap_or_else(|e| {
+                Response::new("HTTP/1.1 400 Bad Request", e.to_string().into_bytes())
+            });
+            let _ = response_tx.send(response);
+        }
+        Ok(())
+    }
+
+    fn handle(&self, request: &Message) -> Result<Response, WError> {
+        let mut parts = request.start_line.split_whitespace();
+        let (method, path) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+        match (method, path) {
+            ("GET", PATH_INFO) => {
+                let mut response = Response::new("HTTP/1.1 200 OK", vec![]);
+                response
+                    .headers
+                    .push((HEADER_ALG_ID, format!("{:08x}", self.signer.alg_id())));
+                if let Some(pk) = self.signer.public_key() {
+                    let pk = Hex::encode_to_string(pk.to_bytes())
+                        .map_err(|_| WError::InternalError("hex"))?;
+                    response.headers.push((HEADER_PUBLIC_KEY, pk));
+                }
+                Ok(response)
+            }
+            ("POST", PATH_SIGN_DIGEST) => {
+                let alg_id = request
+                    .header(HEADER_ALG_ID)
+                    .and_then(|alg_id| u32::from_str_radix(alg_id, 16).ok());
+                if alg_id != Some(self.signer.alg_id()) {
+                    return Err(WError::UsageError("Unexpected signature algorithm"));
+                }
+                let raw = self.signer.sign_prehashed(&request.body)?;
+                Ok(Response::new("HTTP/1.1 200 OK", raw))
+            }
+            ("POST", PATH_SIGN_MODULE) => {
+                let [ad, context, sections, module_bytes] = decode_sign_module_body(&request.body)?;
+                let ad = AdditionalData::new(ad);
+                let ad = match request.header(HEADER_AD_EMBEDDING) {
+                    None => ad,
+                    Some(embedding) => ad.embed(AdEmbedding::from_name(embedding)?),
+                };
+                let ad = &ad;
+                let hash_alg = match request.header(HEADER_HASH) {
+                    None => HashAlg::default(),
+                    Some(name) => HashAlg::from_name(name)?,
+                };
+                let context =
+                    match context.is_empty() {
+                        true => None,
+                        false => Some(std::str::from_utf8(context).map_err(|_| {
+                            WError::ParseError("Invalid context encoding".to_string())
+                        })?),
+                    };
+                let signer = self.signer.as_ref();
+                let signed_module_bytes = match (
+                    request.header(HEADER_CUSTOM_SECTION),
+                    request.header(HEADER_SYMBOL),
+                ) {
+                    (Some(n), _) => {
+                        let sections = match sections.is_empty() {
+                            true => SectionSelection::All,
+                            false => SectionSelection::from_bytes(sections)?,
+                        };
+                        let sign_custom_section = match request.header(HEADER_MERKLE) {
+                            Some("1") => crate::sign_custom_section_merkle,
+                            _ => crate::sign_custom_section,
+                        };
+                        sign_custom_section(
+                            module_bytes,
+                            signer,
+                            ad,
+                            hash_alg,
+                            context,
+                            &sections,
+                            n,
+                        )?
+                    }
+                    (None, Some(n)) => crate::sign(module_bytes, signer, ad, hash_alg, context, n)?,
+                    (None, None) => crate::sign(
+                        module_bytes,
+                        signer,
+                        ad,
+                        hash_alg,
+                        context,
+                        crate::DEFAULT_SYMBOL_NAME,
+                    )?,
+                };
+                Ok(Response::new("HTTP/1.1 200 OK", signed_module_bytes))
+            }
+            _ => Ok(Response::new(
+                "HTTP/1.1 404 Not Found",
+                b"Not found".to_vec(),
+            )),
+        }
+    }
+}
@@ -0,0 +1,151 @@
+use super::super::errors::*;
+use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
+
+const FIELDS_MAGIC: &[u8] = b"wasmsign-ad-fields-v1\0";
+
+/// Additional data: either an opaque byte string, or labeled fields with a
+/// canonical encoding that doesn't depend on the order they were given in.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct AdditionalData {
+    bytes: Vec<u8>,
+    embedding: AdEmbedding,
+    from_signature: bool,
+}
+
+/// What the signature records about the additional data.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AdEmbedding {
+    #[default]
+    None,
+    Data,
+    Hash,
+}
+
+impl AdEmbedding {
+    pub fn from_name(name: &str) -> Result<Self, WError> {
+        match name {
+            "none" => Ok(AdEmbedding::None),
+            "data" => Ok(AdEmbedding::Data),
+            "hash" => Ok(AdEmbedding::Hash),
+            _ => Err(WError::UsageError(
+                "Unsupported additional data embedding (none, data or hash)",
+            )),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AdEmbedding::None => "none",
+            AdEmbedding::Data => "data",
+            AdEmbedding::Hash => "hash",
+        }
+    }
+}
+
+impl AdditionalData {
+    pub fn new(bytes: impl Into<Vec<u8>>) -> Self {
+        AdditionalData {
+            bytes: bytes.into(),
+            embedding: AdEmbedding::None,
+            from_signature: false,
+        }
+    }
+
+    /// Verify with the additional data embedded in the signature, if any.
+    pub fn from_signature() -> Self {
+        AdditionalData {
+            from_signature: true,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_from_signature(&self) -> bool {
+        self.from_signature
+    }
+
+    pub fn embed(mut self, embedding: AdEmbedding) -> Self {
+        self.embedding = embedding;
+        self
+    }
+
+    pub fn embedding(&self) -> AdEmbedding {
+        self.embedding
+    }
+
+    pub fn from_fields<L: AsRef<str>, V: AsRef<[u8]>>(
+        fields: &[(L, V)],
+    ) -> Result<Self, WError> {
+        let mut fields: Vec<(&str, &[u8])> = fields
+            .iter()
+            .map(|(label, value)| (label.as_ref(), value.as_ref()))
+            .collect();
+        fields.sort_by(|a, b| a.0.cmp(b.0));
+        if fields.iter().any(|(label, _)| label.is_empty()) {
+            return Err(WError::UsageError("Additional data labels cannot be empty"));
+        }
+        if fields.windows(2).any(|w| w[0].0 == w[1].0) {
+            return Err(WError::UsageError("Duplicate additional data label"));
+        }
+        let mut bytes = FIELDS_MAGIC.to_vec();
+        bytes.write_u32::<LittleEndian>(fields.len() as u32)?;
+        for (label, value) in fields {
+            bytes.write_u32::<LittleEndian>(label.len() as u32)?;
+            bytes.extend_from_slice(label.as_bytes());
+            bytes.write_u32::<LittleEndian>(value.len() as u32)?;
+            bytes.extend_from_slice(value);
+        }
+        Ok(AdditionalData::new(bytes))
+    }
+
+    pub fn fields(&self) -> Option<Vec<(String, Vec<u8>)>> {
+        fn read_chunk<'t>(bytes: &mut &'t [u8]) -> Option<&'t [u8]> {
+            if bytes.len() < 4 {
+                return None;
+            }
+            let len = LittleEndian::read_u32(&bytes[..4]) as usize;
+            if bytes.len() - 4 < len {
+                return None;
+            }
+            let chunk = &bytes[4..4 + len];
+            *bytes = &bytes[4 + len..];
+            Some(chunk)
+        }
+
+        let mut bytes = self.bytes.strip_prefix(FIELDS_MAGIC)?;
+        if bytes.len() < 4 {
+            return None;
+        }
+        let count = LittleEndian::read_u32(&bytes[..4]);
+        bytes = &bytes[4..];
+        let mut fields = vec![];
+        for _ in 0..count {
+            let label = String::from_utf8(read_chunk(&mut bytes)?.to_vec()).ok()?;
+            let value = read_chunk(&mut bytes)?.to_vec();
+            fields.push((label, value));
+        }
+        if !bytes.is_empty() {
+            return None;
+        }
+        Some(fields)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+}
+
+impl From<&[u8]> for AdditionalData {
+    fn from(bytes: &[u8]) -> Self {
+        AdditionalData::new(bytes)
+    }
+}
+
+impl From<Option<&[u8]>> for AdditionalData {
+    fn from(bytes: Option<&[u8]>) -> Self {
+        AdditionalData::new(bytes.unwrap_or_default())
+    }
+}
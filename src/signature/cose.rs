@@ -0,0 +1,18 @@
+use super::*;
+use coset::iana;
+
+pub fn cose_algorithm(alg_id: u32) -> Result<iana::Algorithm, WError> {
+    match alg_id {
+        eddsa::ALG_ID => Ok(iana::Algorithm::EdDSA),
+        _ => Err(WError::SignatureError(
+            "No COSE identifier for this signature scheme",
+        )),
+    }
+}
+
+pub fn alg_id_from_cose(alg: &coset::Algorithm) -> Result<u32, WError> {
+    match alg {
+        coset::Algorithm::Assigned(iana::Algorithm::EdDSA) => Ok(eddsa::ALG_ID),
+        _ => Err(WError::SignatureError("Unsupported COSE algorithm")),
+    }
+}
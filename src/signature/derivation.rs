@@ -0,0 +1,65 @@
+use super::*;
+use hmac_sha512::HMAC;
+
+const SLIP10_ED25519_CURVE: &[u8] = b"ed25519 seed";
+const HARDENED: u32 = 0x8000_0000;
+const PASSPHRASE_SALT: &[u8] = b"wasmsign passphrase";
+const PASSPHRASE_ITERATIONS: u32 = 200_000;
+
+pub fn seed_from_passphrase(passphrase: &[u8]) -> Vec<u8> {
+    let mut salt = PASSPHRASE_SALT.to_vec();
+    salt.write_u32::<BigEndian>(1).expect("Unable to serialize");
+    let mut u = HMAC::mac(&salt, passphrase);
+    let mut seed = u;
+    for _ in 1..PASSPHRASE_ITERATIONS {
+        u = HMAC::mac(u, passphrase);
+        for (s, x) in seed.iter_mut().zip(u.iter()) {
+            *s ^= x;
+        }
+    }
+    seed.to_vec()
+}
+
+pub fn parse_derivation_path(path: &str) -> Result<Vec<u32>, WError> {
+    let mut components = path.split('/');
+    if components.next() != Some("m") {
+        return Err(WError::UsageError("Derivation paths must start with m"));
+    }
+    components
+        .map(|component| {
+            let index = component
+                .strip_suffix('\'')
+                .or_else(|| component.strip_suffix('h'))
+                .ok_or(WError::UsageError(
+                    "Only hardened derivation (e.g. m/0'/1') is supported",
+                ))?;
+            let index: u32 = index
+                .parse()
+                .map_err(|_| WError::UsageError("Invalid derivation path"))?;
+            if index >= HARDENED {
+                return Err(WError::UsageError("Derivation index is too large"));
+            }
+            Ok(index | HARDENED)
+        })
+        .collect()
+}
+
+pub fn derive_seed(master_seed: &[u8], path: &str) -> Result<Vec<u8>, WError> {
+    if master_seed.len() < 16 || master_seed.len() > 64 {
+        return Err(WError::UsageError(
+            "The master seed must be between 16 and 64 bytes long",
+        ));
+    }
+    let indices = parse_derivation_path(path)?;
+    let i = HMAC::mac(master_seed, SLIP10_ED25519_CURVE);
+    let (mut key, mut chain_code) = (i[..32].to_vec(), i[32..].to_vec());
+    for index in indices {
+        let mut data = vec![0u8];
+        data.extend_from_slice(&key);
+        data.write_u32::<BigEndian>(index)?;
+        let i = HMAC::mac(&data, &chain_code);
+        key = i[..32].to_vec();
+        chain_code = i[32..].to_vec();
+    }
+    Ok(key)
+}
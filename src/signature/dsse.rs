@@ -0,0 +1,84 @@
+use super::*;
+use ct_codecs::{Base64, Decoder, Encoder, Hex};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Envelope {
+    payload_type: String,
+    payload: String,
+    signatures: Vec<EnvelopeSignature>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct EnvelopeSignature {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    keyid: String,
+    sig: String,
+}
+
+/// The pre-authentication encoding of a payload.
+pub fn pae(payload_type: &str, payload: &[u8]) -> Vec<u8> {
+    let mut encoded = format!(
+        "DSSEv1 {} {} {} ",
+        payload_type.len(),
+        payload_type,
+        payload.len()
+    )
+    .into_bytes();
+    encoded.extend_from_slice(payload);
+    encoded
+}
+
+fn parse_error<E: std::fmt::Display>(e: E) -> WError {
+    WError::ParseError(format!("Invalid DSSE envelope: {}", e))
+}
+
+pub fn sign(
+    payload_type: &str,
+    payload: &[u8],
+    signer: &dyn Signer,
+    key_id: Option<&[u8]>,
+) -> Result<Vec<u8>, WError> {
+    let sig = signer.sign_prehashed(&pae(payload_type, payload))?;
+    let keyid = match key_id {
+        None => String::new(),
+        Some(key_id) => Hex::encode_to_string(key_id).map_err(|_| WError::InternalError("hex"))?,
+    };
+    let envelope = Envelope {
+        payload_type: payload_type.to_string(),
+        payload: Base64::encode_to_string(payload).map_err(|_| WError::InternalError("base64"))?,
+        signatures: vec![EnvelopeSignature {
+            keyid,
+            sig: Base64::encode_to_string(sig).map_err(|_| WError::InternalError("base64"))?,
+        }],
+    };
+    serde_json::to_vec_pretty(&envelope).map_err(|_| WError::InternalError("json"))
+}
+
+/// Returns the payload type and payload of an envelope, without verifying it.
+pub fn open(envelope: &[u8]) -> Result<(String, Vec<u8>), WError> {
+    let envelope: Envelope = serde_json::from_slice(envelope).map_err(parse_error)?;
+    let payload = Base64::decode_to_vec(&envelope.payload, None).map_err(parse_error)?;
+    Ok((envelope.payload_type, payload))
+}
+
+/// Verifies an envelope, and returns its payload type and payload.
+pub fn verify(envelope: &[u8], pk: &PublicKey) -> Result<(String, Vec<u8>), WError> {
+    let envelope: Envelope = serde_json::from_slice(envelope).map_err(parse_error)?;
+    let payload = Base64::decode_to_vec(&envelope.payload, None).map_err(parse_error)?;
+    let signature_alg = pk.to_alg()?;
+    let pae = pae(&envelope.payload_type, &payload);
+    let verified = envelope.signatures.iter().any(|signature| {
+        match Base64::decode_to_vec(&signature.sig, None) {
+            Ok(raw) => signature_alg
+                .verify_prehashed(&pae, pk.raw(), &Signature::new(pk.alg_id(), raw))
+                .is_ok(),
+            Err(_) => false,
+        }
+    });
+    match verified {
+        true => Ok((envelope.payload_type, payload)),
+        false => Err(WError::SignatureError("No valid DSSE signature for this key")),
+    }
+}
@@ -0,0 +1,158 @@
+//! ECDSA over the NIST P-256 and P-384 curves.
+//!
+//! The prehashed message is signed as-is, with SHA-256 (P-256) or SHA-384
+//! (P-384) as the message digest. Signatures are the fixed-length encoding of
+//! `r` and `s`, public keys are uncompressed SEC1 points, and secret keys are
+//! PKCS#8 documents. Nonces are always random.
+
+use super::*;
+use ring::rand::SystemRandom;
+use ring::signature::{self as ring_signature, EcdsaKeyPair, KeyPair as _, UnparsedPublicKey};
+
+pub const P256_ALG_ID: u32 = 0x0000_0003;
+pub const P384_ALG_ID: u32 = 0x0000_0004;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ecdsa {
+    P256,
+    P384,
+}
+
+impl Ecdsa {
+    pub fn from_alg_id(alg_id: u32) -> Option<Self> {
+        match alg_id {
+            P256_ALG_ID => Some(Ecdsa::P256),
+            P384_ALG_ID => Some(Ecdsa::P384),
+            _ => None,
+        }
+    }
+
+    /// Length of a coordinate, or of a scalar, in bytes.
+    pub fn field_length(&self) -> usize {
+        match self {
+            Ecdsa::P256 => 32,
+            Ecdsa::P384 => 48,
+        }
+    }
+
+    /// The digest of the prehashed message, as signed by the raw ECDSA operation.
+    pub fn digest(&self, prehashed: &[u8]) -> Vec<u8> {
+        let alg = match self {
+            Ecdsa::P256 => &ring::digest::SHA256,
+            Ecdsa::P384 => &ring::digest::SHA384,
+        };
+        ring::digest::digest(alg, prehashed).as_ref().to_vec()
+    }
+
+    fn signing_alg(&self) -> &'static ring_signature::EcdsaSigningAlgorithm {
+        match self {
+            Ecdsa::P256 => &ring_signature::ECDSA_P256_SHA256_FIXED_SIGNING,
+            Ecdsa::P384 => &ring_signature::ECDSA_P384_SHA384_FIXED_SIGNING,
+        }
+    }
+
+    fn verification_alg(&self) -> &'static ring_signature::EcdsaVerificationAlgorithm {
+        match self {
+            Ecdsa::P256 => &ring_signature::ECDSA_P256_SHA256_FIXED,
+            Ecdsa::P384 => &ring_signature::ECDSA_P384_SHA384_FIXED,
+        }
+    }
+
+    fn ring_key_pair(&self, sk: &[u8]) -> Result<EcdsaKeyPair, WError> {
+        EcdsaKeyPair::from_pkcs8(self.signing_alg(), sk, &SystemRandom::new())
+            .map_err(|_| WError::ParseError("Invalid ECDSA secret key".to_string()))
+    }
+}
+
+impl SignatureAlg for Ecdsa {
+    fn alg_id(&self) -> u32 {
+        match self {
+            Ecdsa::P256 => P256_ALG_ID,
+            Ecdsa::P384 => P384_ALG_ID,
+        }
+    }
+
+    fn raw_signature_length(&self) -> usize {
+        self.field_length() * 2
+    }
+
+    fn keygen(&self) -> KeyPair {
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(self.signing_alg(), &SystemRandom::new())
+            .expect("ECDSA key generation");
+        let sk = SecretKey::new(self.alg_id(), pkcs8.as_ref().to_vec());
+        let pk = self.public_key(&sk).expect("ECDSA key generation");
+        KeyPair::new(self.alg_id(), pk, sk)
+    }
+
+    fn keygen_from_seed(&self, _seed: &[u8]) -> Result<KeyPair, WError> {
+        Err(WError::UsageError(
+            "ECDSA keys cannot be generated from a seed",
+        ))
+    }
+
+    fn public_key(&self, sk: &SecretKey) -> Result<PublicKey, WError> {
+        let kp = self.ring_key_pair(sk.raw())?;
+        Ok(PublicKey::new(
+            self.alg_id(),
+            kp.public_key().as_ref().to_vec(),
+        ))
+    }
+
+    fn sign(
+        &self,
+        data: &[u8],
+        ad: &AdditionalData,
+        hash_alg: HashAlg,
+        context: Option<&str>,
+        key_pair: &KeyPair,
+    ) -> Result<Signature, WError> {
+        let prehashed = self.prehash(data, ad, hash_alg, context)?;
+        let raw = self.sign_prehashed(&prehashed, key_pair)?;
+        Ok(Signature::new(self.alg_id(), raw)
+            .with_hash_alg(hash_alg)
+            .with_context(context))
+    }
+
+    fn sign_prehashed(&self, prehashed: &[u8], key_pair: &KeyPair) -> Result<Vec<u8>, WError> {
+        let kp = self.ring_key_pair(key_pair.sk.raw())?;
+        let raw = kp
+            .sign(&SystemRandom::new(), prehashed)
+            .map_err(|_| WError::SignatureError("ECDSA signing failed"))?;
+        Ok(raw.as_ref().to_vec())
+    }
+
+    fn verify(
+        &self,
+        data: &[u8],
+        ad: &AdditionalData,
+        pk: &[u8],
+        signature: &Signature,
+    ) -> Result<(), WError> {
+        let prehashed = self.prehash(data, ad, signature.hash_alg(), signature.context())?;
+        self.verify_prehashed(&prehashed, pk, signature)
+    }
+
+    fn verify_prehashed(
+        &self,
+        prehashed: &[u8],
+        pk: &[u8],
+        signature: &Signature,
+    ) -> Result<(), WError> {
+        if signature.alg_id() != self.alg_id() {
+            return Err(WError::SignatureError("Unexpected signature scheme"));
+        }
+        UnparsedPublicKey::new(self.verification_alg(), pk)
+            .verify(prehashed, signature.raw())
+            .map_err(|_| WError::SignatureError("Signature verification failed"))
+    }
+
+    fn prehash(
+        &self,
+        data: &[u8],
+        ad: &AdditionalData,
+        hash_alg: HashAlg,
+        context: Option<&str>,
+    ) -> Result<Vec<u8>, WError> {
+        eddsa::prehash(data, ad, hash_alg, context)
+    }
+}
@@ -1,9 +1,9 @@
 use super::*;
 use byteorder::{LittleEndian, WriteBytesExt};
-use hmac_sha512::Hash;
-use std::u32;
 
-const CONTEXT: &[u8] = b"WasmSignature";
+// Values of the AD length field that introduce a parameter instead
+const CUSTOM_CONTEXT_MARKER: u32 = u32::MAX;
+const HASH_ALG_MARKER: u32 = u32::MAX - 1;
 pub const ALG_ID: u32 = 0x0000_0002;
 
 pub struct EdDSA;
@@ -19,56 +19,121 @@ impl SignatureAlg for EdDSA {
 
     fn keygen(&self) -> KeyPair {
         let kp = ed25519_compact::KeyPair::from_seed(ed25519_compact::Seed::default());
-        KeyPair {
-            alg_id: ALG_ID,
-            pk: PublicKey::new(ALG_ID, kp.pk.to_vec()),
-            sk: SecretKey::new(ALG_ID, kp.sk.to_vec()),
-        }
+        KeyPair::new(
+            ALG_ID,
+            PublicKey::new(ALG_ID, kp.pk.to_vec()),
+            SecretKey::new(ALG_ID, kp.sk.to_vec()),
+        )
+    }
+
+    fn keygen_from_seed(&self, seed: &[u8]) -> Result<KeyPair, WError> {
+        let seed = ed25519_compact::Seed::from_slice(seed)
+            .map_err(|_| WError::UsageError("Ed25519 seeds must be 32 bytes long"))?;
+        let kp = ed25519_compact::KeyPair::from_seed(seed);
+        Ok(KeyPair::new(
+            ALG_ID,
+            PublicKey::new(ALG_ID, kp.pk.to_vec()),
+            SecretKey::new(ALG_ID, kp.sk.to_vec()),
+        ))
+    }
+
+    fn public_key(&self, sk: &SecretKey) -> Result<PublicKey, WError> {
+        let xsk = ed25519_compact::SecretKey::from_slice(sk.raw())?;
+        Ok(PublicKey::new(ALG_ID, xsk.public_key().to_vec()))
     }
 
     fn sign(
         &self,
         data: &[u8],
-        ad: Option<&[u8]>,
+        ad: &AdditionalData,
+        hash_alg: HashAlg,
+        context: Option<&str>,
         key_pair: &KeyPair,
     ) -> Result<Signature, WError> {
-        let prehashed = Self::prehash(data, ad)?;
+        let prehashed = self.prehash(data, ad, hash_alg, context)?;
+        let raw = self.sign_prehashed(&prehashed, key_pair)?;
+        Ok(Signature::new(ALG_ID, raw)
+            .with_hash_alg(hash_alg)
+            .with_context(context))
+    }
+
+    fn sign_prehashed(&self, prehashed: &[u8], key_pair: &KeyPair) -> Result<Vec<u8>, WError> {
         let xsk = ed25519_compact::SecretKey::from_slice(key_pair.sk.raw())?;
-        let raw = xsk
-            .sign(prehashed, Some(ed25519_compact::Noise::default()))
-            .to_vec();
-        Ok(Signature::new(ALG_ID, raw))
+        let noise = match key_pair.is_deterministic() {
+            true => None,
+            false => Some(ed25519_compact::Noise::default()),
+        };
+        let raw = xsk.sign(prehashed, noise).to_vec();
+        Ok(raw)
     }
 
     fn verify(
         &self,
         data: &[u8],
-        ad: Option<&[u8]>,
+        ad: &AdditionalData,
+        pk: &[u8],
+        signature: &Signature,
+    ) -> Result<(), WError> {
+        let prehashed = self.prehash(data, ad, signature.hash_alg(), signature.context())?;
+        self.verify_prehashed(&prehashed, pk, signature)
+    }
+
+    fn verify_prehashed(
+        &self,
+        prehashed: &[u8],
         pk: &[u8],
         signature: &Signature,
     ) -> Result<(), WError> {
         assert_eq!(signature.alg_id, ALG_ID);
-        let prehashed = Self::prehash(data, ad)?;
         let xpk = ed25519_compact::PublicKey::from_slice(pk)?;
         let xsignature = ed25519_compact::Signature::from_slice(&signature.raw)?;
         xpk.verify(prehashed, &xsignature).map_err(|e| e.into())
     }
+
+    fn prehash(
+        &self,
+        data: &[u8],
+        ad: &AdditionalData,
+        hash_alg: HashAlg,
+        context: Option<&str>,
+    ) -> Result<Vec<u8>, WError> {
+        prehash(data, ad, hash_alg, context)
+    }
 }
 
-impl EdDSA {
-    fn prehash(data: &[u8], ad: Option<&[u8]>) -> Result<[u8; 64], WError> {
-        let mut ad_len = vec![];
-        let ad = ad.unwrap_or_default();
-        if ad.len() > u32::MAX as usize {
-            return Err(WError::UsageError("Additional data too long"))
-        }
-        ad_len.write_u32::<LittleEndian>(ad.len() as u32)?;
+/// Hashes a message along with its additional data and parameters. Other
+/// signature schemes sign the same prehashed message.
+pub fn prehash(
+    data: &[u8],
+    ad: &AdditionalData,
+    hash_alg: HashAlg,
+    context: Option<&str>,
+) -> Result<Vec<u8>, WError> {
+    let mut ad_len = vec![];
+    let ad = ad.as_bytes();
+    if ad.len() >= HASH_ALG_MARKER as usize {
+        return Err(WError::UsageError("Additional data too long"))
+    }
+    ad_len.write_u32::<LittleEndian>(ad.len() as u32)?;
 
-        let mut prehashed = Hash::default();
-        prehashed.update(CONTEXT);
-        prehashed.update(&ad_len);
-        prehashed.update(ad);
-        prehashed.update(data);
-        Ok(prehashed.finalize())
+    let mut prehashed = hash_alg.hasher();
+    prehashed.update(DEFAULT_CONTEXT.as_bytes());
+    if hash_alg != HashAlg::default() {
+        let mut hash_alg_header = vec![];
+        hash_alg_header.write_u32::<LittleEndian>(HASH_ALG_MARKER)?;
+        hash_alg_header.push(hash_alg.id());
+        prehashed.update(&hash_alg_header);
+    }
+    check_context(context)?;
+    if let Some(context) = context.filter(|&context| context != DEFAULT_CONTEXT) {
+        let mut context_header = vec![];
+        context_header.write_u32::<LittleEndian>(CUSTOM_CONTEXT_MARKER)?;
+        context_header.write_u32::<LittleEndian>(context.len() as u32)?;
+        prehashed.update(&context_header);
+        prehashed.update(context.as_bytes());
     }
+    prehashed.update(&ad_len);
+    prehashed.update(ad);
+    prehashed.update(data);
+    Ok(prehashed.finalize())
 }
@@ -0,0 +1,33 @@
+use super::super::errors::*;
+
+/// Formats for signatures that can be stored outside of the module.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnvelopeFormat {
+    Dsse,
+    Jws,
+}
+
+impl EnvelopeFormat {
+    pub fn from_name(name: &str) -> Result<Self, WError> {
+        match name {
+            "dsse" => Ok(EnvelopeFormat::Dsse),
+            "jws" => Ok(EnvelopeFormat::Jws),
+            _ => Err(WError::UsageError("Unsupported envelope format (dsse or jws)")),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EnvelopeFormat::Dsse => "dsse",
+            EnvelopeFormat::Jws => "jws",
+        }
+    }
+
+    /// Guesses the format of an encoded envelope.
+    pub fn detect(envelope: &[u8]) -> Self {
+        match envelope.iter().find(|c| !c.is_ascii_whitespace()) {
+            Some(b'{') => EnvelopeFormat::Dsse,
+            _ => EnvelopeFormat::Jws,
+        }
+    }
+}
@@ -0,0 +1,424 @@
+//! FROST(Ed25519, SHA-512) threshold signatures (RFC 9591).
+//!
+//! Keys are split by a trusted dealer. Any `min_signers` share holders can
+//! then produce a standard Ed25519 signature over a `SigningRequest`, in two
+//! rounds whose messages can be exchanged as files:
+//!
+//! 1. each signer runs `commit()`, keeps the nonces secret, and publishes
+//!    the commitments;
+//! 2. each signer runs `sign()` over the request and the commitments of all
+//!    the participants, and publishes a signature share;
+//! 3. anyone can `aggregate()` the shares into a signature.
+
+use super::*;
+use curve25519_dalek::edwards::{CompressedEdwardsY, EdwardsPoint};
+use curve25519_dalek::scalar::Scalar;
+use curve25519_dalek::traits::Identity;
+use hmac_sha512::Hash;
+
+const CONTEXT_STRING: &[u8] = b"FROST-ED25519-SHA512-v1";
+
+const TAG_KEY_SHARE: u8 = 0x01;
+const TAG_NONCES: u8 = 0x02;
+const TAG_COMMITMENTS: u8 = 0x03;
+const TAG_SIGNATURE_SHARE: u8 = 0x04;
+
+fn random_scalar() -> Result<Scalar, WError> {
+    let mut bytes = [0u8; 64];
+    getrandom::getrandom(&mut bytes).map_err(|_| WError::InternalError("RNG failure"))?;
+    Ok(Scalar::from_bytes_mod_order_wide(&bytes))
+}
+
+fn hash_to_scalar(parts: &[&[u8]]) -> Scalar {
+    let mut h = Hash::new();
+    for part in parts {
+        h.update(part);
+    }
+    Scalar::from_bytes_mod_order_wide(&h.finalize())
+}
+
+fn identifier_to_scalar(identifier: u16) -> Scalar {
+    Scalar::from(identifier as u64)
+}
+
+fn decode_scalar(bytes: &[u8]) -> Result<Scalar, WError> {
+    let mut encoded = [0u8; 32];
+    encoded.copy_from_slice(bytes);
+    Option::from(Scalar::from_canonical_bytes(encoded))
+        .ok_or_else(|| WError::ParseError("Invalid scalar encoding".to_string()))
+}
+
+fn decode_point(bytes: &[u8]) -> Result<EdwardsPoint, WError> {
+    let point = CompressedEdwardsY::from_slice(bytes)
+        .ok()
+        .and_then(|p| p.decompress())
+        .ok_or_else(|| WError::ParseError("Invalid point encoding".to_string()))?;
+    if point == EdwardsPoint::identity() {
+        return Err(WError::ParseError(
+            "Unexpected identity element".to_string(),
+        ));
+    }
+    Ok(point)
+}
+
+fn decode_tagged(bytes: &[u8], tag: u8, len: usize) -> Result<(u16, &[u8]), WError> {
+    if bytes.len() != 3 + len || bytes[0] != tag {
+        return Err(WError::ParseError("Unexpected FROST message".to_string()));
+    }
+    let identifier = LittleEndian::read_u16(&bytes[1..3]);
+    if identifier == 0 {
+        return Err(WError::ParseError("Invalid FROST identifier".to_string()));
+    }
+    Ok((identifier, &bytes[3..]))
+}
+
+fn encode_tagged(tag: u8, identifier: u16, parts: &[&[u8]]) -> Vec<u8> {
+    let mut bytes = vec![tag];
+    bytes
+        .write_u16::<LittleEndian>(identifier)
+        .expect("Unable to serialize");
+    for part in parts {
+        bytes.extend_from_slice(part);
+    }
+    bytes
+}
+
+pub struct KeyShare {
+    identifier: u16,
+    min_signers: u16,
+    group_pk: EdwardsPoint,
+    signing_share: Scalar,
+}
+
+impl KeyShare {
+    pub fn identifier(&self) -> u16 {
+        self.identifier
+    }
+
+    pub fn min_signers(&self) -> u16 {
+        self.min_signers
+    }
+
+    pub fn group_public_key(&self) -> PublicKey {
+        PublicKey::new(eddsa::ALG_ID, self.group_pk.compress().to_bytes().to_vec())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut min_signers = vec![];
+        min_signers
+            .write_u16::<LittleEndian>(self.min_signers)
+            .expect("Unable to serialize");
+        encode_tagged(
+            TAG_KEY_SHARE,
+            self.identifier,
+            &[
+                &min_signers,
+                self.group_pk.compress().as_bytes(),
+                self.signing_share.as_bytes(),
+            ],
+        )
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, WError> {
+        let (identifier, bytes) = decode_tagged(bytes, TAG_KEY_SHARE, 2 + 32 + 32)?;
+        Ok(KeyShare {
+            identifier,
+            min_signers: LittleEndian::read_u16(&bytes[0..2]),
+            group_pk: decode_point(&bytes[2..34])?,
+            signing_share: decode_scalar(&bytes[34..66])?,
+        })
+    }
+}
+
+pub struct SigningNonces {
+    identifier: u16,
+    hiding: Scalar,
+    binding: Scalar,
+}
+
+impl SigningNonces {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode_tagged(
+            TAG_NONCES,
+            self.identifier,
+            &[self.hiding.as_bytes(), self.binding.as_bytes()],
+        )
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, WError> {
+        let (identifier, bytes) = decode_tagged(bytes, TAG_NONCES, 32 + 32)?;
+        Ok(SigningNonces {
+            identifier,
+            hiding: decode_scalar(&bytes[0..32])?,
+            binding: decode_scalar(&bytes[32..64])?,
+        })
+    }
+}
+
+#[derive(Clone)]
+pub struct SigningCommitments {
+    identifier: u16,
+    hiding: EdwardsPoint,
+    binding: EdwardsPoint,
+}
+
+impl SigningCommitments {
+    pub fn identifier(&self) -> u16 {
+        self.identifier
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode_tagged(
+            TAG_COMMITMENTS,
+            self.identifier,
+            &[
+                self.hiding.compress().as_bytes(),
+                self.binding.compress().as_bytes(),
+            ],
+        )
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, WError> {
+        let (identifier, bytes) = decode_tagged(bytes, TAG_COMMITMENTS, 32 + 32)?;
+        Ok(SigningCommitments {
+            identifier,
+            hiding: decode_point(&bytes[0..32])?,
+            binding: decode_point(&bytes[32..64])?,
+        })
+    }
+}
+
+pub struct SignatureShare {
+    identifier: u16,
+    share: Scalar,
+}
+
+impl SignatureShare {
+    pub fn identifier(&self) -> u16 {
+        self.identifier
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode_tagged(
+            TAG_SIGNATURE_SHARE,
+            self.identifier,
+            &[self.share.as_bytes()],
+        )
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, WError> {
+        let (identifier, bytes) = decode_tagged(bytes, TAG_SIGNATURE_SHARE, 32)?;
+        Ok(SignatureShare {
+            identifier,
+            share: decode_scalar(bytes)?,
+        })
+    }
+}
+
+pub fn keygen(min_signers: u16, max_signers: u16) -> Result<(PublicKey, Vec<KeyShare>), WError> {
+    if min_signers < 2 || min_signers > max_signers {
+        return Err(WError::UsageError(
+            "The threshold must be at least 2 and at most the number of signers",
+        ));
+    }
+    let mut coefficients = vec![];
+    for _ in 0..min_signers {
+        coefficients.push(random_scalar()?);
+    }
+    let group_pk = EdwardsPoint::mul_base(&coefficients[0]);
+    let shares = (1..=max_signers)
+        .map(|identifier| {
+            let x = identifier_to_scalar(identifier);
+            let signing_share = coefficients
+                .iter()
+                .rev()
+                .fold(Scalar::ZERO, |acc, coefficient| acc * x + coefficient);
+            KeyShare {
+                identifier,
+                min_signers,
+                group_pk,
+                signing_share,
+            }
+        })
+        .collect();
+    let pk = PublicKey::new(eddsa::ALG_ID, group_pk.compress().to_bytes().to_vec());
+    Ok((pk, shares))
+}
+
+fn generate_nonce(secret: &Scalar) -> Result<Scalar, WError> {
+    let mut random_bytes = [0u8; 32];
+    getrandom::getrandom(&mut random_bytes).map_err(|_| WError::InternalError("RNG failure"))?;
+    Ok(hash_to_scalar(&[
+        CONTEXT_STRING,
+        b"nonce",
+        &random_bytes,
+        secret.as_bytes(),
+    ]))
+}
+
+pub fn commit(key_share: &KeyShare) -> Result<(SigningNonces, SigningCommitments), WError> {
+    let nonces = SigningNonces {
+        identifier: key_share.identifier,
+        hiding: generate_nonce(&key_share.signing_share)?,
+        binding: generate_nonce(&key_share.signing_share)?,
+    };
+    let commitments = SigningCommitments {
+        identifier: key_share.identifier,
+        hiding: EdwardsPoint::mul_base(&nonces.hiding),
+        binding: EdwardsPoint::mul_base(&nonces.binding),
+    };
+    Ok((nonces, commitments))
+}
+
+struct SigningPackage {
+    commitments: Vec<SigningCommitments>,
+    binding_factors: Vec<Scalar>,
+    group_commitment: EdwardsPoint,
+    challenge: Scalar,
+}
+
+impl SigningPackage {
+    fn new(
+        group_pk: &EdwardsPoint,
+        msg: &[u8],
+        commitments: &[SigningCommitments],
+    ) -> Result<Self, WError> {
+        let mut commitments = commitments.to_vec();
+        commitments.sort_by_key(|c| c.identifier);
+        if commitments
+            .windows(2)
+            .any(|w| w[0].identifier == w[1].identifier)
+        {
+            return Err(WError::UsageError("Duplicate FROST participant"));
+        }
+        let group_pk_enc = group_pk.compress().to_bytes();
+        let msg_hash = Hash::hash([CONTEXT_STRING, b"msg", msg].concat());
+        let mut encoded_commitments = vec![];
+        for c in &commitments {
+            encoded_commitments.extend_from_slice(identifier_to_scalar(c.identifier).as_bytes());
+            encoded_commitments.extend_from_slice(c.hiding.compress().as_bytes());
+            encoded_commitments.extend_from_slice(c.binding.compress().as_bytes());
+        }
+        let commitments_hash = Hash::hash([CONTEXT_STRING, b"com", &encoded_commitments].concat());
+        let binding_factors: Vec<Scalar> = commitments
+            .iter()
+            .map(|c| {
+                hash_to_scalar(&[
+                    CONTEXT_STRING,
+                    b"rho",
+                    &group_pk_enc,
+                    &msg_hash,
+                    &commitments_hash,
+                    identifier_to_scalar(c.identifier).as_bytes(),
+                ])
+            })
+            .collect();
+        let group_commitment = commitments
+            .iter()
+            .zip(&binding_factors)
+            .fold(EdwardsPoint::identity(), |acc, (c, rho)| {
+                acc + c.hiding + c.binding * rho
+            });
+        let challenge =
+            hash_to_scalar(&[group_commitment.compress().as_bytes(), &group_pk_enc, msg]);
+        Ok(SigningPackage {
+            commitments,
+            binding_factors,
+            group_commitment,
+            challenge,
+        })
+    }
+
+    fn lagrange_coefficient(&self, identifier: u16) -> Scalar {
+        let x_i = identifier_to_scalar(identifier);
+        let (num, den) = self
+            .commitments
+            .iter()
+            .filter(|c| c.identifier != identifier)
+            .fold((Scalar::ONE, Scalar::ONE), |(num, den), c| {
+                let x_j = identifier_to_scalar(c.identifier);
+                (num * x_j, den * (x_j - x_i))
+            });
+        num * den.invert()
+    }
+}
+
+pub fn sign(
+    request: &SigningRequest,
+    key_share: &KeyShare,
+    nonces: &SigningNonces,
+    commitments: &[SigningCommitments],
+) -> Result<SignatureShare, WError> {
+    if request.alg_id() != eddsa::ALG_ID {
+        return Err(WError::SignatureError(
+            "Signing request uses a different scheme than the key share",
+        ));
+    }
+    if nonces.identifier != key_share.identifier {
+        return Err(WError::UsageError("Nonces don't belong to this key share"));
+    }
+    if commitments.len() < key_share.min_signers as usize {
+        return Err(WError::UsageError("Not enough FROST participants"));
+    }
+    let package = SigningPackage::new(&key_share.group_pk, request.digest(), commitments)?;
+    let index = package
+        .commitments
+        .iter()
+        .position(|c| c.identifier == key_share.identifier)
+        .ok_or(WError::UsageError("Own commitments are missing"))?;
+    let own = &package.commitments[index];
+    if own.hiding != EdwardsPoint::mul_base(&nonces.hiding)
+        || own.binding != EdwardsPoint::mul_base(&nonces.binding)
+    {
+        return Err(WError::UsageError("Nonces don't match the commitments"));
+    }
+    let lambda = package.lagrange_coefficient(key_share.identifier);
+    let share = nonces.hiding
+        + nonces.binding * package.binding_factors[index]
+        + lambda * key_share.signing_share * package.challenge;
+    Ok(SignatureShare {
+        identifier: key_share.identifier,
+        share,
+    })
+}
+
+pub fn aggregate(
+    request: &SigningRequest,
+    group_pk: &PublicKey,
+    commitments: &[SigningCommitments],
+    signature_shares: &[SignatureShare],
+) -> Result<Signature, WError> {
+    if request.alg_id() != eddsa::ALG_ID || group_pk.alg_id() != eddsa::ALG_ID {
+        return Err(WError::SignatureError(
+            "FROST signatures are only supported for Ed25519",
+        ));
+    }
+    let group_pk_point = decode_point(group_pk.raw())?;
+    let package = SigningPackage::new(&group_pk_point, request.digest(), commitments)?;
+    let mut identifiers: Vec<u16> = signature_shares.iter().map(|s| s.identifier).collect();
+    identifiers.sort_unstable();
+    identifiers.dedup();
+    if identifiers.len() != signature_shares.len()
+        || identifiers
+            != package
+                .commitments
+                .iter()
+                .map(|c| c.identifier)
+                .collect::<Vec<_>>()
+    {
+        return Err(WError::UsageError(
+            "Signature shares don't match the commitments",
+        ));
+    }
+    let z = signature_shares
+        .iter()
+        .fold(Scalar::ZERO, |acc, s| acc + s.share);
+    let mut raw = package.group_commitment.compress().to_bytes().to_vec();
+    raw.extend_from_slice(z.as_bytes());
+    let mut signature = Signature::new(eddsa::ALG_ID, raw);
+    signature.parameters = request.parameters.clone();
+    EdDSA
+        .verify_prehashed(request.digest(), group_pk.raw(), &signature)
+        .map_err(|_| WError::SignatureError("Invalid signature share"))?;
+    Ok(signature)
+}
@@ -0,0 +1,97 @@
+use super::super::errors::*;
+use sha3::Digest;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum HashAlg {
+    Sha256,
+    #[default]
+    Sha512,
+    Sha3_256,
+    Blake3,
+}
+
+impl HashAlg {
+    pub fn id(&self) -> u8 {
+        match self {
+            HashAlg::Sha256 => 1,
+            HashAlg::Sha512 => 2,
+            HashAlg::Sha3_256 => 3,
+            HashAlg::Blake3 => 4,
+        }
+    }
+
+    pub fn from_id(id: u8) -> Result<Self, WError> {
+        match id {
+            1 => Ok(HashAlg::Sha256),
+            2 => Ok(HashAlg::Sha512),
+            3 => Ok(HashAlg::Sha3_256),
+            4 => Ok(HashAlg::Blake3),
+            _ => Err(WError::SignatureError("Unsupported hash function")),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashAlg::Sha256 => "sha256",
+            HashAlg::Sha512 => "sha512",
+            HashAlg::Sha3_256 => "sha3-256",
+            HashAlg::Blake3 => "blake3",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self, WError> {
+        match name.to_ascii_lowercase().as_str() {
+            "sha256" | "sha-256" => Ok(HashAlg::Sha256),
+            "sha512" | "sha-512" => Ok(HashAlg::Sha512),
+            "sha3-256" | "sha3_256" => Ok(HashAlg::Sha3_256),
+            "blake3" => Ok(HashAlg::Blake3),
+            _ => Err(WError::UsageError(
+                "Unsupported hash function (sha256, sha512, sha3-256 or blake3)",
+            )),
+        }
+    }
+
+    pub fn hasher(&self) -> Hasher {
+        match self {
+            HashAlg::Sha256 => Hasher::Sha256(hmac_sha256::Hash::new()),
+            HashAlg::Sha512 => Hasher::Sha512(hmac_sha512::Hash::new()),
+            HashAlg::Sha3_256 => Hasher::Sha3_256(sha3::Sha3_256::new()),
+            HashAlg::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+        }
+    }
+
+    pub fn hash(&self, data: &[u8]) -> Vec<u8> {
+        let mut hasher = self.hasher();
+        hasher.update(data);
+        hasher.finalize()
+    }
+}
+
+pub enum Hasher {
+    Sha256(hmac_sha256::Hash),
+    Sha512(hmac_sha512::Hash),
+    Sha3_256(sha3::Sha3_256),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha256(h) => h.update(data),
+            Hasher::Sha512(h) => h.update(data),
+            Hasher::Sha3_256(h) => h.update(data),
+            Hasher::Blake3(h) => {
+                h.update(data);
+            }
+        }
+    }
+
+    pub fn finalize(self) -> Vec<u8> {
+        match self {
+            Hasher::Sha256(h) => h.finalize().to_vec(),
+            Hasher::Sha512(h) => h.finalize().to_vec(),
+            Hasher::Sha3_256(h) => h.finalize().to_vec(),
+            Hasher::Blake3(h) => h.finalize().as_bytes().to_vec(),
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use super::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+pub const PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
+pub const STATEMENT_TYPE: &str = "https://in-toto.io/Statement/v1";
+const LEGACY_STATEMENT_TYPE: &str = "https://in-toto.io/Statement/v0.1";
+pub const SLSA_PROVENANCE_TYPE: &str = "https://slsa.dev/provenance/v1";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Subject {
+    pub name: String,
+    pub digest: BTreeMap<String, String>,
+}
+
+/// An in-toto statement. The predicate is kept as-is.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Statement {
+    #[serde(rename = "_type")]
+    pub statement_type: String,
+    #[serde(default)]
+    pub subject: Vec<Subject>,
+    #[serde(rename = "predicateType")]
+    pub predicate_type: String,
+    #[serde(default, skip_serializing_if = "Value::is_null")]
+    pub predicate: Value,
+}
+
+/// The name of a hash function in an in-toto digest set.
+pub fn digest_name(hash_alg: HashAlg) -> &'static str {
+    match hash_alg {
+        HashAlg::Sha3_256 => "sha3_256",
+        hash_alg => hash_alg.name(),
+    }
+}
+
+impl Statement {
+    pub fn from_json(json: &[u8]) -> Result<Self, WError> {
+        let statement: Statement = serde_json::from_slice(json)
+            .map_err(|e| WError::ParseError(format!("Invalid in-toto statement: {}", e)))?;
+        if statement.statement_type != STATEMENT_TYPE
+            && statement.statement_type != LEGACY_STATEMENT_TYPE
+        {
+            return Err(WError::ParseError(format!(
+                "Unsupported statement type: {}",
+                statement.statement_type
+            )));
+        }
+        Ok(statement)
+    }
+
+    pub fn to_json(&self) -> Result<Vec<u8>, WError> {
+        serde_json::to_vec(self).map_err(|_| WError::InternalError("json"))
+    }
+
+    /// A minimal SLSA provenance statement, without subjects.
+    pub fn slsa_provenance(
+        builder_id: &str,
+        build_type: &str,
+        external_parameters: &[(String, String)],
+    ) -> Self {
+        let external_parameters: Map<String, Value> = external_parameters
+            .iter()
+            .map(|(name, value)| (name.clone(), Value::String(value.clone())))
+            .collect();
+        let predicate = serde_json::json!({
+            "buildDefinition": {
+                "buildType": build_type,
+                "externalParameters": external_parameters,
+            },
+            "runDetails": {
+                "builder": { "id": builder_id },
+            },
+        });
+        Statement {
+            statement_type: STATEMENT_TYPE.to_string(),
+            subject: vec![],
+            predicate_type: SLSA_PROVENANCE_TYPE.to_string(),
+            predicate,
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use super::*;
+use ct_codecs::{Base64UrlSafeNoPadding, Decoder, Encoder, Hex};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+struct Header {
+    alg: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cty: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    crit: Option<Vec<String>>,
+}
+
+pub fn jws_algorithm(alg_id: u32) -> Result<&'static str, WError> {
+    match alg_id {
+        eddsa::ALG_ID => Ok("EdDSA"),
+        _ => Err(WError::SignatureError(
+            "No JWS identifier for this signature scheme",
+        )),
+    }
+}
+
+fn parse_error<E: std::fmt::Display>(e: E) -> WError {
+    WError::ParseError(format!("Invalid JWS: {}", e))
+}
+
+fn encode(bin: &[u8]) -> Result<String, WError> {
+    Base64UrlSafeNoPadding::encode_to_string(bin).map_err(|_| WError::InternalError("base64"))
+}
+
+/// Signs a payload, and returns a JWS using the compact serialization.
+pub fn sign(
+    content_type: &str,
+    payload: &[u8],
+    signer: &dyn Signer,
+    key_id: Option<&[u8]>,
+) -> Result<String, WError> {
+    let kid = match key_id {
+        None => None,
+        Some(key_id) => {
+            Some(Hex::encode_to_string(key_id).map_err(|_| WError::InternalError("hex"))?)
+        }
+    };
+    let header = Header {
+        alg: jws_algorithm(signer.alg_id())?.to_string(),
+        kid,
+        cty: Some(content_type.to_string()),
+        crit: None,
+    };
+    let header = serde_json::to_vec(&header).map_err(|_| WError::InternalError("json"))?;
+    let signing_input = format!("{}.{}", encode(&header)?, encode(payload)?);
+    let sig = signer.sign_prehashed(signing_input.as_bytes())?;
+    Ok(format!("{}.{}", signing_input, encode(&sig)?))
+}
+
+/// Verifies a compact JWS, and returns its content type and payload.
+pub fn verify(jws: &str, pk: &PublicKey) -> Result<(Option<String>, Vec<u8>), WError> {
+    let parts: Vec<&str> = jws.trim().split('.').collect();
+    if parts.len() != 3 {
+        return Err(WError::ParseError(
+            "JWS compact serialization expected".to_string(),
+        ));
+    }
+    let header = Base64UrlSafeNoPadding::decode_to_vec(parts[0], None).map_err(parse_error)?;
+    let header: Header = serde_json::from_slice(&header).map_err(parse_error)?;
+    if header.crit.is_some() {
+        return Err(WError::ParseError(
+            "Unsupported critical JWS header parameters".to_string(),
+        ));
+    }
+    if header.alg != jws_algorithm(pk.alg_id())? {
+        return Err(WError::SignatureError(
+            "Signature uses a different scheme than the provided public key",
+        ));
+    }
+    let payload = Base64UrlSafeNoPadding::decode_to_vec(parts[1], None).map_err(parse_error)?;
+    let raw = Base64UrlSafeNoPadding::decode_to_vec(parts[2], None).map_err(parse_error)?;
+    let signing_input = &jws.trim()[..parts[0].len() + 1 + parts[1].len()];
+    pk.to_alg()?.verify_prehashed(
+        signing_input.as_bytes(),
+        pk.raw(),
+        &Signature::new(pk.alg_id(), raw),
+    )?;
+    Ok((header.cty, payload))
+}
@@ -0,0 +1,190 @@
+use super::super::errors::*;
+use super::hash::HashAlg;
+use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
+use std::fmt;
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+const KIND_HEADER: u8 = 0x00;
+const KIND_SECTION: u8 = 0x01;
+const KIND_CUSTOM_SECTION: u8 = 0x02;
+const KIND_FUNCTION: u8 = 0x03;
+
+/// What a leaf of the Merkle tree covers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LeafKind {
+    /// The module header
+    Header,
+    /// A non-custom section. For the code section, only the function count.
+    Section(u8),
+    CustomSection(String),
+    /// A function body, numbered from the start of the code section
+    Function(u32),
+}
+
+impl LeafKind {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        match self {
+            LeafKind::Header => bytes.push(KIND_HEADER),
+            LeafKind::Section(id) => bytes.extend_from_slice(&[KIND_SECTION, *id]),
+            LeafKind::CustomSection(name) => {
+                bytes.push(KIND_CUSTOM_SECTION);
+                bytes
+                    .write_u32::<LittleEndian>(name.len() as u32)
+                    .expect("Unable to serialize");
+                bytes.extend_from_slice(name.as_bytes());
+            }
+            LeafKind::Function(index) => {
+                bytes.push(KIND_FUNCTION);
+                bytes
+                    .write_u32::<LittleEndian>(*index)
+                    .expect("Unable to serialize");
+            }
+        }
+        bytes
+    }
+
+    fn from_bytes(bytes: &mut &[u8]) -> Result<Self, WError> {
+        let invalid = || WError::ParseError("Invalid Merkle leaf".to_string());
+        let (&kind, rest) = bytes.split_first().ok_or_else(invalid)?;
+        let (leaf_kind, rest) = match kind {
+            KIND_HEADER => (LeafKind::Header, rest),
+            KIND_SECTION => {
+                let (&id, rest) = rest.split_first().ok_or_else(invalid)?;
+                (LeafKind::Section(id), rest)
+            }
+            KIND_CUSTOM_SECTION if rest.len() >= 4 => {
+                let len = LittleEndian::read_u32(&rest[..4]) as usize;
+                if rest.len() - 4 < len {
+                    return Err(invalid());
+                }
+                let name = std::str::from_utf8(&rest[4..4 + len]).map_err(|_| invalid())?;
+                (LeafKind::CustomSection(name.to_string()), &rest[4 + len..])
+            }
+            KIND_FUNCTION if rest.len() >= 4 => {
+                (LeafKind::Function(LittleEndian::read_u32(&rest[..4])), &rest[4..])
+            }
+            _ => return Err(invalid()),
+        };
+        *bytes = rest;
+        Ok(leaf_kind)
+    }
+}
+
+impl fmt::Display for LeafKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let section_name = |id| match id {
+            1 => "type",
+            2 => "import",
+            3 => "function",
+            4 => "table",
+            5 => "memory",
+            6 => "global",
+            7 => "export",
+            8 => "start",
+            9 => "element",
+            10 => "code",
+            11 => "data",
+            12 => "data count",
+            _ => "unknown",
+        };
+        match self {
+            LeafKind::Header => write!(f, "module header"),
+            LeafKind::Section(id) => write!(f, "{} section (id {})", section_name(*id), id),
+            LeafKind::CustomSection(name) => write!(f, "custom section [{}]", name),
+            LeafKind::Function(index) => write!(f, "function body #{}", index),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerkleLeaf {
+    kind: LeafKind,
+    hash: Vec<u8>,
+}
+
+impl MerkleLeaf {
+    pub fn new(hash_alg: HashAlg, kind: LeafKind, data: &[u8]) -> Self {
+        let mut hasher = hash_alg.hasher();
+        hasher.update(&[LEAF_PREFIX]);
+        hasher.update(&kind.to_bytes());
+        hasher.update(data);
+        MerkleLeaf {
+            kind,
+            hash: hasher.finalize(),
+        }
+    }
+
+    pub fn kind(&self) -> &LeafKind {
+        &self.kind
+    }
+
+    pub fn hash(&self) -> &[u8] {
+        &self.hash
+    }
+}
+
+pub fn merkle_root(hash_alg: HashAlg, leaves: &[MerkleLeaf]) -> Vec<u8> {
+    let mut level: Vec<Vec<u8>> = leaves.iter().map(|leaf| leaf.hash.clone()).collect();
+    if level.is_empty() {
+        return hash_alg.hash(&[NODE_PREFIX]);
+    }
+    while level.len() > 1 {
+        level = level
+            .chunks(2)
+            .map(|pair| match pair {
+                [left, right] => {
+                    let mut hasher = hash_alg.hasher();
+                    hasher.update(&[NODE_PREFIX]);
+                    hasher.update(left);
+                    hasher.update(right);
+                    hasher.finalize()
+                }
+                _ => pair[0].clone(),
+            })
+            .collect();
+    }
+    level.pop().unwrap()
+}
+
+pub(crate) fn encode_leaves(leaves: &[MerkleLeaf]) -> Vec<u8> {
+    let mut bytes = vec![];
+    bytes
+        .write_u32::<LittleEndian>(leaves.len() as u32)
+        .expect("Unable to serialize");
+    for leaf in leaves {
+        bytes.extend_from_slice(&leaf.kind.to_bytes());
+        bytes.push(leaf.hash.len() as u8);
+        bytes.extend_from_slice(&leaf.hash);
+    }
+    bytes
+}
+
+pub(crate) fn decode_leaves(bytes: &[u8]) -> Result<Vec<MerkleLeaf>, WError> {
+    let invalid = || WError::ParseError("Invalid Merkle leaves".to_string());
+    if bytes.len() < 4 {
+        return Err(invalid());
+    }
+    let count = LittleEndian::read_u32(&bytes[..4]);
+    let mut bytes = &bytes[4..];
+    let mut leaves = vec![];
+    for _ in 0..count {
+        let kind = LeafKind::from_bytes(&mut bytes)?;
+        let (&hash_len, rest) = bytes.split_first().ok_or_else(invalid)?;
+        let hash_len = hash_len as usize;
+        if rest.len() < hash_len {
+            return Err(invalid());
+        }
+        leaves.push(MerkleLeaf {
+            kind,
+            hash: rest[..hash_len].to_vec(),
+        });
+        bytes = &rest[hash_len..];
+    }
+    if !bytes.is_empty() {
+        return Err(invalid());
+    }
+    Ok(leaves)
+}
@@ -1,50 +1,546 @@
 use super::errors::*;
-use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
+use byteorder::{BigEndian, ByteOrder, LittleEndian, WriteBytesExt};
+use hmac_sha512::Hash;
 
+pub mod ad;
+pub mod cose;
+pub mod derivation;
+pub mod dsse;
+pub mod ecdsa;
 pub mod eddsa;
+pub mod envelope;
+pub mod frost;
+pub mod hash;
+pub mod intoto;
+pub mod jws;
+pub mod merkle;
+#[cfg(unix)]
+pub mod pkcs11;
+pub mod plugin;
+pub mod shamir;
+pub mod sigstore;
+pub mod ssh;
+pub mod tlog;
+pub mod tsp;
+pub mod wasmsign2;
+pub mod x509;
 
+use self::ecdsa::Ecdsa;
 use self::eddsa::*;
+pub use self::ad::{AdEmbedding, AdditionalData};
+pub use self::envelope::EnvelopeFormat;
+pub use self::hash::HashAlg;
+pub use self::merkle::{merkle_root, LeafKind, MerkleLeaf};
+
+pub const KEY_ID_BYTES: usize = 8;
+
+pub const DEFAULT_CONTEXT: &str = "WasmSignature";
+pub const MAX_CONTEXT_LENGTH: usize = 255;
+
+const EXTENDED_ENVELOPE: u32 = 0x8000_0000;
+const EXT_HASH_ALG: u8 = 0x01;
+const EXT_CONTEXT: u8 = 0x02;
+const EXT_AD: u8 = 0x03;
+const EXT_AD_HASH: u8 = 0x04;
+const EXT_SECTIONS: u8 = 0x05;
+const EXT_MERKLE: u8 = 0x06;
+const EXT_CERTIFICATES: u8 = 0x07;
+const EXT_TIMESTAMP: u8 = 0x08;
+const EXT_LOG_PROOF: u8 = 0x09;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EmbeddedAd {
+    Data(Vec<u8>),
+    Hash(HashAlg, Vec<u8>),
+}
+
+impl EmbeddedAd {
+    fn new(ad: &AdditionalData, hash_alg: HashAlg) -> Option<Self> {
+        match ad.embedding() {
+            _ if ad.is_empty() => None,
+            AdEmbedding::None => None,
+            AdEmbedding::Data => Some(EmbeddedAd::Data(ad.as_bytes().to_vec())),
+            AdEmbedding::Hash => Some(EmbeddedAd::Hash(hash_alg, hash_alg.hash(ad.as_bytes()))),
+        }
+    }
+
+    pub fn matches(&self, ad: &AdditionalData) -> bool {
+        match self {
+            EmbeddedAd::Data(data) => data == ad.as_bytes(),
+            EmbeddedAd::Hash(hash_alg, hash) => &hash_alg.hash(ad.as_bytes()) == hash,
+        }
+    }
+}
+
+/// The part of a module covered by a signature stored in a Custom Section.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum SectionSelection {
+    /// Every byte before the signature
+    #[default]
+    All,
+    /// All the non-custom sections, and the listed Custom Sections
+    Standard(Vec<String>),
+}
+
+impl SectionSelection {
+    pub fn standard<S: AsRef<str>>(custom_section_names: &[S]) -> Self {
+        let mut names: Vec<String> = custom_section_names
+            .iter()
+            .map(|name| name.as_ref().to_string())
+            .collect();
+        names.sort();
+        names.dedup();
+        SectionSelection::Standard(names)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        if let SectionSelection::Standard(names) = self {
+            bytes
+                .write_u32::<LittleEndian>(names.len() as u32)
+                .expect("Unable to serialize");
+            for name in names {
+                bytes
+                    .write_u32::<LittleEndian>(name.len() as u32)
+                    .expect("Unable to serialize");
+                bytes.extend_from_slice(name.as_bytes());
+            }
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, WError> {
+        let invalid = || WError::ParseError("Invalid section selection".to_string());
+        if bytes.len() < 4 {
+            return Err(invalid());
+        }
+        let count = LittleEndian::read_u32(&bytes[..4]);
+        let mut bytes = &bytes[4..];
+        let mut names = vec![];
+        for _ in 0..count {
+            if bytes.len() < 4 {
+                return Err(invalid());
+            }
+            let len = LittleEndian::read_u32(&bytes[..4]) as usize;
+            if bytes.len() - 4 < len {
+                return Err(invalid());
+            }
+            let name = std::str::from_utf8(&bytes[4..4 + len]).map_err(|_| invalid())?;
+            names.push(name.to_string());
+            bytes = &bytes[4 + len..];
+        }
+        let selection = SectionSelection::standard(&names);
+        if !bytes.is_empty() || selection != SectionSelection::Standard(names) {
+            return Err(invalid());
+        }
+        Ok(selection)
+    }
+}
+
+/// The parameters of a signature, as encoded in its envelope.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Parameters {
+    hash_alg: HashAlg,
+    context: Option<String>,
+    ad: Option<EmbeddedAd>,
+    sections: SectionSelection,
+    merkle: Option<Vec<MerkleLeaf>>,
+    certificates: Vec<Vec<u8>>,
+    timestamp: Option<Vec<u8>>,
+    log_proof: Option<tlog::InclusionProof>,
+}
+
+fn write_extension(extensions: &mut Vec<u8>, tag: u8, value: &[u8]) {
+    extensions.push(tag);
+    extensions
+        .write_u32::<LittleEndian>(value.len() as u32)
+        .expect("Unable to serialize");
+    extensions.extend_from_slice(value);
+}
+
+fn encode_certificates(certificates: &[Vec<u8>]) -> Vec<u8> {
+    let mut bytes = vec![];
+    bytes
+        .write_u32::<LittleEndian>(certificates.len() as u32)
+        .expect("Unable to serialize");
+    for certificate in certificates {
+        bytes
+            .write_u32::<LittleEndian>(certificate.len() as u32)
+            .expect("Unable to serialize");
+        bytes.extend_from_slice(certificate);
+    }
+    bytes
+}
+
+fn decode_certificates(mut bytes: &[u8]) -> Result<Vec<Vec<u8>>, WError> {
+    let invalid = || WError::ParseError("Invalid certificate chain".to_string());
+    if bytes.len() < 4 {
+        return Err(invalid());
+    }
+    let count = LittleEndian::read_u32(&bytes[..4]) as usize;
+    bytes = &bytes[4..];
+    let mut certificates = vec![];
+    for _ in 0..count {
+        if bytes.len() < 4 {
+            return Err(invalid());
+        }
+        let len = LittleEndian::read_u32(&bytes[..4]) as usize;
+        bytes = &bytes[4..];
+        if bytes.len() < len {
+            return Err(invalid());
+        }
+        certificates.push(bytes[..len].to_vec());
+        bytes = &bytes[len..];
+    }
+    if certificates.is_empty() || !bytes.is_empty() {
+        return Err(invalid());
+    }
+    Ok(certificates)
+}
+
+fn encode_envelope(alg_id: u32, parameters: &Parameters, payload: &[u8]) -> Vec<u8> {
+    let mut extensions: Vec<u8> = vec![];
+    if parameters.hash_alg != HashAlg::default() {
+        write_extension(&mut extensions, EXT_HASH_ALG, &[parameters.hash_alg.id()]);
+    }
+    if let Some(context) = &parameters.context {
+        write_extension(&mut extensions, EXT_CONTEXT, context.as_bytes());
+    }
+    match &parameters.ad {
+        None => {}
+        Some(EmbeddedAd::Data(data)) => write_extension(&mut extensions, EXT_AD, data),
+        Some(EmbeddedAd::Hash(hash_alg, hash)) => {
+            let mut value = vec![hash_alg.id()];
+            value.extend_from_slice(hash);
+            write_extension(&mut extensions, EXT_AD_HASH, &value)
+        }
+    }
+    if parameters.sections != SectionSelection::All {
+        write_extension(&mut extensions, EXT_SECTIONS, &parameters.sections.to_bytes());
+    }
+    if let Some(leaves) = &parameters.merkle {
+        write_extension(&mut extensions, EXT_MERKLE, &merkle::encode_leaves(leaves));
+    }
+    if !parameters.certificates.is_empty() {
+        write_extension(
+            &mut extensions,
+            EXT_CERTIFICATES,
+            &encode_certificates(&parameters.certificates),
+        );
+    }
+    if let Some(timestamp) = &parameters.timestamp {
+        write_extension(&mut extensions, EXT_TIMESTAMP, timestamp);
+    }
+    if let Some(log_proof) = &parameters.log_proof {
+        write_extension(&mut extensions, EXT_LOG_PROOF, &log_proof.to_bytes());
+    }
+    let mut bytes: Vec<u8> = vec![];
+    if extensions.is_empty() {
+        bytes
+            .write_u32::<LittleEndian>(alg_id)
+            .expect("Unable to serialize");
+    } else {
+        bytes
+            .write_u32::<LittleEndian>(alg_id | EXTENDED_ENVELOPE)
+            .expect("Unable to serialize");
+        bytes
+            .write_u32::<LittleEndian>(extensions.len() as u32)
+            .expect("Unable to serialize");
+        bytes.extend_from_slice(&extensions);
+    }
+    bytes.extend_from_slice(payload);
+    bytes
+}
+
+fn decode_envelope(bytes: &[u8]) -> Result<(u32, Parameters, Vec<u8>), WError> {
+    if bytes.len() <= 4 {
+        return Err(WError::ParseError("Short encoded envelope".to_string()));
+    }
+    let alg_id = LittleEndian::read_u32(&bytes[..4]);
+    let mut parameters = Parameters::default();
+    if alg_id & EXTENDED_ENVELOPE == 0 {
+        return Ok((alg_id, parameters, bytes[4..].to_vec()));
+    }
+    let bytes = &bytes[4..];
+    if bytes.len() < 4 {
+        return Err(WError::ParseError("Short encoded envelope".to_string()));
+    }
+    let extensions_len = LittleEndian::read_u32(&bytes[..4]) as usize;
+    let bytes = &bytes[4..];
+    if bytes.len() <= extensions_len {
+        return Err(WError::ParseError("Short encoded envelope".to_string()));
+    }
+    let (mut extensions, payload) = bytes.split_at(extensions_len);
+    while !extensions.is_empty() {
+        if extensions.len() < 5 {
+            return Err(WError::ParseError("Truncated envelope extension".to_string()));
+        }
+        let tag = extensions[0];
+        let len = LittleEndian::read_u32(&extensions[1..5]) as usize;
+        extensions = &extensions[5..];
+        if extensions.len() < len {
+            return Err(WError::ParseError("Truncated envelope extension".to_string()));
+        }
+        let (value, rest) = extensions.split_at(len);
+        extensions = rest;
+        match (tag, value) {
+            (EXT_HASH_ALG, &[id]) => parameters.hash_alg = HashAlg::from_id(id)?,
+            (EXT_CONTEXT, context) => {
+                let context = std::str::from_utf8(context)
+                    .map_err(|_| WError::ParseError("Invalid context".to_string()))?;
+                check_context(Some(context))?;
+                if context == DEFAULT_CONTEXT {
+                    return Err(WError::ParseError("Redundant context".to_string()));
+                }
+                parameters.context = Some(context.to_string());
+            }
+            (EXT_AD, data) if !data.is_empty() => {
+                parameters.ad = Some(EmbeddedAd::Data(data.to_vec()))
+            }
+            (EXT_AD_HASH, value) if value.len() > 1 => {
+                let hash_alg = HashAlg::from_id(value[0])?;
+                parameters.ad = Some(EmbeddedAd::Hash(hash_alg, value[1..].to_vec()))
+            }
+            (EXT_SECTIONS, value) => parameters.sections = SectionSelection::from_bytes(value)?,
+            (EXT_MERKLE, value) => parameters.merkle = Some(merkle::decode_leaves(value)?),
+            (EXT_CERTIFICATES, value) => parameters.certificates = decode_certificates(value)?,
+            (EXT_TIMESTAMP, value) if !value.is_empty() => {
+                parameters.timestamp = Some(value.to_vec())
+            }
+            (EXT_LOG_PROOF, value) => {
+                parameters.log_proof = Some(tlog::InclusionProof::from_bytes(value)?)
+            }
+            _ => {
+                return Err(WError::ParseError(format!(
+                    "Unsupported envelope extension 0x{:02x}",
+                    tag
+                )))
+            }
+        }
+    }
+    Ok((alg_id & !EXTENDED_ENVELOPE, parameters, payload.to_vec()))
+}
+
+pub fn check_context(context: Option<&str>) -> Result<(), WError> {
+    match context {
+        Some(context) if context.is_empty() || context.len() > MAX_CONTEXT_LENGTH => Err(
+            WError::UsageError("The context must be between 1 and 255 bytes long"),
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Accessors for the parameters shared by signatures and signing requests.
+pub trait WithParameters: Sized {
+    fn parameters(&self) -> &Parameters;
+    fn parameters_mut(&mut self) -> &mut Parameters;
+
+    fn with_hash_alg(mut self, hash_alg: HashAlg) -> Self {
+        self.parameters_mut().hash_alg = hash_alg;
+        self
+    }
+
+    fn with_context(mut self, context: Option<&str>) -> Self {
+        self.parameters_mut().context = context
+            .filter(|&context| context != DEFAULT_CONTEXT)
+            .map(String::from);
+        self
+    }
+
+    fn with_ad(mut self, ad: &AdditionalData) -> Self {
+        let parameters = self.parameters_mut();
+        parameters.ad = EmbeddedAd::new(ad, parameters.hash_alg);
+        self
+    }
+
+    fn with_sections(mut self, sections: &SectionSelection) -> Self {
+        self.parameters_mut().sections = sections.clone();
+        self
+    }
+
+    fn with_merkle_leaves(mut self, leaves: &[MerkleLeaf]) -> Self {
+        self.parameters_mut().merkle = Some(leaves.to_vec());
+        self
+    }
+
+    /// Adds a certificate chain, starting with the signer's certificate.
+    fn with_certificates(mut self, certificates: &[Vec<u8>]) -> Self {
+        self.parameters_mut().certificates = certificates.to_vec();
+        self
+    }
+
+    fn hash_alg(&self) -> HashAlg {
+        self.parameters().hash_alg
+    }
+
+    fn context(&self) -> Option<&str> {
+        self.parameters().context.as_deref()
+    }
+
+    fn embedded_ad(&self) -> Option<&EmbeddedAd> {
+        self.parameters().ad.as_ref()
+    }
+
+    fn sections(&self) -> &SectionSelection {
+        &self.parameters().sections
+    }
+
+    fn merkle_leaves(&self) -> Option<&[MerkleLeaf]> {
+        self.parameters().merkle.as_deref()
+    }
+
+    fn certificates(&self) -> &[Vec<u8>] {
+        &self.parameters().certificates
+    }
+}
 
 #[derive(Debug)]
 pub struct Signature {
     alg_id: u32,
+    parameters: Parameters,
     raw: Vec<u8>,
 }
 
 impl Signature {
     pub fn new(alg_id: u32, raw: Vec<u8>) -> Self {
-        Signature { alg_id, raw }
+        Signature {
+            alg_id,
+            parameters: Parameters::default(),
+            raw,
+        }
+    }
+
+
+    /// Adds an RFC 3161 time-stamp token over the raw signature.
+    pub fn with_timestamp(mut self, timestamp: Option<&[u8]>) -> Self {
+        self.parameters.timestamp = timestamp.map(|timestamp| timestamp.to_vec());
+        self
+    }
+
+    /// Adds the proof that the signing event was recorded in a transparency log.
+    pub fn with_log_proof(mut self, log_proof: Option<&tlog::InclusionProof>) -> Self {
+        self.parameters.log_proof = log_proof.cloned();
+        self
+    }
+
+    pub fn length(
+        signature_alg: &dyn SignatureAlg,
+        hash_alg: HashAlg,
+        context: Option<&str>,
+        ad: &AdditionalData,
+    ) -> usize {
+        let raw = vec![0; signature_alg.raw_signature_length()];
+        Signature::new(signature_alg.alg_id(), raw)
+            .with_hash_alg(hash_alg)
+            .with_context(context)
+            .with_ad(ad)
+            .to_bytes()
+            .len()
+    }
+
+    pub fn alg_id(&self) -> u32 {
+        self.alg_id
+    }
+
+    /// The RFC 3161 time-stamp token over the raw signature, if any.
+    pub fn timestamp(&self) -> Option<&[u8]> {
+        self.parameters.timestamp.as_deref()
     }
 
-    pub fn length(signature_alg: &Box<dyn SignatureAlg>) -> usize {
-        4 + signature_alg.raw_signature_length()
+    pub fn log_proof(&self) -> Option<&tlog::InclusionProof> {
+        self.parameters.log_proof.as_ref()
+    }
+
+    pub fn raw(&self) -> &[u8] {
+        &self.raw
     }
 
     pub fn to_bytes(&self) -> Vec<u8> {
-        let mut bytes: Vec<u8> = vec![];
-        bytes
-            .write_u32::<LittleEndian>(self.alg_id)
-            .expect("Unable to serialize");
-        bytes.extend_from_slice(&self.raw);
-        bytes
+        encode_envelope(self.alg_id, &self.parameters, &self.raw)
     }
 
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, WError> {
-        let bytes_len = bytes.len();
-        if bytes_len <= 4 {
-            return Err(WError::ParseError("Short encoded signature".to_string()));
-        }
-        let alg_id = LittleEndian::read_u32(&bytes[..4]);
-        let raw = bytes[4..].to_vec();
-        Ok(Signature { alg_id, raw })
+        let (alg_id, parameters, raw) = decode_envelope(bytes)
+            .map_err(|_| WError::ParseError("Invalid encoded signature".to_string()))?;
+        Ok(Signature {
+            alg_id,
+            parameters,
+            raw,
+        })
     }
 
     pub fn to_alg(&self) -> Result<Box<dyn SignatureAlg>, WError> {
-        match self.alg_id {
-            eddsa::ALG_ID => Ok(Box::new(EdDSA)),
-            _ => Err(WError::SignatureError("Unsupported signature scheme")),
+        alg_from_id(self.alg_id)
+    }
+}
+
+impl WithParameters for Signature {
+    fn parameters(&self) -> &Parameters {
+        &self.parameters
+    }
+
+    fn parameters_mut(&mut self) -> &mut Parameters {
+        &mut self.parameters
+    }
+}
+
+#[derive(Debug)]
+pub struct SigningRequest {
+    alg_id: u32,
+    parameters: Parameters,
+    digest: Vec<u8>,
+}
+
+impl SigningRequest {
+    pub fn new(alg_id: u32, digest: Vec<u8>) -> Self {
+        SigningRequest {
+            alg_id,
+            parameters: Parameters::default(),
+            digest,
         }
     }
+
+
+    pub fn alg_id(&self) -> u32 {
+        self.alg_id
+    }
+
+    pub fn digest(&self) -> &[u8] {
+        &self.digest
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encode_envelope(self.alg_id, &self.parameters, &self.digest)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, WError> {
+        let (alg_id, parameters, digest) = decode_envelope(bytes)
+            .map_err(|_| WError::ParseError("Invalid encoded signing request".to_string()))?;
+        Ok(SigningRequest {
+            alg_id,
+            parameters,
+            digest,
+        })
+    }
+}
+
+impl WithParameters for SigningRequest {
+    fn parameters(&self) -> &Parameters {
+        &self.parameters
+    }
+
+    fn parameters_mut(&mut self) -> &mut Parameters {
+        &mut self.parameters
+    }
+}
+
+pub fn alg_from_id(alg_id: u32) -> Result<Box<dyn SignatureAlg>, WError> {
+    match alg_id {
+        eddsa::ALG_ID => Ok(Box::new(EdDSA)),
+        ecdsa::P256_ALG_ID => Ok(Box::new(Ecdsa::P256)),
+        ecdsa::P384_ALG_ID => Ok(Box::new(Ecdsa::P384)),
+        _ => Err(WError::SignatureError("Unsupported signature scheme")),
+    }
 }
 
 pub struct AnyKey {
@@ -82,14 +578,11 @@ pub trait Key {
     }
 
     fn to_alg(&self) -> Result<Box<dyn SignatureAlg>, WError> {
-        match self.alg_id() {
-            eddsa::ALG_ID => Ok(Box::new(EdDSA)),
-            _ => Err(WError::SignatureError("Unsupported signature scheme")),
-        }
+        alg_from_id(self.alg_id())
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct PublicKey {
     alg_id: u32,
     raw: Vec<u8>,
@@ -116,6 +609,10 @@ impl PublicKey {
         PublicKey { alg_id, raw }
     }
 
+    pub fn key_id(&self) -> Vec<u8> {
+        Hash::hash(self.to_bytes())[..KEY_ID_BYTES].to_vec()
+    }
+
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, WError> {
         let key = Self::anykey_from_bytes(bytes)?;
         Ok(PublicKey {
@@ -154,12 +651,31 @@ pub struct KeyPair {
     alg_id: u32,
     pub pk: PublicKey,
     pub sk: SecretKey,
+    deterministic: bool,
 }
 
 impl KeyPair {
     pub fn new(alg_id: u32, pk: PublicKey, sk: SecretKey) -> Self {
         assert!(pk.alg_id() == sk.alg_id());
-        KeyPair { alg_id, pk, sk }
+        KeyPair {
+            alg_id,
+            pk,
+            sk,
+            deterministic: false,
+        }
+    }
+
+    pub fn alg_id(&self) -> u32 {
+        self.alg_id
+    }
+
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic
     }
 }
 
@@ -167,13 +683,91 @@ pub trait SignatureAlg {
     fn alg_id(&self) -> u32;
     fn raw_signature_length(&self) -> usize;
     fn keygen(&self) -> KeyPair;
-    fn sign(&self, data: &[u8], ad: Option<&[u8]>, key_pair: &KeyPair)
-        -> Result<Signature, WError>;
+    fn keygen_from_seed(&self, seed: &[u8]) -> Result<KeyPair, WError>;
+    fn public_key(&self, sk: &SecretKey) -> Result<PublicKey, WError>;
+    fn prehash(
+        &self,
+        data: &[u8],
+        ad: &AdditionalData,
+        hash_alg: HashAlg,
+        context: Option<&str>,
+    ) -> Result<Vec<u8>, WError>;
+    fn sign_prehashed(&self, prehashed: &[u8], key_pair: &KeyPair) -> Result<Vec<u8>, WError>;
+    fn sign(
+        &self,
+        data: &[u8],
+        ad: &AdditionalData,
+        hash_alg: HashAlg,
+        context: Option<&str>,
+        key_pair: &KeyPair,
+    ) -> Result<Signature, WError>;
     fn verify(
         &self,
         data: &[u8],
-        ad: Option<&[u8]>,
+        ad: &AdditionalData,
+        pk: &[u8],
+        signature: &Signature,
+    ) -> Result<(), WError>;
+    fn verify_prehashed(
+        &self,
+        prehashed: &[u8],
         pk: &[u8],
         signature: &Signature,
     ) -> Result<(), WError>;
 }
+
+pub trait Signer {
+    fn alg_id(&self) -> u32;
+    fn sign_prehashed(&self, prehashed: &[u8]) -> Result<Vec<u8>, WError>;
+
+    /// The public key of the signer, if it can be retrieved.
+    fn public_key(&self) -> Option<PublicKey> {
+        None
+    }
+
+    fn sign(
+        &self,
+        data: &[u8],
+        ad: &AdditionalData,
+        hash_alg: HashAlg,
+        context: Option<&str>,
+    ) -> Result<Signature, WError> {
+        let signature_alg = alg_from_id(self.alg_id())?;
+        let prehashed = signature_alg.prehash(data, ad, hash_alg, context)?;
+        let request = SigningRequest::new(self.alg_id(), prehashed)
+            .with_hash_alg(hash_alg)
+            .with_context(context)
+            .with_ad(ad);
+        self.sign_request(&request)
+    }
+
+    fn sign_request(&self, request: &SigningRequest) -> Result<Signature, WError> {
+        if request.alg_id() != self.alg_id() {
+            return Err(WError::SignatureError(
+                "Signing request uses a different scheme than the signer",
+            ));
+        }
+        let signature_alg = alg_from_id(self.alg_id())?;
+        let raw = self.sign_prehashed(request.digest())?;
+        if raw.len() != signature_alg.raw_signature_length() {
+            return Err(WError::SignatureError("Unexpected signature length"));
+        }
+        let mut signature = Signature::new(self.alg_id(), raw);
+        signature.parameters = request.parameters.clone();
+        Ok(signature)
+    }
+}
+
+impl Signer for KeyPair {
+    fn alg_id(&self) -> u32 {
+        self.alg_id
+    }
+
+    fn sign_prehashed(&self, prehashed: &[u8]) -> Result<Vec<u8>, WError> {
+        self.sk.to_alg()?.sign_prehashed(prehashed, self)
+    }
+
+    fn public_key(&self) -> Option<PublicKey> {
+        Some(self.pk.clone())
+    }
+}
@@ -0,0 +1,537 @@
+//! Signing with a key stored on a PKCS#11 token: Ed25519 keys (CKK_EC_EDWARDS,
+//! CKM_EDDSA), and ECDSA keys over P-256 or P-384 (CKK_EC, CKM_ECDSA).
+//!
+//! For ECDSA, the prehashed message is hashed on the host, and the token signs
+//! the resulting digest.
+
+use super::*;
+use libloading::Library;
+use std::fs;
+use std::os::raw::{c_uchar, c_ulong, c_void};
+use std::ptr;
+
+type CkRv = c_ulong;
+type CkUlong = c_ulong;
+type CkSlotId = c_ulong;
+type CkSessionHandle = c_ulong;
+type CkObjectHandle = c_ulong;
+
+const CKR_OK: CkRv = 0x0000_0000;
+const CKR_USER_ALREADY_LOGGED_IN: CkRv = 0x0000_0100;
+const CKR_CRYPTOKI_ALREADY_INITIALIZED: CkRv = 0x0000_0191;
+
+const CKF_SERIAL_SESSION: CkUlong = 0x0000_0004;
+const CKU_USER: CkUlong = 1;
+
+const CKA_CLASS: CkUlong = 0x0000_0000;
+const CKA_LABEL: CkUlong = 0x0000_0003;
+const CKA_KEY_TYPE: CkUlong = 0x0000_0100;
+const CKA_ID: CkUlong = 0x0000_0102;
+const CKA_EC_PARAMS: CkUlong = 0x0000_0180;
+const CKA_EC_POINT: CkUlong = 0x0000_0181;
+
+const CKO_PUBLIC_KEY: CkUlong = 2;
+const CKO_PRIVATE_KEY: CkUlong = 3;
+const CKK_EC: CkUlong = 0x0000_0003;
+const CKK_EC_EDWARDS: CkUlong = 0x0000_0040;
+const CKM_ECDSA: CkUlong = 0x0000_1041;
+const CKM_EDDSA: CkUlong = 0x0000_1057;
+
+// DER-encoded curve identifiers, as found in CKA_EC_PARAMS
+const CURVE_P256: &[u8] = &[0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07];
+const CURVE_P384: &[u8] = &[0x06, 0x05, 0x2b, 0x81, 0x04, 0x00, 0x22];
+
+#[repr(C)]
+struct CkVersion {
+    major: c_uchar,
+    minor: c_uchar,
+}
+
+#[repr(C)]
+struct CkTokenInfo {
+    label: [c_uchar; 32],
+    manufacturer_id: [c_uchar; 32],
+    model: [c_uchar; 16],
+    serial_number: [c_uchar; 16],
+    flags: CkUlong,
+    max_session_count: CkUlong,
+    session_count: CkUlong,
+    max_rw_session_count: CkUlong,
+    rw_session_count: CkUlong,
+    max_pin_len: CkUlong,
+    min_pin_len: CkUlong,
+    total_public_memory: CkUlong,
+    free_public_memory: CkUlong,
+    total_private_memory: CkUlong,
+    free_private_memory: CkUlong,
+    hardware_version: CkVersion,
+    firmware_version: CkVersion,
+    utc_time: [c_uchar; 16],
+}
+
+#[repr(C)]
+struct CkAttribute {
+    type_: CkUlong,
+    value: *mut c_void,
+    value_len: CkUlong,
+}
+
+#[repr(C)]
+struct CkMechanism {
+    mechanism: CkUlong,
+    parameter: *mut c_void,
+    parameter_len: CkUlong,
+}
+
+type Unused = Option<unsafe extern "C" fn()>;
+
+#[repr(C)]
+struct CkFunctionList {
+    version: CkVersion,
+    c_initialize: unsafe extern "C" fn(*mut c_void) -> CkRv,
+    c_finalize: unsafe extern "C" fn(*mut c_void) -> CkRv,
+    c_get_info: Unused,
+    c_get_function_list: Unused,
+    c_get_slot_list: unsafe extern "C" fn(c_uchar, *mut CkSlotId, *mut CkUlong) -> CkRv,
+    c_get_slot_info: Unused,
+    c_get_token_info: unsafe extern "C" fn(CkSlotId, *mut CkTokenInfo) -> CkRv,
+    c_get_mechanism_list: Unused,
+    c_get_mechanism_info: Unused,
+    c_init_token: Unused,
+    c_init_pin: Unused,
+    c_set_pin: Unused,
+    c_open_session: unsafe extern "C" fn(
+        CkSlotId,
+        CkUlong,
+        *mut c_void,
+        *mut c_void,
+        *mut CkSessionHandle,
+    ) -> CkRv,
+    c_close_session: unsafe extern "C" fn(CkSessionHandle) -> CkRv,
+    c_close_all_sessions: Unused,
+    c_get_session_info: Unused,
+    c_get_operation_state: Unused,
+    c_set_operation_state: Unused,
+    c_login: unsafe extern "C" fn(CkSessionHandle, CkUlong, *const c_uchar, CkUlong) -> CkRv,
+    c_logout: Unused,
+    c_create_object: Unused,
+    c_copy_object: Unused,
+    c_destroy_object: Unused,
+    c_get_object_size: Unused,
+    c_get_attribute_value:
+        unsafe extern "C" fn(CkSessionHandle, CkObjectHandle, *mut CkAttribute, CkUlong) -> CkRv,
+    c_set_attribute_value: Unused,
+    c_find_objects_init: unsafe extern "C" fn(CkSessionHandle, *mut CkAttribute, CkUlong) -> CkRv,
+    c_find_objects:
+        unsafe extern "C" fn(CkSessionHandle, *mut CkObjectHandle, CkUlong, *mut CkUlong) -> CkRv,
+    c_find_objects_final: unsafe extern "C" fn(CkSessionHandle) -> CkRv,
+    c_encrypt_init: Unused,
+    c_encrypt: Unused,
+    c_encrypt_update: Unused,
+    c_encrypt_final: Unused,
+    c_decrypt_init: Unused,
+    c_decrypt: Unused,
+    c_decrypt_update: Unused,
+    c_decrypt_final: Unused,
+    c_digest_init: Unused,
+    c_digest: Unused,
+    c_digest_update: Unused,
+    c_digest_key: Unused,
+    c_digest_final: Unused,
+    c_sign_init: unsafe extern "C" fn(CkSessionHandle, *mut CkMechanism, CkObjectHandle) -> CkRv,
+    c_sign: unsafe extern "C" fn(
+        CkSessionHandle,
+        *const c_uchar,
+        CkUlong,
+        *mut c_uchar,
+        *mut CkUlong,
+    ) -> CkRv,
+}
+
+fn check(rv: CkRv, what: &'static str) -> Result<(), WError> {
+    match rv {
+        CKR_OK => Ok(()),
+        _ => Err(WError::SignatureError(what)),
+    }
+}
+
+#[derive(Default, Debug)]
+pub struct Pkcs11Uri {
+    pub module_path: Option<String>,
+    pub token: Option<String>,
+    pub slot_id: Option<u64>,
+    pub object: Option<String>,
+    pub id: Option<Vec<u8>>,
+    pub pin: Option<String>,
+}
+
+fn percent_decode(s: &str) -> Result<Vec<u8>, WError> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = s
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                .ok_or_else(|| WError::ParseError("Invalid escape in PKCS#11 URI".to_string()))?;
+            decoded.push(hex);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Ok(decoded)
+}
+
+fn percent_decode_str(s: &str) -> Result<String, WError> {
+    String::from_utf8(percent_decode(s)?)
+        .map_err(|_| WError::ParseError("Invalid UTF-8 in PKCS#11 URI".to_string()))
+}
+
+impl Pkcs11Uri {
+    pub fn parse(uri: &str) -> Result<Self, WError> {
+        let uri = uri
+            .strip_prefix("pkcs11:")
+            .ok_or(WError::UsageError("PKCS#11 URIs must start with pkcs11:"))?;
+        let (path, query) = match uri.find('?') {
+            None => (uri, ""),
+            Some(i) => (&uri[..i], &uri[i + 1..]),
+        };
+        let mut parsed = Pkcs11Uri::default();
+        for attr in path.split(';').chain(query.split('&')) {
+            if attr.is_empty() {
+                continue;
+            }
+            let (name, value) = match attr.find('=') {
+                None => {
+                    return Err(WError::ParseError(format!(
+                        "Invalid PKCS#11 URI attribute: {}",
+                        attr
+                    )))
+                }
+                Some(i) => (&attr[..i], &attr[i + 1..]),
+            };
+            match name {
+                "token" => parsed.token = Some(percent_decode_str(value)?),
+                "object" => parsed.object = Some(percent_decode_str(value)?),
+                "id" => parsed.id = Some(percent_decode(value)?),
+                "slot-id" => {
+                    parsed.slot_id = Some(value.parse().map_err(|_| {
+                        WError::ParseError("Invalid slot-id in PKCS#11 URI".to_string())
+                    })?)
+                }
+                "module-path" => parsed.module_path = Some(percent_decode_str(value)?),
+                "pin-value" => parsed.pin = Some(percent_decode_str(value)?),
+                "pin-source" => {
+                    let path = percent_decode_str(value)?;
+                    let path = path.strip_prefix("file:").unwrap_or(&path);
+                    parsed.pin = Some(fs::read_to_string(path)?.trim_end().to_string());
+                }
+                _ => {}
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+pub struct Pkcs11Signer {
+    functions: *const CkFunctionList,
+    finalize: bool,
+    session: Option<CkSessionHandle>,
+    key: CkObjectHandle,
+    alg_id: u32,
+    pk: Option<PublicKey>,
+    _library: Library,
+}
+
+impl Pkcs11Signer {
+    pub fn open(uri: &str) -> Result<Self, WError> {
+        let uri = Pkcs11Uri::parse(uri)?;
+        let module_path = uri.module_path.as_ref().ok_or(WError::UsageError(
+            "The PKCS#11 URI must include a module-path",
+        ))?;
+        let library = unsafe { Library::new(module_path) }
+            .map_err(|_| WError::UsageError("Unable to load the PKCS#11 module"))?;
+        let functions = unsafe {
+            let get_function_list = library
+                .get::<unsafe extern "C" fn(*mut *const CkFunctionList) -> CkRv>(
+                    b"C_GetFunctionList\0",
+                )
+                .map_err(|_| WError::UsageError("Not a PKCS#11 module"))?;
+            let mut functions = ptr::null();
+            check(
+                get_function_list(&mut functions),
+                "C_GetFunctionList failed",
+            )?;
+            functions
+        };
+        if functions.is_null() {
+            return Err(WError::UsageError("Not a PKCS#11 module"));
+        }
+        let f = unsafe { &*functions };
+        let rv = unsafe { (f.c_initialize)(ptr::null_mut()) };
+        if rv != CKR_CRYPTOKI_ALREADY_INITIALIZED {
+            check(rv, "C_Initialize failed")?;
+        }
+        // From here, dropping the signer releases what was acquired so far
+        let mut signer = Pkcs11Signer {
+            functions,
+            finalize: rv == CKR_OK,
+            session: None,
+            key: 0,
+            alg_id: eddsa::ALG_ID,
+            pk: None,
+            _library: library,
+        };
+        let slot = signer.find_slot(&uri)?;
+        let f = unsafe { &*signer.functions };
+        let mut session: CkSessionHandle = 0;
+        check(
+            unsafe {
+                (f.c_open_session)(
+                    slot,
+                    CKF_SERIAL_SESSION,
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    &mut session,
+                )
+            },
+            "C_OpenSession failed",
+        )?;
+        signer.session = Some(session);
+        if let Some(pin) = &uri.pin {
+            let rv = unsafe { (f.c_login)(session, CKU_USER, pin.as_ptr(), pin.len() as CkUlong) };
+            if rv != CKR_USER_ALREADY_LOGGED_IN {
+                check(rv, "C_Login failed")?;
+            }
+        }
+        signer.key = signer
+            .find_object(CKO_PRIVATE_KEY, uri.object.as_deref(), uri.id.as_deref())?
+            .ok_or(WError::UsageError("Private key not found on the token"))?;
+        signer.alg_id = signer.key_alg_id()?;
+        // The public key is the one with the same CKA_ID as the private key
+        let key_id = signer.get_attribute(signer.key, CKA_ID)?;
+        let pk_object = match key_id.is_empty() {
+            true => None,
+            false => signer.find_object(CKO_PUBLIC_KEY, None, Some(&key_id))?,
+        };
+        if let Some(pk_object) = pk_object {
+            let ec_point = signer.get_attribute(pk_object, CKA_EC_POINT)?;
+            // Raw, or wrapped in a DER octet string
+            let len = match Ecdsa::from_alg_id(signer.alg_id) {
+                None => 32,
+                Some(curve) => 1 + 2 * curve.field_length(),
+            };
+            let raw = match ec_point.len() {
+                l if l == len => &ec_point[..],
+                l if l == len + 2 && ec_point[0] == 0x04 && ec_point[1] as usize == len => {
+                    &ec_point[2..]
+                }
+                _ => {
+                    return Err(WError::ParseError(
+                        "Unexpected public key encoding".to_string(),
+                    ))
+                }
+            };
+            let pk = PublicKey::new(signer.alg_id, raw.to_vec());
+            signer.check_public_key(&pk)?;
+            signer.pk = Some(pk);
+        }
+        Ok(signer)
+    }
+
+    /// Checks that a public key belongs to the private key, by signing a random challenge.
+    fn check_public_key(&self, pk: &PublicKey) -> Result<(), WError> {
+        let mut challenge = [0u8; 32];
+        getrandom::getrandom(&mut challenge).map_err(|_| WError::InternalError("RNG failure"))?;
+        let raw = self.sign_prehashed(&challenge)?;
+        alg_from_id(self.alg_id)?
+            .verify_prehashed(&challenge, pk.raw(), &Signature::new(self.alg_id, raw))
+            .map_err(|_| {
+                WError::UsageError("The public key on the token doesn't match the private key")
+            })
+    }
+
+    /// The signature scheme of the private key.
+    fn key_alg_id(&self) -> Result<u32, WError> {
+        let key_type = self.get_attribute(self.key, CKA_KEY_TYPE)?;
+        if key_type == (CKK_EC_EDWARDS as c_ulong).to_ne_bytes() {
+            return Ok(eddsa::ALG_ID);
+        }
+        if key_type != (CKK_EC as c_ulong).to_ne_bytes() {
+            return Err(WError::UsageError(
+                "Only Ed25519 and ECDSA keys are supported on PKCS#11 tokens",
+            ));
+        }
+        match self.get_attribute(self.key, CKA_EC_PARAMS)?.as_slice() {
+            CURVE_P256 => Ok(ecdsa::P256_ALG_ID),
+            CURVE_P384 => Ok(ecdsa::P384_ALG_ID),
+            _ => Err(WError::UsageError(
+                "Only P-256 and P-384 ECDSA keys are supported on PKCS#11 tokens",
+            )),
+        }
+    }
+
+    fn session(&self) -> CkSessionHandle {
+        self.session.expect("PKCS#11 session")
+    }
+
+    fn find_slot(&self, uri: &Pkcs11Uri) -> Result<CkSlotId, WError> {
+        if let Some(slot_id) = uri.slot_id {
+            return Ok(slot_id as CkSlotId);
+        }
+        let f = unsafe { &*self.functions };
+        let mut count: CkUlong = 0;
+        check(
+            unsafe { (f.c_get_slot_list)(1, ptr::null_mut(), &mut count) },
+            "C_GetSlotList failed",
+        )?;
+        let mut slots = vec![0 as CkSlotId; count as usize];
+        check(
+            unsafe { (f.c_get_slot_list)(1, slots.as_mut_ptr(), &mut count) },
+            "C_GetSlotList failed",
+        )?;
+        slots.truncate(count as usize);
+        for slot in slots {
+            let token = match &uri.token {
+                None => return Ok(slot),
+                Some(token) => token,
+            };
+            let mut info: CkTokenInfo = unsafe { std::mem::zeroed() };
+            check(
+                unsafe { (f.c_get_token_info)(slot, &mut info) },
+                "C_GetTokenInfo failed",
+            )?;
+            let label = String::from_utf8_lossy(&info.label);
+            if label.trim_end() == token {
+                return Ok(slot);
+            }
+        }
+        Err(WError::UsageError("PKCS#11 token not found"))
+    }
+
+    fn find_object(
+        &self,
+        class: CkUlong,
+        label: Option<&str>,
+        id: Option<&[u8]>,
+    ) -> Result<Option<CkObjectHandle>, WError> {
+        let f = unsafe { &*self.functions };
+        let mut class = class;
+        let mut template = vec![CkAttribute {
+            type_: CKA_CLASS,
+            value: &mut class as *mut CkUlong as *mut c_void,
+            value_len: std::mem::size_of::<CkUlong>() as CkUlong,
+        }];
+        let mut label = label.map(|label| label.as_bytes().to_vec());
+        if let Some(label) = label.as_mut() {
+            template.push(CkAttribute {
+                type_: CKA_LABEL,
+                value: label.as_mut_ptr() as *mut c_void,
+                value_len: label.len() as CkUlong,
+            });
+        }
+        let mut id = id.map(|id| id.to_vec());
+        if let Some(id) = id.as_mut() {
+            template.push(CkAttribute {
+                type_: CKA_ID,
+                value: id.as_mut_ptr() as *mut c_void,
+                value_len: id.len() as CkUlong,
+            });
+        }
+        check(
+            unsafe {
+                (f.c_find_objects_init)(
+                    self.session(),
+                    template.as_mut_ptr(),
+                    template.len() as CkUlong,
+                )
+            },
+            "C_FindObjectsInit failed",
+        )?;
+        let mut object: CkObjectHandle = 0;
+        let mut count: CkUlong = 0;
+        let rv = unsafe { (f.c_find_objects)(self.session(), &mut object, 1, &mut count) };
+        unsafe { (f.c_find_objects_final)(self.session()) };
+        check(rv, "C_FindObjects failed")?;
+        Ok(if count == 0 { None } else { Some(object) })
+    }
+
+    fn get_attribute(&self, object: CkObjectHandle, type_: CkUlong) -> Result<Vec<u8>, WError> {
+        let f = unsafe { &*self.functions };
+        let mut attribute = CkAttribute {
+            type_,
+            value: ptr::null_mut(),
+            value_len: 0,
+        };
+        check(
+            unsafe { (f.c_get_attribute_value)(self.session(), object, &mut attribute, 1) },
+            "C_GetAttributeValue failed",
+        )?;
+        let mut value = vec![0u8; attribute.value_len as usize];
+        attribute.value = value.as_mut_ptr() as *mut c_void;
+        check(
+            unsafe { (f.c_get_attribute_value)(self.session(), object, &mut attribute, 1) },
+            "C_GetAttributeValue failed",
+        )?;
+        value.truncate(attribute.value_len as usize);
+        Ok(value)
+    }
+}
+
+impl Signer for Pkcs11Signer {
+    fn alg_id(&self) -> u32 {
+        self.alg_id
+    }
+
+    fn sign_prehashed(&self, prehashed: &[u8]) -> Result<Vec<u8>, WError> {
+        let f = unsafe { &*self.functions };
+        let (mechanism, message) = match Ecdsa::from_alg_id(self.alg_id) {
+            None => (CKM_EDDSA, prehashed.to_vec()),
+            Some(curve) => (CKM_ECDSA, curve.digest(prehashed)),
+        };
+        let mut mechanism = CkMechanism {
+            mechanism,
+            parameter: ptr::null_mut(),
+            parameter_len: 0,
+        };
+        check(
+            unsafe { (f.c_sign_init)(self.session(), &mut mechanism, self.key) },
+            "C_SignInit failed",
+        )?;
+        let mut raw = vec![0u8; alg_from_id(self.alg_id)?.raw_signature_length()];
+        let mut raw_len = raw.len() as CkUlong;
+        check(
+            unsafe {
+                (f.c_sign)(
+                    self.session(),
+                    message.as_ptr(),
+                    message.len() as CkUlong,
+                    raw.as_mut_ptr(),
+                    &mut raw_len,
+                )
+            },
+            "C_Sign failed",
+        )?;
+        raw.truncate(raw_len as usize);
+        Ok(raw)
+    }
+
+    fn public_key(&self) -> Option<PublicKey> {
+        self.pk.clone()
+    }
+}
+
+impl Drop for Pkcs11Signer {
+    fn drop(&mut self) {
+        let f = unsafe { &*self.functions };
+        if let Some(session) = self.session {
+            unsafe { (f.c_close_session)(session) };
+        }
+        if self.finalize {
+            unsafe { (f.c_finalize)(ptr::null_mut()) };
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! External signer plugins.
+//!
+//! A plugin is an executable spawned once per request. It receives a
+//! request on its standard input, as ASCII lines terminated by `\n`, and the
+//! standard input is closed after the last line.
+//!
+//! Before signing, the plugin is asked for the key it signs with:
+//!
+//! ```text
+//! wasmsign-signer 1
+//! request info
+//! ```
+//!
+//! The plugin must respond with the algorithm identifier and, optionally, the
+//! raw public key:
+//!
+//! ```text
+//! alg-id <algorithm identifier, 8 hex digits>
+//! public-key <raw public key, hex>
+//! ```
+//!
+//! Then, for every signature:
+//!
+//! ```text
+//! wasmsign-signer 1
+//! request sign
+//! alg-id <algorithm identifier, 8 hex digits>
+//! digest <prehashed message, hex>
+//! ```
+//!
+//! The plugin must sign the digest as-is (for Ed25519, the digest is the
+//! message passed to the signing function) and respond with:
+//!
+//! ```text
+//! signature <raw signature, hex>
+//! ```
+//!
+//! If a request cannot be fulfilled, the plugin must respond with:
+//!
+//! ```text
+//! error <message>
+//! ```
+//!
+//! A non-zero exit status is treated as an error as well.
+
+use super::*;
+use ct_codecs::{Decoder, Encoder, Hex};
+use std::ffi::OsString;
+use std::io::prelude::*;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+pub const PROTOCOL_VERSION: u32 = 1;
+
+pub struct PluginSigner {
+    path: PathBuf,
+    args: Vec<OsString>,
+    alg_id: u32,
+    pk: Option<PublicKey>,
+}
+
+fn decode_hex(encoded: &str, what: &str) -> Result<Vec<u8>, WError> {
+    Hex::decode_to_vec(encoded.trim(), None)
+        .map_err(|_| WError::PluginError(format!("invalid {} encoding", what)))
+}
+
+impl PluginSigner {
+    /// Spawns the plugin to retrieve the algorithm and the public key it signs with.
+    pub fn new(path: impl Into<PathBuf>, args: Vec<OsString>) -> Result<Self, WError> {
+        let mut signer = PluginSigner {
+            path: path.into(),
+            args,
+            alg_id: 0,
+            pk: None,
+        };
+        let response = signer.request("request info\n")?;
+        let field = |name: &str| {
+            response
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.as_str())
+        };
+        let alg_id = field("alg-id")
+            .and_then(|alg_id| u32::from_str_radix(alg_id.trim(), 16).ok())
+            .ok_or_else(|| WError::PluginError("missing or invalid alg-id".to_string()))?;
+        alg_from_id(alg_id)?;
+        signer.alg_id = alg_id;
+        if let Some(pk) = field("public-key") {
+            signer.pk = Some(PublicKey::new(alg_id, decode_hex(pk, "public key")?));
+        }
+        Ok(signer)
+    }
+
+    /// Sends a request to the plugin, and returns the fields of its response.
+    fn request(&self, request: &str) -> Result<Vec<(String, String)>, WError> {
+        let mut child = Command::new(&self.path)
+            .args(&self.args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        {
+            let mut stdin = child.stdin.take().expect("stdin");
+            stdin.write_all(format!("wasmsign-signer {}\n", PROTOCOL_VERSION).as_bytes())?;
+            stdin.write_all(request.as_bytes())?;
+        }
+        let output = child.wait_with_output()?;
+        let response = String::from_utf8_lossy(&output.stdout);
+        let fields: Vec<(String, String)> = response
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        if let Some((_, message)) = fields.iter().find(|(key, _)| key == "error") {
+            return Err(WError::PluginError(message.to_string()));
+        }
+        if !output.status.success() {
+            return Err(WError::PluginError(format!(
+                "signer exited with {}",
+                output.status
+            )));
+        }
+        Ok(fields)
+    }
+}
+
+impl Signer for PluginSigner {
+    fn alg_id(&self) -> u32 {
+        self.alg_id
+    }
+
+    fn public_key(&self) -> Option<PublicKey> {
+        self.pk.clone()
+    }
+
+    fn sign_prehashed(&self, prehashed: &[u8]) -> Result<Vec<u8>, WError> {
+        let response = self.request(&format!(
+            "request sign\nalg-id {:08x}\ndigest {}\n",
+            self.alg_id,
+            Hex::encode_to_string(prehashed).map_err(|_| WError::InternalError("hex"))?
+        ))?;
+        let encoded = response
+            .iter()
+            .find(|(key, _)| key == "signature")
+            .map(|(_, value)| value)
+            .ok_or_else(|| WError::PluginError("unexpected response".to_string()))?;
+        decode_hex(encoded, "signature")
+    }
+}
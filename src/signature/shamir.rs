@@ -0,0 +1,191 @@
+use super::*;
+use hmac_sha512::Hash;
+
+const SHARE_VERSION: u8 = 1;
+const CHECKSUM_BYTES: usize = 16;
+
+fn gf256_mul(a: u8, b: u8) -> u8 {
+    let (mut a, mut b, mut p) = (a, b, 0u8);
+    for _ in 0..8 {
+        p ^= a & (b & 1).wrapping_neg();
+        let carry = (a >> 7).wrapping_neg();
+        a = (a << 1) ^ (0x1b & carry);
+        b >>= 1;
+    }
+    p
+}
+
+fn gf256_inv(a: u8) -> u8 {
+    let mut x = a;
+    let mut result = 1u8;
+    for _ in 0..7 {
+        x = gf256_mul(x, x);
+        result = gf256_mul(result, x);
+    }
+    result
+}
+
+fn checksum(bytes: &[u8]) -> Vec<u8> {
+    Hash::hash(bytes)[..CHECKSUM_BYTES].to_vec()
+}
+
+#[derive(Debug)]
+pub struct SecretKeyShare {
+    key_id: Vec<u8>,
+    alg_id: u32,
+    threshold: u8,
+    index: u8,
+    data: Vec<u8>,
+}
+
+impl SecretKeyShare {
+    pub fn key_id(&self) -> &[u8] {
+        &self.key_id
+    }
+
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![SHARE_VERSION];
+        bytes.extend_from_slice(&self.key_id);
+        bytes
+            .write_u32::<LittleEndian>(self.alg_id)
+            .expect("Unable to serialize");
+        bytes.push(self.threshold);
+        bytes.push(self.index);
+        bytes.extend_from_slice(&self.data);
+        let checksum = checksum(&bytes);
+        bytes.extend_from_slice(&checksum);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, WError> {
+        let header_len = 1 + KEY_ID_BYTES + 4 + 1 + 1;
+        if bytes.len() <= header_len + CHECKSUM_BYTES {
+            return Err(WError::ParseError("Short encoded key share".to_string()));
+        }
+        if bytes[0] != SHARE_VERSION {
+            return Err(WError::ParseError(
+                "Unsupported key share version".to_string(),
+            ));
+        }
+        let (bytes, expected_checksum) = bytes.split_at(bytes.len() - CHECKSUM_BYTES);
+        if checksum(bytes) != expected_checksum {
+            return Err(WError::ParseError("Corrupted key share".to_string()));
+        }
+        let key_id = bytes[1..1 + KEY_ID_BYTES].to_vec();
+        let bytes = &bytes[1 + KEY_ID_BYTES..];
+        let alg_id = LittleEndian::read_u32(&bytes[..4]);
+        let (threshold, index) = (bytes[4], bytes[5]);
+        if threshold == 0 || index == 0 {
+            return Err(WError::ParseError("Invalid key share".to_string()));
+        }
+        Ok(SecretKeyShare {
+            key_id,
+            alg_id,
+            threshold,
+            index,
+            data: bytes[6..].to_vec(),
+        })
+    }
+}
+
+pub fn split_secret_key(
+    sk: &SecretKey,
+    threshold: u8,
+    shares_count: u8,
+) -> Result<Vec<SecretKeyShare>, WError> {
+    if threshold == 0 || threshold > shares_count {
+        return Err(WError::UsageError(
+            "The threshold must be at least 1 and at most the number of shares",
+        ));
+    }
+    let key_id = sk.to_alg()?.public_key(sk)?.key_id();
+    let secret = sk.raw();
+    let mut coefficients = vec![0u8; secret.len() * (threshold as usize - 1)];
+    getrandom::getrandom(&mut coefficients).map_err(|_| WError::InternalError("RNG failure"))?;
+    let shares = (1..=shares_count)
+        .map(|index| {
+            let data = secret
+                .iter()
+                .enumerate()
+                .map(|(i, &s)| {
+                    let acc = coefficients
+                        .chunks(secret.len())
+                        .rev()
+                        .fold(0u8, |acc, c| gf256_mul(acc, index) ^ c[i]);
+                    gf256_mul(acc, index) ^ s
+                })
+                .collect();
+            SecretKeyShare {
+                key_id: key_id.clone(),
+                alg_id: sk.alg_id(),
+                threshold,
+                index,
+                data,
+            }
+        })
+        .collect();
+    Ok(shares)
+}
+
+pub fn combine_secret_key_shares(shares: &[SecretKeyShare]) -> Result<SecretKey, WError> {
+    let first = shares
+        .first()
+        .ok_or(WError::UsageError("No key shares provided"))?;
+    if shares.iter().any(|share| {
+        share.key_id != first.key_id
+            || share.alg_id != first.alg_id
+            || share.threshold != first.threshold
+            || share.data.len() != first.data.len()
+    }) {
+        return Err(WError::UsageError("Key shares belong to different keys"));
+    }
+    let mut indices: Vec<u8> = shares.iter().map(|share| share.index).collect();
+    indices.sort_unstable();
+    indices.dedup();
+    if indices.len() != shares.len() {
+        return Err(WError::UsageError("Duplicate key shares"));
+    }
+    if shares.len() < first.threshold as usize {
+        return Err(WError::UsageError("Not enough key shares"));
+    }
+    let shares = &shares[..first.threshold as usize];
+    let lagrange: Vec<u8> = shares
+        .iter()
+        .map(|share_i| {
+            let (num, den) = shares
+                .iter()
+                .filter(|share_j| share_j.index != share_i.index)
+                .fold((1u8, 1u8), |(num, den), share_j| {
+                    (
+                        gf256_mul(num, share_j.index),
+                        gf256_mul(den, share_j.index ^ share_i.index),
+                    )
+                });
+            gf256_mul(num, gf256_inv(den))
+        })
+        .collect();
+    let raw = (0..first.data.len())
+        .map(|i| {
+            shares
+                .iter()
+                .zip(&lagrange)
+                .fold(0u8, |acc, (share, &l)| acc ^ gf256_mul(share.data[i], l))
+        })
+        .collect();
+    let sk = SecretKey::new(first.alg_id, raw);
+    let key_id = sk.to_alg()?.public_key(&sk)?.key_id();
+    if key_id != first.key_id {
+        return Err(WError::SignatureError(
+            "Reconstructed key doesn't match the key identifier",
+        ));
+    }
+    Ok(sk)
+}
@@ -0,0 +1,456 @@
+use super::super::errors::*;
+use super::hash::HashAlg;
+use super::{tlog, x509};
+use ct_codecs::{Base64, Decoder, Encoder, Hex};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// OIDC issuer extensions of Fulcio certificates: a raw string (v1), and a DER string (v2).
+const ISSUER_V1_OID: &str = "1.3.6.1.4.1.57264.1.1";
+const ISSUER_V2_OID: &str = "1.3.6.1.4.1.57264.1.8";
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Bundle {
+    media_type: String,
+    verification_material: VerificationMaterial,
+    message_signature: Option<MessageSignature>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VerificationMaterial {
+    certificate: Option<RawBytes>,
+    x509_certificate_chain: Option<CertificateChain>,
+    #[serde(default)]
+    tlog_entries: Vec<TlogEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawBytes {
+    raw_bytes: String,
+}
+
+#[derive(Deserialize)]
+struct CertificateChain {
+    certificates: Vec<RawBytes>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MessageSignature {
+    message_digest: Option<MessageDigest>,
+    signature: String,
+}
+
+#[derive(Deserialize)]
+struct MessageDigest {
+    algorithm: String,
+    digest: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TlogEntry {
+    log_index: String,
+    log_id: KeyId,
+    kind_version: KindVersion,
+    integrated_time: String,
+    inclusion_promise: Option<InclusionPromise>,
+    inclusion_proof: Option<InclusionProof>,
+    canonicalized_body: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct KeyId {
+    key_id: String,
+}
+
+#[derive(Deserialize)]
+struct KindVersion {
+    kind: String,
+    version: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InclusionPromise {
+    signed_entry_timestamp: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InclusionProof {
+    log_index: String,
+    root_hash: String,
+    tree_size: String,
+    #[serde(default)]
+    hashes: Vec<String>,
+    checkpoint: Checkpoint,
+}
+
+#[derive(Deserialize)]
+struct Checkpoint {
+    envelope: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrustedRoot {
+    #[serde(default)]
+    tlogs: Vec<TransparencyLog>,
+    #[serde(default)]
+    certificate_authorities: Vec<CertificateAuthority>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TransparencyLog {
+    public_key: TrustedPublicKey,
+    log_id: KeyId,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TrustedPublicKey {
+    raw_bytes: String,
+    valid_for: Option<ValidityPeriod>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CertificateAuthority {
+    cert_chain: CertificateChain,
+    valid_for: Option<ValidityPeriod>,
+}
+
+#[derive(Deserialize)]
+struct ValidityPeriod {
+    start: Option<String>,
+    end: Option<String>,
+}
+
+/// What a verified bundle attests.
+#[derive(Clone, Debug)]
+pub struct VerifiedBundle {
+    pub identities: Vec<String>,
+    pub issuer: Option<String>,
+    pub log_index: u64,
+    pub integrated_time: u64,
+}
+
+impl VerifiedBundle {
+    /// Checks the identity the certificate was issued to, and the OIDC issuer
+    /// that authenticated it.
+    pub fn check_identity(&self, identity: &str, issuer: &str) -> Result<(), WError> {
+        if !self.identities.iter().any(|name| name == identity) {
+            return Err(WError::SignatureError(
+                "The certificate was issued to a different identity",
+            ));
+        }
+        if self.issuer.as_deref() != Some(issuer) {
+            return Err(WError::SignatureError(
+                "The certificate was issued by a different OIDC issuer",
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn parse_error<E: std::fmt::Display>(e: E) -> WError {
+    WError::ParseError(format!("Invalid Sigstore bundle: {}", e))
+}
+
+fn base64_decode(s: &str) -> Result<Vec<u8>, WError> {
+    Base64::decode_to_vec(s, None).map_err(|_| parse_error("base64"))
+}
+
+fn parse_u64(s: &str) -> Result<u64, WError> {
+    s.parse().map_err(parse_error)
+}
+
+impl ValidityPeriod {
+    fn contains(&self, unix_time: u64) -> Result<bool, WError> {
+        if let Some(start) = &self.start {
+            if unix_time < x509::parse_rfc3339(start)? {
+                return Ok(false);
+            }
+        }
+        if let Some(end) = &self.end {
+            if unix_time > x509::parse_rfc3339(end)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+fn valid_at(valid_for: &Option<ValidityPeriod>, unix_time: u64) -> Result<bool, WError> {
+    match valid_for {
+        None => Ok(true),
+        Some(valid_for) => valid_for.contains(unix_time),
+    }
+}
+
+/// Verifies a signed checkpoint, and returns the tree size and root hash it commits to.
+fn verify_checkpoint(
+    checkpoint: &str,
+    log_id: &[u8],
+    log_spki: &[u8],
+) -> Result<(u64, Vec<u8>), WError> {
+    let separator = checkpoint
+        .find("\n\n")
+        .ok_or_else(|| parse_error("malformed checkpoint"))?;
+    let (note, signatures) = checkpoint.split_at(separator + 1);
+    let mut lines = note.lines();
+    let (_origin, tree_size, root_hash) = match (lines.next(), lines.next(), lines.next()) {
+        (Some(origin), Some(tree_size), Some(root_hash)) => (origin, tree_size, root_hash),
+        _ => return Err(parse_error("malformed checkpoint")),
+    };
+    let verified = signatures
+        .lines()
+        .filter_map(|line| line.strip_prefix("\u{2014} "))
+        .filter_map(|line| line.rsplit(' ').next())
+        .filter_map(|signature| Base64::decode_to_vec(signature, None).ok())
+        .filter(|signature| signature.len() > 4 && signature[..4] == log_id[..4])
+        .any(|signature| {
+            x509::verify_with_spki(log_spki, note.as_bytes(), &signature[4..]).is_ok()
+        });
+    if !verified {
+        return Err(WError::SignatureError("Invalid checkpoint signature"));
+    }
+    Ok((parse_u64(tree_size)?, base64_decode(root_hash)?))
+}
+
+/// Checks that the log entry records the same artifact, signature and certificate.
+fn check_hashed_rekord(
+    body: &[u8],
+    digest: &[u8],
+    signature: &[u8],
+    leaf_der: &[u8],
+) -> Result<(), WError> {
+    let body: Value = serde_json::from_slice(body).map_err(parse_error)?;
+    let spec = &body["spec"];
+    let (hash_algorithm, hash_value, signature_content, public_key_content) = match (
+        spec["data"]["hash"]["algorithm"].as_str(),
+        spec["data"]["hash"]["value"].as_str(),
+        spec["signature"]["content"].as_str(),
+        spec["signature"]["publicKey"]["content"].as_str(),
+    ) {
+        (Some(a), Some(v), Some(s), Some(k)) => (a, v, s, k),
+        _ => return Err(parse_error("incomplete hashedrekord entry")),
+    };
+    let public_key_pem =
+        String::from_utf8(base64_decode(public_key_content)?).map_err(parse_error)?;
+    if hash_algorithm != "sha256"
+        || !hash_value.eq_ignore_ascii_case(
+            &Hex::encode_to_string(digest).map_err(|_| WError::InternalError("hex"))?,
+        )
+        || base64_decode(signature_content)? != signature
+        || x509::pem_decode(&public_key_pem, "CERTIFICATE")?
+            .first()
+            .map(Vec::as_slice)
+            != Some(leaf_der)
+    {
+        return Err(WError::SignatureError(
+            "The transparency log entry doesn't match the bundle",
+        ));
+    }
+    Ok(())
+}
+
+/// Verifies the signed entry timestamp of a log entry.
+fn verify_inclusion_promise(
+    entry: &TlogEntry,
+    body: &[u8],
+    log_id: &[u8],
+    log_spki: &[u8],
+) -> Result<(), WError> {
+    let promise = entry
+        .inclusion_promise
+        .as_ref()
+        .ok_or(WError::SignatureError(
+            "The transparency log entry has no signed timestamp",
+        ))?;
+    let mut payload = BTreeMap::new();
+    payload.insert(
+        "body",
+        Value::from(Base64::encode_to_string(body).map_err(|_| WError::InternalError("base64"))?),
+    );
+    payload.insert(
+        "integratedTime",
+        Value::from(parse_u64(&entry.integrated_time)?),
+    );
+    payload.insert(
+        "logID",
+        Value::from(Hex::encode_to_string(log_id).map_err(|_| WError::InternalError("hex"))?),
+    );
+    payload.insert("logIndex", Value::from(parse_u64(&entry.log_index)?));
+    let payload = serde_json::to_vec(&payload).map_err(|_| WError::InternalError("json"))?;
+    x509::verify_with_spki(
+        log_spki,
+        &payload,
+        &base64_decode(&promise.signed_entry_timestamp)?,
+    )
+    .map_err(|_| WError::SignatureError("Invalid signed entry timestamp"))
+}
+
+fn verify_inclusion_proof(
+    proof: &InclusionProof,
+    body: &[u8],
+    log_id: &[u8],
+    log_spki: &[u8],
+) -> Result<(), WError> {
+    let hashes = proof
+        .hashes
+        .iter()
+        .map(|hash| base64_decode(hash))
+        .collect::<Result<Vec<_>, _>>()?;
+    let tree_size = parse_u64(&proof.tree_size)?;
+    let root_hash = tlog::root_from_inclusion_proof(
+        parse_u64(&proof.log_index)?,
+        tree_size,
+        tlog::leaf_hash(body),
+        &hashes,
+    )?;
+    let (checkpoint_tree_size, checkpoint_root_hash) =
+        verify_checkpoint(&proof.checkpoint.envelope, log_id, log_spki)?;
+    if root_hash != base64_decode(&proof.root_hash)?
+        || root_hash != checkpoint_root_hash
+        || tree_size != checkpoint_tree_size
+    {
+        return Err(WError::SignatureError("Invalid inclusion proof"));
+    }
+    Ok(())
+}
+
+fn certificate_issuer(leaf_der: &[u8]) -> Result<Option<String>, WError> {
+    if let Some(issuer) = x509::certificate_extension(leaf_der, ISSUER_V2_OID)? {
+        // DER UTF8String with a short length
+        if issuer.len() >= 2 && issuer[0] == 0x0c && issuer[1] as usize == issuer.len() - 2 {
+            return Ok(Some(String::from_utf8_lossy(&issuer[2..]).into_owned()));
+        }
+    }
+    Ok(x509::certificate_extension(leaf_der, ISSUER_V1_OID)?
+        .map(|issuer| String::from_utf8_lossy(&issuer).into_owned()))
+}
+
+/// Verifies a Sigstore bundle for an artifact, using a Sigstore trusted root.
+/// The signing certificate must be valid when the signature was entered in a
+/// transparency log trusted by the root.
+pub fn verify_bundle(
+    artifact: &[u8],
+    bundle: &[u8],
+    trusted_root: &[u8],
+) -> Result<VerifiedBundle, WError> {
+    let bundle: Bundle = serde_json::from_slice(bundle).map_err(parse_error)?;
+    if !bundle
+        .media_type
+        .starts_with("application/vnd.dev.sigstore.bundle")
+    {
+        return Err(parse_error(format!(
+            "unsupported media type {}",
+            bundle.media_type
+        )));
+    }
+    let trusted_root: TrustedRoot = serde_json::from_slice(trusted_root)
+        .map_err(|e| WError::ParseError(format!("Invalid trusted root: {}", e)))?;
+    let material = &bundle.verification_material;
+    let mut certificates = match (&material.certificate, &material.x509_certificate_chain) {
+        (Some(certificate), _) => vec![base64_decode(&certificate.raw_bytes)?],
+        (None, Some(chain)) => chain
+            .certificates
+            .iter()
+            .map(|certificate| base64_decode(&certificate.raw_bytes))
+            .collect::<Result<Vec<_>, _>>()?,
+        (None, None) => return Err(WError::Unsupported),
+    };
+    if certificates.is_empty() {
+        return Err(parse_error("no signing certificate"));
+    }
+    let leaf_der = certificates.remove(0);
+    let message_signature = bundle
+        .message_signature
+        .as_ref()
+        .ok_or(WError::Unsupported)?;
+    let signature = base64_decode(&message_signature.signature)?;
+
+    // The artifact signature
+
+    let digest = HashAlg::Sha256.hash(artifact);
+    if let Some(message_digest) = &message_signature.message_digest {
+        let expected = match message_digest.algorithm.as_str() {
+            "SHA2_256" => digest.clone(),
+            "SHA2_512" => HashAlg::Sha512.hash(artifact),
+            _ => return Err(WError::Unsupported),
+        };
+        if base64_decode(&message_digest.digest)? != expected {
+            return Err(WError::SignatureError(
+                "The module doesn't match the signed digest",
+            ));
+        }
+    }
+    x509::verify_with_spki(&x509::certificate_spki(&leaf_der)?, artifact, &signature)?;
+
+    // The transparency log entry
+
+    let entry = match material.tlog_entries.as_slice() {
+        [entry, ..] => entry,
+        [] => return Err(parse_error("no transparency log entry")),
+    };
+    if entry.kind_version.kind != "hashedrekord" || entry.kind_version.version != "0.0.1" {
+        return Err(WError::Unsupported);
+    }
+    let integrated_time = parse_u64(&entry.integrated_time)?;
+    let log_id = base64_decode(&entry.log_id.key_id)?;
+    let tlog = trusted_root
+        .tlogs
+        .iter()
+        .find(|tlog| base64_decode(&tlog.log_id.key_id).ok().as_ref() == Some(&log_id))
+        .ok_or(WError::SignatureError(
+            "The transparency log is not trusted",
+        ))?;
+    if log_id.len() < 4 || !valid_at(&tlog.public_key.valid_for, integrated_time)? {
+        return Err(WError::SignatureError(
+            "The transparency log is not trusted",
+        ));
+    }
+    let log_spki = base64_decode(&tlog.public_key.raw_bytes)?;
+    let body = base64_decode(&entry.canonicalized_body)?;
+    check_hashed_rekord(&body, &digest, &signature, &leaf_der)?;
+    verify_inclusion_promise(entry, &body, &log_id, &log_spki)?;
+    if let Some(proof) = &entry.inclusion_proof {
+        verify_inclusion_proof(proof, &body, &log_id, &log_spki)?;
+    }
+
+    // The certificate chain, at the time the entry was logged
+
+    let mut roots = vec![];
+    let mut intermediates = certificates;
+    for authority in &trusted_root.certificate_authorities {
+        if !valid_at(&authority.valid_for, integrated_time)? {
+            continue;
+        }
+        let mut chain = authority
+            .cert_chain
+            .certificates
+            .iter()
+            .map(|certificate| base64_decode(&certificate.raw_bytes))
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(root) = chain.pop() {
+            roots.push(root);
+        }
+        intermediates.extend(chain);
+    }
+    x509::verify_chain(&leaf_der, &intermediates, &roots, integrated_time)?;
+
+    Ok(VerifiedBundle {
+        identities: x509::certificate_identities(&leaf_der)?,
+        issuer: certificate_issuer(&leaf_der)?,
+        log_index: parse_u64(&entry.log_index)?,
+        integrated_time,
+    })
+}
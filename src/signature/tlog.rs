@@ -0,0 +1,433 @@
+use super::super::errors::*;
+use super::KEY_ID_BYTES;
+use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
+use ct_codecs::{Decoder, Encoder, Hex};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+const HASH_BYTES: usize = 32;
+const ENTRIES_FILE_NAME: &str = "entries";
+
+fn sha256(parts: &[&[u8]]) -> Vec<u8> {
+    let mut hasher = hmac_sha256::Hash::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().to_vec()
+}
+
+/// RFC 6962 hash of a leaf.
+pub fn leaf_hash(data: &[u8]) -> Vec<u8> {
+    sha256(&[&[0x00], data])
+}
+
+/// RFC 6962 hash of an interior node.
+pub fn node_hash(left: &[u8], right: &[u8]) -> Vec<u8> {
+    sha256(&[&[0x01], left, right])
+}
+
+/// The largest power of 2 smaller than `n`, for `n` > 1.
+fn split_point(n: usize) -> usize {
+    let mut k = 1;
+    while k << 1 < n {
+        k <<= 1;
+    }
+    k
+}
+
+/// Root hash of a tree, given the hashes of its leaves.
+pub fn root_hash(leaf_hashes: &[Vec<u8>]) -> Vec<u8> {
+    match leaf_hashes.len() {
+        0 => sha256(&[]),
+        1 => leaf_hashes[0].clone(),
+        n => {
+            let k = split_point(n);
+            node_hash(&root_hash(&leaf_hashes[..k]), &root_hash(&leaf_hashes[k..]))
+        }
+    }
+}
+
+/// Audit path of the leaf at `index`.
+pub fn inclusion_path(leaf_hashes: &[Vec<u8>], index: usize) -> Vec<Vec<u8>> {
+    let n = leaf_hashes.len();
+    if n <= 1 {
+        return vec![];
+    }
+    let k = split_point(n);
+    let (mut path, sibling) = match index < k {
+        true => (
+            inclusion_path(&leaf_hashes[..k], index),
+            root_hash(&leaf_hashes[k..]),
+        ),
+        false => (
+            inclusion_path(&leaf_hashes[k..], index - k),
+            root_hash(&leaf_hashes[..k]),
+        ),
+    };
+    path.push(sibling);
+    path
+}
+
+fn subproof(old_size: usize, leaf_hashes: &[Vec<u8>], complete: bool) -> Vec<Vec<u8>> {
+    let n = leaf_hashes.len();
+    if old_size == n {
+        return match complete {
+            true => vec![],
+            false => vec![root_hash(leaf_hashes)],
+        };
+    }
+    let k = split_point(n);
+    let (mut proof, sibling) = match old_size <= k {
+        true => (
+            subproof(old_size, &leaf_hashes[..k], complete),
+            root_hash(&leaf_hashes[k..]),
+        ),
+        false => (
+            subproof(old_size - k, &leaf_hashes[k..], false),
+            root_hash(&leaf_hashes[..k]),
+        ),
+    };
+    proof.push(sibling);
+    proof
+}
+
+/// Proof that the first `old_size` leaves form a prefix of the tree.
+pub fn consistency_proof(leaf_hashes: &[Vec<u8>], old_size: usize) -> Vec<Vec<u8>> {
+    if old_size == 0 || old_size >= leaf_hashes.len() {
+        return vec![];
+    }
+    subproof(old_size, leaf_hashes, true)
+}
+
+/// Computes the root of a tree from a leaf hash and its inclusion proof.
+pub fn root_from_inclusion_proof(
+    index: u64,
+    tree_size: u64,
+    leaf_hash: Vec<u8>,
+    proof: &[Vec<u8>],
+) -> Result<Vec<u8>, WError> {
+    if index >= tree_size {
+        return Err(WError::ParseError(
+            "Log index beyond the tree size".to_string(),
+        ));
+    }
+    let inner = (64 - (index ^ (tree_size - 1)).leading_zeros()) as usize;
+    let border = (index >> inner).count_ones() as usize;
+    if proof.len() != inner + border {
+        return Err(WError::ParseError("Wrong inclusion proof size".to_string()));
+    }
+    let mut hash = leaf_hash;
+    for (i, sibling) in proof[..inner].iter().enumerate() {
+        hash = match (index >> i) & 1 {
+            0 => node_hash(&hash, sibling),
+            _ => node_hash(sibling, &hash),
+        };
+    }
+    for sibling in &proof[inner..] {
+        hash = node_hash(sibling, &hash);
+    }
+    Ok(hash)
+}
+
+/// Verifies that a tree is an append-only extension of an older tree.
+pub fn verify_consistency(
+    old_head: &TreeHead,
+    new_head: &TreeHead,
+    proof: &[Vec<u8>],
+) -> Result<(), WError> {
+    let invalid = || WError::SignatureError("Invalid consistency proof");
+    let (old_size, new_size) = (old_head.size, new_head.size);
+    if old_size > new_size {
+        return Err(invalid());
+    }
+    if old_size == new_size {
+        return match proof.is_empty() && old_head.root_hash == new_head.root_hash {
+            true => Ok(()),
+            false => Err(invalid()),
+        };
+    }
+    if old_size == 0 {
+        return match proof.is_empty() {
+            true => Ok(()),
+            false => Err(invalid()),
+        };
+    }
+    let mut path = proof.to_vec();
+    if old_size & (old_size - 1) == 0 {
+        path.insert(0, old_head.root_hash.clone());
+    }
+    if path.is_empty() {
+        return Err(invalid());
+    }
+    let (mut fn_, mut sn) = (old_size - 1, new_size - 1);
+    while fn_ & 1 == 1 {
+        fn_ >>= 1;
+        sn >>= 1;
+    }
+    let (mut fr, mut sr) = (path[0].clone(), path[0].clone());
+    for c in &path[1..] {
+        if sn == 0 {
+            return Err(invalid());
+        }
+        if fn_ & 1 == 1 || fn_ == sn {
+            fr = node_hash(c, &fr);
+            sr = node_hash(c, &sr);
+            while fn_ & 1 == 0 && fn_ != 0 {
+                fn_ >>= 1;
+                sn >>= 1;
+            }
+        } else {
+            sr = node_hash(&sr, c);
+        }
+        fn_ >>= 1;
+        sn >>= 1;
+    }
+    if sn != 0 || fr != old_head.root_hash || sr != new_head.root_hash {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+/// The size and root hash of a log, written as `size:hex_root_hash`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TreeHead {
+    pub size: u64,
+    pub root_hash: Vec<u8>,
+}
+
+impl fmt::Display for TreeHead {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let root_hash = Hex::encode_to_string(&self.root_hash).map_err(|_| fmt::Error)?;
+        write!(f, "{}:{}", self.size, root_hash)
+    }
+}
+
+impl FromStr for TreeHead {
+    type Err = WError;
+
+    fn from_str(s: &str) -> Result<Self, WError> {
+        let invalid = || WError::ParseError(format!("Invalid tree head: {}", s));
+        let (size, root_hash) = s.trim().split_once(':').ok_or_else(invalid)?;
+        let size = size.parse().map_err(|_| invalid())?;
+        let root_hash = Hex::decode_to_vec(root_hash, None).map_err(|_| invalid())?;
+        if root_hash.len() != HASH_BYTES {
+            return Err(invalid());
+        }
+        Ok(TreeHead { size, root_hash })
+    }
+}
+
+/// A signing event: the digest of the module, the key ID, and the time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LogEntry {
+    pub module_digest: Vec<u8>,
+    pub key_id: Vec<u8>,
+    pub time: u64,
+}
+
+impl LogEntry {
+    pub const BYTES: usize = HASH_BYTES + KEY_ID_BYTES + 8;
+
+    /// Creates an entry for a module. An empty key ID is recorded as zeros.
+    pub fn new(module_bytes: &[u8], key_id: Option<&[u8]>, time: u64) -> Result<Self, WError> {
+        let key_id = match key_id {
+            None => vec![0u8; KEY_ID_BYTES],
+            Some(key_id) if key_id.len() == KEY_ID_BYTES => key_id.to_vec(),
+            Some(_) => return Err(WError::UsageError("Unexpected key ID length")),
+        };
+        Ok(LogEntry {
+            module_digest: sha256(&[module_bytes]),
+            key_id,
+            time,
+        })
+    }
+
+    pub fn matches_module(&self, module_bytes: &[u8]) -> bool {
+        self.module_digest == sha256(&[module_bytes])
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::BYTES);
+        bytes.extend_from_slice(&self.module_digest);
+        bytes.extend_from_slice(&self.key_id);
+        bytes
+            .write_u64::<LittleEndian>(self.time)
+            .expect("Unable to serialize");
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, WError> {
+        if bytes.len() != Self::BYTES {
+            return Err(WError::ParseError("Invalid log entry".to_string()));
+        }
+        let (module_digest, rest) = bytes.split_at(HASH_BYTES);
+        let (key_id, time) = rest.split_at(KEY_ID_BYTES);
+        Ok(LogEntry {
+            module_digest: module_digest.to_vec(),
+            key_id: key_id.to_vec(),
+            time: LittleEndian::read_u64(time),
+        })
+    }
+
+    pub fn leaf_hash(&self) -> Vec<u8> {
+        leaf_hash(&self.to_bytes())
+    }
+}
+
+/// Proof that an entry is included in a log of a given size.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InclusionProof {
+    pub entry: LogEntry,
+    pub index: u64,
+    pub head: TreeHead,
+    pub hashes: Vec<Vec<u8>>,
+}
+
+impl InclusionProof {
+    pub fn verify(&self) -> Result<(), WError> {
+        let root_hash = root_from_inclusion_proof(
+            self.index,
+            self.head.size,
+            self.entry.leaf_hash(),
+            &self.hashes,
+        )?;
+        if root_hash != self.head.root_hash {
+            return Err(WError::SignatureError("Invalid inclusion proof"));
+        }
+        Ok(())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.entry.to_bytes();
+        bytes
+            .write_u64::<LittleEndian>(self.index)
+            .expect("Unable to serialize");
+        bytes
+            .write_u64::<LittleEndian>(self.head.size)
+            .expect("Unable to serialize");
+        bytes.extend_from_slice(&self.head.root_hash);
+        for hash in &self.hashes {
+            bytes.extend_from_slice(hash);
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, WError> {
+        let fixed_len = LogEntry::BYTES + 8 + 8 + HASH_BYTES;
+        if bytes.len() < fixed_len || !(bytes.len() - fixed_len).is_multiple_of(HASH_BYTES) {
+            return Err(WError::ParseError("Invalid inclusion proof".to_string()));
+        }
+        let (entry, rest) = bytes.split_at(LogEntry::BYTES);
+        let index = LittleEndian::read_u64(&rest[..8]);
+        let size = LittleEndian::read_u64(&rest[8..16]);
+        let (root_hash, hashes) = rest[16..].split_at(HASH_BYTES);
+        Ok(InclusionProof {
+            entry: LogEntry::from_bytes(entry)?,
+            index,
+            head: TreeHead {
+                size,
+                root_hash: root_hash.to_vec(),
+            },
+            hashes: hashes
+                .chunks(HASH_BYTES)
+                .map(|hash| hash.to_vec())
+                .collect(),
+        })
+    }
+}
+
+/// An append-only transparency log, stored in a directory.
+pub struct TransparencyLog {
+    entries_path: PathBuf,
+}
+
+fn read_entries(file: &mut File) -> Result<Vec<LogEntry>, WError> {
+    let mut bytes = vec![];
+    file.read_to_end(&mut bytes)?;
+    if !bytes.len().is_multiple_of(LogEntry::BYTES) {
+        return Err(WError::ParseError("Truncated log".to_string()));
+    }
+    bytes
+        .chunks(LogEntry::BYTES)
+        .map(LogEntry::from_bytes)
+        .collect()
+}
+
+impl TransparencyLog {
+    /// Opens an existing log.
+    pub fn open<P: AsRef<Path>>(dir: P) -> Result<Self, WError> {
+        let entries_path = dir.as_ref().join(ENTRIES_FILE_NAME);
+        if !entries_path.is_file() {
+            return Err(WError::UsageError("Transparency log not found"));
+        }
+        Ok(TransparencyLog { entries_path })
+    }
+
+    /// Opens a log, creating it if it doesn't exist yet.
+    pub fn create<P: AsRef<Path>>(dir: P) -> Result<Self, WError> {
+        fs::create_dir_all(dir.as_ref())?;
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.as_ref().join(ENTRIES_FILE_NAME))?;
+        Self::open(dir)
+    }
+
+    pub fn entries(&self) -> Result<Vec<LogEntry>, WError> {
+        read_entries(&mut File::open(&self.entries_path)?)
+    }
+
+    fn leaf_hashes(&self) -> Result<Vec<Vec<u8>>, WError> {
+        Ok(self
+            .entries()?
+            .iter()
+            .map(|entry| entry.leaf_hash())
+            .collect())
+    }
+
+    pub fn head(&self) -> Result<TreeHead, WError> {
+        let leaf_hashes = self.leaf_hashes()?;
+        Ok(TreeHead {
+            size: leaf_hashes.len() as u64,
+            root_hash: root_hash(&leaf_hashes),
+        })
+    }
+
+    /// Appends an entry, and returns its inclusion proof in the new tree.
+    /// The log is locked until the proof is computed.
+    pub fn append(&self, entry: &LogEntry) -> Result<InclusionProof, WError> {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .append(true)
+            .open(&self.entries_path)?;
+        file.lock()?;
+        let mut leaf_hashes: Vec<_> = read_entries(&mut file)?
+            .iter()
+            .map(|entry| entry.leaf_hash())
+            .collect();
+        file.write_all(&entry.to_bytes())?;
+        let index = leaf_hashes.len();
+        leaf_hashes.push(entry.leaf_hash());
+        Ok(InclusionProof {
+            entry: entry.clone(),
+            index: index as u64,
+            head: TreeHead {
+                size: leaf_hashes.len() as u64,
+                root_hash: root_hash(&leaf_hashes),
+            },
+            hashes: inclusion_path(&leaf_hashes, index),
+        })
+    }
+
+    /// Proof that the current tree is an extension of the tree of size `old_size`.
+    pub fn consistency_proof(&self, old_size: u64) -> Result<Vec<Vec<u8>>, WError> {
+        let leaf_hashes = self.leaf_hashes()?;
+        if old_size > leaf_hashes.len() as u64 {
+            return Err(WError::UsageError("The log is smaller than the tree head"));
+        }
+        Ok(consistency_proof(&leaf_hashes, old_size as usize))
+    }
+}
@@ -0,0 +1,344 @@
+use super::super::errors::*;
+use super::x509;
+use cms::cert::CertificateChoices;
+use cms::content_info::ContentInfo;
+use cms::signed_data::{SignedData, SignerIdentifier};
+use der::asn1::{BitString, GeneralizedTime, Int, ObjectIdentifier, OctetString};
+use der::{Decode, Encode, Sequence};
+use ring::digest;
+use ring::signature::{self as ring_signature, UnparsedPublicKey};
+use x509_cert::ext::pkix::name::GeneralName;
+use x509_cert::ext::pkix::SubjectKeyIdentifier;
+use x509_cert::ext::Extensions;
+use x509_cert::spki::{AlgorithmIdentifierOwned, SubjectPublicKeyInfoOwned};
+
+pub const ID_SIGNED_DATA: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.7.2");
+pub const ID_CT_TST_INFO: ObjectIdentifier =
+    ObjectIdentifier::new_unwrap("1.2.840.113549.1.9.16.1.4");
+pub const ID_CONTENT_TYPE: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.9.3");
+pub const ID_MESSAGE_DIGEST: ObjectIdentifier =
+    ObjectIdentifier::new_unwrap("1.2.840.113549.1.9.4");
+pub const ID_SHA256: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.16.840.1.101.3.4.2.1");
+pub const ID_SHA384: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.16.840.1.101.3.4.2.2");
+pub const ID_SHA512: ObjectIdentifier = ObjectIdentifier::new_unwrap("2.16.840.1.101.3.4.2.3");
+
+const EC_PUBLIC_KEY: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.2.1");
+const CURVE_P256: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.3.1.7");
+const CURVE_P384: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.132.0.34");
+const RSA_ENCRYPTION: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.113549.1.1.1");
+const ED25519: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.101.112");
+
+const STATUS_GRANTED: u8 = 0;
+const STATUS_GRANTED_WITH_MODS: u8 = 1;
+
+/// RFC 3161 MessageImprint.
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+pub struct MessageImprint {
+    pub hash_algorithm: AlgorithmIdentifierOwned,
+    pub hashed_message: OctetString,
+}
+
+/// RFC 3161 TimeStampReq.
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+pub struct TimeStampReq {
+    pub version: u8,
+    pub message_imprint: MessageImprint,
+    pub req_policy: Option<ObjectIdentifier>,
+    pub nonce: Option<Int>,
+    #[asn1(default = "Default::default")]
+    pub cert_req: bool,
+    #[asn1(context_specific = "0", tag_mode = "IMPLICIT", optional = "true")]
+    pub extensions: Option<Extensions>,
+}
+
+/// RFC 3161 PKIStatusInfo.
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+pub struct PkiStatusInfo {
+    pub status: u8,
+    pub status_string: Option<Vec<String>>,
+    pub fail_info: Option<BitString>,
+}
+
+/// RFC 3161 TimeStampResp.
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+pub struct TimeStampResp {
+    pub status: PkiStatusInfo,
+    pub time_stamp_token: Option<ContentInfo>,
+}
+
+/// RFC 3161 Accuracy.
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+pub struct Accuracy {
+    pub seconds: Option<Int>,
+    #[asn1(context_specific = "0", tag_mode = "IMPLICIT", optional = "true")]
+    pub millis: Option<Int>,
+    #[asn1(context_specific = "1", tag_mode = "IMPLICIT", optional = "true")]
+    pub micros: Option<Int>,
+}
+
+/// RFC 3161 TSTInfo, the content signed by a Time Stamping Authority.
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+pub struct TstInfo {
+    pub version: u8,
+    pub policy: ObjectIdentifier,
+    pub message_imprint: MessageImprint,
+    pub serial_number: Int,
+    pub gen_time: GeneralizedTime,
+    pub accuracy: Option<Accuracy>,
+    #[asn1(default = "Default::default")]
+    pub ordering: bool,
+    pub nonce: Option<Int>,
+    #[asn1(context_specific = "0", tag_mode = "EXPLICIT", optional = "true")]
+    pub tsa: Option<GeneralName>,
+    #[asn1(context_specific = "1", tag_mode = "IMPLICIT", optional = "true")]
+    pub extensions: Option<Extensions>,
+}
+
+fn der_error<E: std::fmt::Display>(e: E) -> WError {
+    WError::ParseError(format!("Invalid timestamp: {}", e))
+}
+
+fn digest_algorithm(oid: &ObjectIdentifier) -> Result<&'static digest::Algorithm, WError> {
+    match *oid {
+        ID_SHA256 => Ok(&digest::SHA256),
+        ID_SHA384 => Ok(&digest::SHA384),
+        ID_SHA512 => Ok(&digest::SHA512),
+        _ => Err(WError::Unsupported),
+    }
+}
+
+fn algorithm_identifier(oid: ObjectIdentifier) -> AlgorithmIdentifierOwned {
+    AlgorithmIdentifierOwned {
+        oid,
+        parameters: None,
+    }
+}
+
+/// Creates a DER-encoded time-stamp request for a message, asking the
+/// TSA to include its certificate in the response.
+pub fn request(message: &[u8]) -> Result<Vec<u8>, WError> {
+    let hashed_message = digest::digest(&digest::SHA256, message);
+    TimeStampReq {
+        version: 1,
+        message_imprint: MessageImprint {
+            hash_algorithm: algorithm_identifier(ID_SHA256),
+            hashed_message: OctetString::new(hashed_message.as_ref()).map_err(der_error)?,
+        },
+        req_policy: None,
+        nonce: None,
+        cert_req: true,
+        extensions: None,
+    }
+    .to_der()
+    .map_err(der_error)
+}
+
+/// Extracts the DER-encoded time-stamp token from a TSA response.
+pub fn token_from_response(response: &[u8]) -> Result<Vec<u8>, WError> {
+    let response = TimeStampResp::from_der(response).map_err(der_error)?;
+    match response.status.status {
+        STATUS_GRANTED | STATUS_GRANTED_WITH_MODS => {}
+        status => {
+            let reason = response
+                .status
+                .status_string
+                .map(|strings| format!(": {}", strings.join(", ")))
+                .unwrap_or_default();
+            return Err(WError::ParseError(format!(
+                "The time-stamp request was rejected (status {}){}",
+                status, reason
+            )));
+        }
+    }
+    response
+        .time_stamp_token
+        .ok_or_else(|| WError::ParseError("No time-stamp token in the response".to_string()))?
+        .to_der()
+        .map_err(der_error)
+}
+
+fn signed_data(token: &[u8]) -> Result<(SignedData, Vec<u8>, TstInfo), WError> {
+    let content_info = ContentInfo::from_der(token).map_err(der_error)?;
+    if content_info.content_type != ID_SIGNED_DATA {
+        return Err(WError::ParseError(
+            "The time-stamp token is not signed data".to_string(),
+        ));
+    }
+    let signed_data: SignedData = content_info.content.decode_as().map_err(der_error)?;
+    let encap = &signed_data.encap_content_info;
+    if encap.econtent_type != ID_CT_TST_INFO {
+        return Err(WError::ParseError(
+            "The time-stamp token doesn't contain a TSTInfo structure".to_string(),
+        ));
+    }
+    let econtent: OctetString = encap
+        .econtent
+        .as_ref()
+        .ok_or_else(|| WError::ParseError("The time-stamp token has no content".to_string()))?
+        .decode_as()
+        .map_err(der_error)?;
+    let econtent = econtent.into_bytes();
+    let tst_info = TstInfo::from_der(&econtent).map_err(der_error)?;
+    Ok((signed_data, econtent, tst_info))
+}
+
+/// Decodes a time-stamp token, without verifying it.
+pub fn tst_info(token: &[u8]) -> Result<TstInfo, WError> {
+    Ok(signed_data(token)?.2)
+}
+
+/// Checks that a time-stamp token was issued for the given message.
+pub fn check_imprint(tst_info: &TstInfo, message: &[u8]) -> Result<(), WError> {
+    let imprint = &tst_info.message_imprint;
+    let alg = digest_algorithm(&imprint.hash_algorithm.oid)?;
+    if digest::digest(alg, message).as_ref() != imprint.hashed_message.as_bytes() {
+        return Err(WError::SignatureError(
+            "The time-stamp token was issued for a different signature",
+        ));
+    }
+    Ok(())
+}
+
+fn verify_signer(
+    spki_der: &[u8],
+    digest_oid: &ObjectIdentifier,
+    msg: &[u8],
+    signature: &[u8],
+) -> Result<(), WError> {
+    let spki = SubjectPublicKeyInfoOwned::from_der(spki_der).map_err(der_error)?;
+    let curve = match &spki.algorithm.parameters {
+        Some(parameters) if spki.algorithm.oid == EC_PUBLIC_KEY => Some(
+            parameters
+                .decode_as::<ObjectIdentifier>()
+                .map_err(der_error)?,
+        ),
+        _ => None,
+    };
+    let alg: &dyn ring_signature::VerificationAlgorithm =
+        match (spki.algorithm.oid, curve, *digest_oid) {
+            (EC_PUBLIC_KEY, Some(CURVE_P256), ID_SHA256) => &ring_signature::ECDSA_P256_SHA256_ASN1,
+            (EC_PUBLIC_KEY, Some(CURVE_P256), ID_SHA384) => &ring_signature::ECDSA_P256_SHA384_ASN1,
+            (EC_PUBLIC_KEY, Some(CURVE_P384), ID_SHA256) => &ring_signature::ECDSA_P384_SHA256_ASN1,
+            (EC_PUBLIC_KEY, Some(CURVE_P384), ID_SHA384) => &ring_signature::ECDSA_P384_SHA384_ASN1,
+            (RSA_ENCRYPTION, None, ID_SHA256) => &ring_signature::RSA_PKCS1_2048_8192_SHA256,
+            (RSA_ENCRYPTION, None, ID_SHA384) => &ring_signature::RSA_PKCS1_2048_8192_SHA384,
+            (RSA_ENCRYPTION, None, ID_SHA512) => &ring_signature::RSA_PKCS1_2048_8192_SHA512,
+            (ED25519, None, _) => &ring_signature::ED25519,
+            _ => return Err(WError::Unsupported),
+        };
+    UnparsedPublicKey::new(alg, spki.subject_public_key.raw_bytes())
+        .verify(msg, signature)
+        .map_err(|_| WError::SignatureError("Invalid time-stamp token signature"))
+}
+
+fn signer_certificate(
+    sid: &SignerIdentifier,
+    certificates: &[x509_cert::Certificate],
+) -> Result<usize, WError> {
+    certificates
+        .iter()
+        .position(|certificate| match sid {
+            SignerIdentifier::IssuerAndSerialNumber(sid) => {
+                certificate.tbs_certificate.issuer == sid.issuer
+                    && certificate.tbs_certificate.serial_number == sid.serial_number
+            }
+            SignerIdentifier::SubjectKeyIdentifier(sid) => matches!(
+                certificate.tbs_certificate.get::<SubjectKeyIdentifier>(),
+                Ok(Some((_, ski))) if ski == *sid
+            ),
+        })
+        .ok_or_else(|| {
+            WError::CertificateError(
+                "The TSA certificate is not included in the time-stamp token".to_string(),
+            )
+        })
+}
+
+/// Verifies a time-stamp token over a message, issued by a TSA whose
+/// certificate chains to one of the trusted roots, and returns the time it
+/// certifies, in seconds since the UNIX epoch.
+pub fn verify(token: &[u8], message: &[u8], roots: &[Vec<u8>]) -> Result<u64, WError> {
+    let (signed_data, econtent, tst_info) = signed_data(token)?;
+    check_imprint(&tst_info, message)?;
+
+    // The signed attributes must bind the TSTInfo structure
+
+    let signer_infos = signed_data.signer_infos.0.as_slice();
+    let signer_info = match signer_infos {
+        [signer_info] => signer_info,
+        _ => {
+            return Err(WError::ParseError(
+                "The time-stamp token must have exactly one signer".to_string(),
+            ))
+        }
+    };
+    let signed_attrs = signer_info.signed_attrs.as_ref().ok_or_else(|| {
+        WError::ParseError("The time-stamp token has no signed attributes".to_string())
+    })?;
+    let attr_value = |oid: ObjectIdentifier| {
+        signed_attrs
+            .iter()
+            .find(|attr| attr.oid == oid)
+            .and_then(|attr| attr.values.get(0))
+            .ok_or_else(|| {
+                WError::ParseError(format!("Missing signed attribute {} in the token", oid))
+            })
+    };
+    let content_type: ObjectIdentifier = attr_value(ID_CONTENT_TYPE)?
+        .decode_as()
+        .map_err(der_error)?;
+    let message_digest: OctetString = attr_value(ID_MESSAGE_DIGEST)?
+        .decode_as()
+        .map_err(der_error)?;
+    let digest_oid = signer_info.digest_alg.oid;
+    let content_digest = digest::digest(digest_algorithm(&digest_oid)?, &econtent);
+    if content_type != ID_CT_TST_INFO || message_digest.as_bytes() != content_digest.as_ref() {
+        return Err(WError::SignatureError(
+            "The time-stamp token signed attributes don't match its content",
+        ));
+    }
+
+    // Check the signature, then the TSA certificate at the time it certifies
+
+    let certificates: Vec<x509_cert::Certificate> = signed_data
+        .certificates
+        .as_ref()
+        .map(|certificates| {
+            certificates
+                .0
+                .iter()
+                .filter_map(|choice| match choice {
+                    CertificateChoices::Certificate(certificate) => Some(certificate.clone()),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let signer_index = signer_certificate(&signer_info.sid, &certificates)?;
+    let certificates_der = certificates
+        .iter()
+        .map(|certificate| certificate.to_der().map_err(der_error))
+        .collect::<Result<Vec<_>, _>>()?;
+    let leaf = &certificates_der[signer_index];
+    verify_signer(
+        &x509::certificate_spki(leaf)?,
+        &digest_oid,
+        &signed_attrs.to_der().map_err(der_error)?,
+        signer_info.signature.as_bytes(),
+    )?;
+    let gen_time = tst_info.gen_time.to_unix_duration().as_secs();
+    let intermediates: Vec<Vec<u8>> = certificates_der
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| *i != signer_index)
+        .map(|(_, certificate)| certificate.clone())
+        .collect();
+    x509::verify_chain_for_usage(
+        leaf,
+        &intermediates,
+        roots,
+        gen_time,
+        x509::TIME_STAMPING_EKU,
+    )?;
+    Ok(gen_time)
+}
@@ -0,0 +1,50 @@
+use super::*;
+
+pub const ED25519_PK_ID: u8 = 0x01;
+pub const ED25519_SK_ID: u8 = 0x81;
+
+pub fn is_public_key(bytes: &[u8]) -> bool {
+    bytes.len() == 1 + ed25519_compact::PublicKey::BYTES && bytes[0] == ED25519_PK_ID
+}
+
+pub fn is_secret_key(bytes: &[u8]) -> bool {
+    bytes.len() == 1 + ed25519_compact::SecretKey::BYTES && bytes[0] == ED25519_SK_ID
+}
+
+pub fn public_key_from_bytes(bytes: &[u8]) -> Result<PublicKey, WError> {
+    if !is_public_key(bytes) {
+        return Err(WError::ParseError(
+            "Invalid wasmsign2 public key".to_string(),
+        ));
+    }
+    Ok(PublicKey::new(eddsa::ALG_ID, bytes[1..].to_vec()))
+}
+
+pub fn public_key_to_bytes(pk: &PublicKey) -> Result<Vec<u8>, WError> {
+    if pk.alg_id() != eddsa::ALG_ID {
+        return Err(WError::UsageError("Only Ed25519 keys can be used with wasmsign2"));
+    }
+    let mut bytes = vec![ED25519_PK_ID];
+    bytes.extend_from_slice(pk.raw());
+    Ok(bytes)
+}
+
+pub fn key_pair_from_bytes(bytes: &[u8]) -> Result<KeyPair, WError> {
+    if !is_secret_key(bytes) {
+        return Err(WError::ParseError(
+            "Invalid wasmsign2 secret key".to_string(),
+        ));
+    }
+    let sk = SecretKey::new(eddsa::ALG_ID, bytes[1..].to_vec());
+    let pk = EdDSA.public_key(&sk)?;
+    Ok(KeyPair::new(eddsa::ALG_ID, pk, sk))
+}
+
+pub fn secret_key_to_bytes(sk: &SecretKey) -> Result<Vec<u8>, WError> {
+    if sk.alg_id() != eddsa::ALG_ID {
+        return Err(WError::UsageError("Only Ed25519 keys can be used with wasmsign2"));
+    }
+    let mut bytes = vec![ED25519_SK_ID];
+    bytes.extend_from_slice(sk.raw());
+    Ok(bytes)
+}
@@ -0,0 +1,309 @@
+use super::super::errors::*;
+use super::{eddsa, Key, PublicKey, Signature, Signer, SigningRequest, WithParameters};
+use ct_codecs::{Base64, Decoder};
+use ring::signature::{self as ring_signature, UnparsedPublicKey};
+use rustls_pki_types::{CertificateDer, UnixTime};
+use std::convert::TryFrom;
+use std::time::Duration;
+use x509_cert::der::asn1::ObjectIdentifier;
+use x509_cert::der::{Decode, Encode};
+use x509_cert::ext::pkix::name::GeneralName;
+use x509_cert::ext::pkix::{ExtendedKeyUsage, SubjectAltName};
+use x509_cert::Certificate;
+
+/// id-kp-codeSigning (1.3.6.1.5.5.7.3.3), DER-encoded.
+pub const CODE_SIGNING_EKU: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x03];
+/// id-kp-timeStamping (1.3.6.1.5.5.7.3.8), DER-encoded.
+pub const TIME_STAMPING_EKU: &[u8] = &[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x03, 0x08];
+
+const EC_PUBLIC_KEY: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.2.1");
+const CURVE_P256: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.3.1.7");
+const CURVE_P384: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.132.0.34");
+const ED25519: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.3.101.112");
+
+fn certificate_error<E: std::fmt::Display>(e: E) -> WError {
+    WError::CertificateError(e.to_string())
+}
+
+/// Decodes all the PEM-encoded blocks with the given label.
+pub fn pem_decode(pem: &str, label: &str) -> Result<Vec<Vec<u8>>, WError> {
+    let (begin, end) = (
+        format!("-----BEGIN {}-----", label),
+        format!("-----END {}-----", label),
+    );
+    let mut blocks = vec![];
+    let mut rest = pem;
+    while let Some(start) = rest.find(&begin) {
+        let body = &rest[start + begin.len()..];
+        let stop = body
+            .find(&end)
+            .ok_or_else(|| WError::ParseError(format!("Unterminated PEM {} block", label)))?;
+        blocks.push(
+            Base64::decode_to_vec(&body[..stop], Some(b"\r\n\t "))
+                .map_err(|_| WError::ParseError("Invalid PEM encoding".to_string()))?,
+        );
+        rest = &body[stop + end.len()..];
+    }
+    Ok(blocks)
+}
+
+pub fn parse_certificate(der: &[u8]) -> Result<Certificate, WError> {
+    Certificate::from_der(der).map_err(certificate_error)
+}
+
+/// The DER-encoded SubjectPublicKeyInfo of a certificate.
+pub fn certificate_spki(der: &[u8]) -> Result<Vec<u8>, WError> {
+    parse_certificate(der)?
+        .tbs_certificate
+        .subject_public_key_info
+        .to_der()
+        .map_err(certificate_error)
+}
+
+/// Verifies a signature using a DER-encoded SubjectPublicKeyInfo.
+/// ECDSA signatures are ASN.1-encoded, and use the hash function that matches the curve.
+pub fn verify_with_spki(spki_der: &[u8], msg: &[u8], signature: &[u8]) -> Result<(), WError> {
+    let spki = x509_cert::spki::SubjectPublicKeyInfoOwned::from_der(spki_der)
+        .map_err(certificate_error)?;
+    let curve = match &spki.algorithm.parameters {
+        Some(parameters) if spki.algorithm.oid == EC_PUBLIC_KEY => Some(
+            parameters
+                .decode_as::<ObjectIdentifier>()
+                .map_err(certificate_error)?,
+        ),
+        _ => None,
+    };
+    let alg: &dyn ring_signature::VerificationAlgorithm = match (spki.algorithm.oid, curve) {
+        (EC_PUBLIC_KEY, Some(CURVE_P256)) => &ring_signature::ECDSA_P256_SHA256_ASN1,
+        (EC_PUBLIC_KEY, Some(CURVE_P384)) => &ring_signature::ECDSA_P384_SHA384_ASN1,
+        (ED25519, None) => &ring_signature::ED25519,
+        _ => return Err(WError::Unsupported),
+    };
+    let raw = spki.subject_public_key.raw_bytes();
+    UnparsedPublicKey::new(alg, raw)
+        .verify(msg, signature)
+        .map_err(|_| WError::SignatureError("Signature verification failed"))
+}
+
+/// Verifies that a code-signing certificate chains to one of the trusted roots
+/// at the given time.
+pub fn verify_chain(
+    leaf_der: &[u8],
+    intermediates_der: &[Vec<u8>],
+    roots_der: &[Vec<u8>],
+    unix_time: u64,
+) -> Result<(), WError> {
+    verify_chain_for_usage(
+        leaf_der,
+        intermediates_der,
+        roots_der,
+        unix_time,
+        CODE_SIGNING_EKU,
+    )
+}
+
+/// Verifies that a certificate chains to one of the trusted roots at the given
+/// time, and can be used for the given extended key usage. Certificate
+/// authorities don't need to list extended key usages, but the leaf
+/// certificate does.
+pub fn verify_chain_for_usage(
+    leaf_der: &[u8],
+    intermediates_der: &[Vec<u8>],
+    roots_der: &[Vec<u8>],
+    unix_time: u64,
+    eku_der: &'static [u8],
+) -> Result<(), WError> {
+    let roots: Vec<CertificateDer> = roots_der
+        .iter()
+        .map(|root| CertificateDer::from(root.as_slice()))
+        .collect();
+    let trust_anchors = roots
+        .iter()
+        .map(|root| webpki::anchor_from_trusted_cert(root).map_err(certificate_error))
+        .collect::<Result<Vec<_>, _>>()?;
+    let intermediates: Vec<CertificateDer> = intermediates_der
+        .iter()
+        .map(|intermediate| CertificateDer::from(intermediate.as_slice()))
+        .collect();
+    let required_eku = ObjectIdentifier::from_bytes(eku_der).map_err(certificate_error)?;
+    let eku = parse_certificate(leaf_der)?
+        .tbs_certificate
+        .get::<ExtendedKeyUsage>()
+        .map_err(certificate_error)?;
+    if !eku.is_some_and(|(_, eku)| eku.0.contains(&required_eku)) {
+        return Err(WError::CertificateError(format!(
+            "The certificate is not valid for {}",
+            required_eku
+        )));
+    }
+    let leaf = CertificateDer::from(leaf_der);
+    let leaf = webpki::EndEntityCert::try_from(&leaf).map_err(certificate_error)?;
+    leaf.verify_for_usage(
+        webpki::ALL_VERIFICATION_ALGS,
+        &trust_anchors,
+        &intermediates,
+        UnixTime::since_unix_epoch(Duration::from_secs(unix_time)),
+        webpki::KeyUsage::required_if_present(eku_der),
+        None,
+        None,
+    )
+    .map_err(certificate_error)?;
+    Ok(())
+}
+
+/// The subject distinguished name of a certificate.
+pub fn certificate_subject(der: &[u8]) -> Result<String, WError> {
+    Ok(parse_certificate(der)?.tbs_certificate.subject.to_string())
+}
+
+/// The e-mail addresses and URIs of the Subject Alternative Name extension.
+pub fn certificate_identities(der: &[u8]) -> Result<Vec<String>, WError> {
+    let certificate = parse_certificate(der)?;
+    let san = certificate
+        .tbs_certificate
+        .get::<SubjectAltName>()
+        .map_err(certificate_error)?;
+    let mut identities = vec![];
+    for name in san.map(|(_, san)| san.0).unwrap_or_default() {
+        match name {
+            GeneralName::Rfc822Name(name) | GeneralName::UniformResourceIdentifier(name) => {
+                identities.push(name.as_str().to_string())
+            }
+            _ => {}
+        }
+    }
+    Ok(identities)
+}
+
+/// The raw value of an extension, if present.
+pub fn certificate_extension(der: &[u8], oid: &str) -> Result<Option<Vec<u8>>, WError> {
+    let oid = ObjectIdentifier::new(oid).map_err(certificate_error)?;
+    let certificate = parse_certificate(der)?;
+    Ok(certificate
+        .tbs_certificate
+        .extensions
+        .unwrap_or_default()
+        .into_iter()
+        .find(|extension| extension.extn_id == oid)
+        .map(|extension| extension.extn_value.as_bytes().to_vec()))
+}
+
+/// Parses an RFC 3339 UTC timestamp, ignoring fractional seconds.
+pub fn parse_rfc3339(s: &str) -> Result<u64, WError> {
+    let s = match s.find('.') {
+        Some(dot) if s.ends_with('Z') => format!("{}Z", &s[..dot]),
+        _ => s.to_string(),
+    };
+    let datetime: x509_cert::der::DateTime = s
+        .parse()
+        .map_err(|_| WError::ParseError(format!("Invalid timestamp: {}", s)))?;
+    Ok(datetime.unix_duration().as_secs())
+}
+
+/// The public key of a certificate, if it can be used to verify signatures.
+pub fn certificate_public_key(der: &[u8]) -> Result<PublicKey, WError> {
+    let spki = parse_certificate(der)?.tbs_certificate.subject_public_key_info;
+    match spki.algorithm.oid {
+        ED25519 => Ok(PublicKey::new(
+            eddsa::ALG_ID,
+            spki.subject_public_key.raw_bytes().to_vec(),
+        )),
+        _ => Err(WError::CertificateError(
+            "Unsupported public key type in the signer certificate".to_string(),
+        )),
+    }
+}
+
+/// A set of root certificates, to verify signatures made with certified keys.
+#[derive(Clone, Debug, Default)]
+pub struct TrustedRoots {
+    roots: Vec<Vec<u8>>,
+}
+
+impl TrustedRoots {
+    pub fn new(roots: Vec<Vec<u8>>) -> Self {
+        TrustedRoots { roots }
+    }
+
+    pub fn from_pem(pem: &str) -> Result<Self, WError> {
+        let roots = pem_decode(pem, "CERTIFICATE")?;
+        if roots.is_empty() {
+            return Err(WError::ParseError("No root certificates".to_string()));
+        }
+        Ok(TrustedRoots { roots })
+    }
+
+    pub fn roots(&self) -> &[Vec<u8>] {
+        &self.roots
+    }
+
+    /// Verifies the certificate chain of a signature at the given time, and
+    /// returns the public key to verify the signature with.
+    pub fn signer_public_key(
+        &self,
+        signature: &Signature,
+        unix_time: u64,
+    ) -> Result<PublicKey, WError> {
+        let (leaf, intermediates) = signature
+            .certificates()
+            .split_first()
+            .ok_or_else(|| WError::CertificateError("No certificate chain".to_string()))?;
+        verify_chain(leaf, intermediates, &self.roots, unix_time)?;
+        let pk = certificate_public_key(leaf)?;
+        if pk.alg_id() != signature.alg_id() {
+            return Err(WError::SignatureError(
+                "Signature uses a different scheme than the signer certificate",
+            ));
+        }
+        Ok(pk)
+    }
+}
+
+/// A signer that adds a certificate chain to the signatures it makes.
+pub struct CertifiedSigner {
+    signer: Box<dyn Signer>,
+    certificates: Vec<Vec<u8>>,
+}
+
+impl CertifiedSigner {
+    /// Wraps a signer, after checking that the first certificate is for its public key.
+    pub fn new(signer: Box<dyn Signer>, certificates: Vec<Vec<u8>>) -> Result<Self, WError> {
+        let leaf_pk = match certificates.first() {
+            None => return Err(WError::CertificateError("No certificates".to_string())),
+            Some(leaf) => certificate_public_key(leaf)?,
+        };
+        let pk = signer.public_key().ok_or(WError::UsageError(
+            "The public key of the signer is required to check its certificate",
+        ))?;
+        if leaf_pk.alg_id() != pk.alg_id() || leaf_pk.raw() != pk.raw() {
+            return Err(WError::CertificateError(
+                "The certificate doesn't match the signing key".to_string(),
+            ));
+        }
+        Ok(CertifiedSigner {
+            signer,
+            certificates,
+        })
+    }
+}
+
+impl Signer for CertifiedSigner {
+    fn alg_id(&self) -> u32 {
+        self.signer.alg_id()
+    }
+
+    fn sign_prehashed(&self, prehashed: &[u8]) -> Result<Vec<u8>, WError> {
+        self.signer.sign_prehashed(prehashed)
+    }
+
+    fn public_key(&self) -> Option<PublicKey> {
+        self.signer.public_key()
+    }
+
+    fn sign_request(&self, request: &SigningRequest) -> Result<Signature, WError> {
+        Ok(self
+            .signer
+            .sign_request(request)?
+            .with_certificates(&self.certificates))
+    }
+}
@@ -1,14 +1,17 @@
 use super::super::errors::*;
 use super::super::signature::*;
+use super::merkle::*;
+use super::sections::{custom_section, raw_sections, signed_data};
+use super::verify::signature_data_segment_mut;
 use byteorder::{LittleEndian, WriteBytesExt};
 use parity_wasm::elements::*;
-use std::{i32, u32};
 
-pub fn attach_signature(
+pub fn prepare_signature(
     module_bytes: &[u8],
-    signature_alg: &Box<dyn SignatureAlg>,
-    ad: Option<&[u8]>,
-    key_pair: &KeyPair,
+    signature_alg: &dyn SignatureAlg,
+    hash_alg: HashAlg,
+    context: Option<&str>,
+    ad: &AdditionalData,
     signature_symbol: &str,
 ) -> Result<Vec<u8>, WError> {
     let mut module: Module = parity_wasm::deserialize_buffer(module_bytes)?;
@@ -53,7 +56,7 @@ pub fn attach_signature(
 
     // Add the signature to the data section
 
-    let (new_data_segment_offset, new_data_segment_len, new_data_memory_index, new_data_index) = {
+    let (new_data_segment_offset, new_data_segment_len, new_data_memory_index) = {
         let data_section = module.data_section().expect("No data section");
         if (i32::MAX as u32 - last_data_segment.0 as u32) < last_data_segment.1 {
             return Err(WError::UsageError(
@@ -66,10 +69,10 @@ pub fn attach_signature(
         let new_data_segment_offset = last_data_segment.0 + last_data_segment.1 as i32;
         let new_data_memory_index = 0;
         let new_data_segment_offset_code = vec![
-            Instruction::I32Const(new_data_segment_offset as i32),
+            Instruction::I32Const(new_data_segment_offset),
             Instruction::End,
         ];
-        let empty_signature = vec![0; Signature::length(signature_alg)];
+        let empty_signature = vec![0; Signature::length(signature_alg, hash_alg, context, ad)];
         let new_data = empty_signature;
         let new_data_segment_len = new_data.len() as u32;
         let new_data_segment = DataSegment::new(
@@ -78,13 +81,11 @@ pub fn attach_signature(
             new_data,
         );
         let data_section = module.data_section_mut().expect("No data section");
-        let new_data_index = data_section.entries().len();
         data_section.entries_mut().push(new_data_segment);
         (
             new_data_segment_offset,
             new_data_segment_len,
             new_data_memory_index,
-            new_data_index,
         )
     };
 
@@ -103,7 +104,7 @@ pub fn attach_signature(
         let new_ref_data_segment_offset = new_data_segment_offset + new_data_segment_len as i32;
         let new_ref_data_memory_index = new_data_memory_index;
         let new_ref_data_segment_offset_code = vec![
-            Instruction::I32Const(new_ref_data_segment_offset as i32),
+            Instruction::I32Const(new_ref_data_segment_offset),
             Instruction::End,
         ];
         let mut ref_bytes = vec![];
@@ -130,7 +131,7 @@ pub fn attach_signature(
         let new_global_id = new_global_id as u32;
         let new_global_type = GlobalType::new(ValueType::I32, false);
         let new_global_code = vec![
-            Instruction::I32Const(new_ref_data_segment_offset as i32),
+            Instruction::I32Const(new_ref_data_segment_offset),
             Instruction::End,
         ];
         let new_global_entry = GlobalEntry::new(new_global_type, InitExpr::new(new_global_code));
@@ -159,71 +160,153 @@ pub fn attach_signature(
         export_section.entries_mut().push(new_export_entry);
     }
 
+    let prepared_module_bytes = parity_wasm::serialize(module)?;
+    Ok(prepared_module_bytes)
+}
+
+pub fn store_signature(
+    prepared_module_bytes: &[u8],
+    signature: &Signature,
+    signature_symbol: &str,
+) -> Result<Vec<u8>, WError> {
+    let mut module: Module = parity_wasm::deserialize_buffer(prepared_module_bytes)?;
+
     // Store the actual signature
 
-    let module = {
-        let module_code = parity_wasm::serialize(module)?;
-        let signature = signature_alg.sign(&module_code, ad, key_pair)?;
-        let mut module: Module = parity_wasm::deserialize_buffer(&module_code)?;
-        let data_section = module.data_section_mut().expect("No data section");
-        let signature_bytes = data_section.entries_mut()[new_data_index].value_mut();
-        let empty_signature = vec![0; Signature::length(signature_alg)];
-        assert_eq!(signature_bytes, &empty_signature);
-        signature_bytes[..].copy_from_slice(&signature.to_bytes());
-        module
-    };
+    {
+        let signature_bytes = signature_data_segment_mut(&mut module, signature_symbol)?;
+        let encoded_signature = signature.to_bytes();
+        if signature_bytes.len() != encoded_signature.len() {
+            return Err(WError::SignatureError(
+                "Signature doesn't match the size of the reserved space",
+            ));
+        }
+        if signature_bytes.iter().any(|&x| x != 0) {
+            return Err(WError::ParseError("Module is already signed".to_string()));
+        }
+        signature_bytes[..].copy_from_slice(&encoded_signature);
+    }
 
     let signed_module_bytes = parity_wasm::serialize(module)?;
     Ok(signed_module_bytes)
 }
 
-pub fn attach_signature_in_custom_section(
+pub fn attach_signature(
     module_bytes: &[u8],
-    signature_alg: &Box<dyn SignatureAlg>,
-    ad: Option<&[u8]>,
-    key_pair: &KeyPair,
-    signature_section_name: &str,
+    signer: &dyn Signer,
+    ad: &AdditionalData,
+    hash_alg: HashAlg,
+    context: Option<&str>,
+    signature_symbol: &str,
 ) -> Result<Vec<u8>, WError> {
-    // Check if Custom Section with the requested name can be stored using a single-byte varint.
-
-    let section_len = 1 + signature_section_name.len() + Signature::length(signature_alg);
-    if section_len > 127 {
-        return Err(WError::UsageError(
-            "Custom Section name is too long (max. 58 characters)",
-        ));
-    }
+    let signature_alg = alg_from_id(signer.alg_id())?;
+    let prepared_module_bytes = prepare_signature(
+        module_bytes,
+        signature_alg.as_ref(),
+        hash_alg,
+        context,
+        ad,
+        signature_symbol,
+    )?;
+    let signature = signer.sign(&prepared_module_bytes, ad, hash_alg, context)?;
+    store_signature(&prepared_module_bytes, &signature, signature_symbol)
+}
 
+pub fn prepare_signature_in_custom_section(
+    module_bytes: &[u8],
+    signature_section_name: &str,
+) -> Result<(), WError> {
     // Check if the Custom Section with a signature already exists
 
+    let module: Module = parity_wasm::deserialize_buffer(module_bytes)?;
+    if module
+        .custom_sections()
+        .any(|section| section.name() == signature_section_name)
     {
-        let module: Module = parity_wasm::deserialize_buffer(module_bytes)?;
-        if module
-            .custom_sections()
-            .any(|section| section.name() == signature_section_name)
-        {
-            return Err(WError::ParseError(format!(
-                "{} Custom Section already present",
-                signature_section_name
-            )));
-        }
+        return Err(WError::ParseError(format!(
+            "{} Custom Section already present",
+            signature_section_name
+        )));
+    }
+    Ok(())
+}
+
+pub fn store_signature_in_custom_section(
+    module_bytes: &[u8],
+    signature: &Signature,
+    signature_section_name: &str,
+) -> Result<Vec<u8>, WError> {
+    let signature_alg = signature.to_alg()?;
+    prepare_signature_in_custom_section(module_bytes, signature_section_name)?;
+    if signature.raw().len() != signature_alg.raw_signature_length() {
+        return Err(WError::SignatureError("Unexpected signature length"));
     }
 
     // Add Custom Section with the signature to the end of the module
 
-    let signature = signature_alg.sign(module_bytes, ad, key_pair)?;
+    let mut signed_module_bytes = module_bytes.to_vec();
+    signed_module_bytes.extend_from_slice(&custom_section(
+        signature_section_name,
+        &signature.to_bytes(),
+    ));
+    Ok(signed_module_bytes)
+}
 
-    let signed_module_bytes = {
-        let mut custom_section = Vec::<u8>::with_capacity(section_len + 2);
-        custom_section.extend_from_slice(&[0]);
-        custom_section.extend_from_slice(&[section_len as u8]);
-        custom_section.extend_from_slice(&[signature_section_name.len() as u8]);
-        custom_section.extend_from_slice(signature_section_name.as_bytes());
-        custom_section.extend_from_slice(&signature.to_bytes());
+/// Replaces the signature stored in a Custom Section. The section is rewritten
+/// in place, so that its position doesn't change.
+pub fn replace_signature_in_custom_section(
+    module_bytes: &[u8],
+    signature: &Signature,
+    signature_section_name: &str,
+) -> Result<Vec<u8>, WError> {
+    let mut updated_module_bytes = module_bytes[..8].to_vec();
+    for section in raw_sections(module_bytes)? {
+        if section.name.as_deref() == Some(signature_section_name) {
+            updated_module_bytes.extend_from_slice(&custom_section(
+                signature_section_name,
+                &signature.to_bytes(),
+            ));
+        } else {
+            updated_module_bytes.extend_from_slice(&module_bytes[section.range]);
+        }
+    }
+    Ok(updated_module_bytes)
+}
 
-        let mut signed_module_bytes = module_bytes.to_vec();
-        signed_module_bytes.extend_from_slice(&custom_section);
-        signed_module_bytes
-    };
+pub fn attach_signature_in_custom_section(
+    module_bytes: &[u8],
+    signer: &dyn Signer,
+    ad: &AdditionalData,
+    hash_alg: HashAlg,
+    context: Option<&str>,
+    sections: &SectionSelection,
+    signature_section_name: &str,
+) -> Result<Vec<u8>, WError> {
+    alg_from_id(signer.alg_id())?;
+    prepare_signature_in_custom_section(module_bytes, signature_section_name)?;
+    let data = signed_data(module_bytes, sections, signature_section_name)?;
+    let signature = signer
+        .sign(&data, ad, hash_alg, context)?
+        .with_sections(sections);
+    store_signature_in_custom_section(module_bytes, &signature, signature_section_name)
+}
 
-    Ok(signed_module_bytes)
+pub fn attach_merkle_signature_in_custom_section(
+    module_bytes: &[u8],
+    signer: &dyn Signer,
+    ad: &AdditionalData,
+    hash_alg: HashAlg,
+    context: Option<&str>,
+    sections: &SectionSelection,
+    signature_section_name: &str,
+) -> Result<Vec<u8>, WError> {
+    alg_from_id(signer.alg_id())?;
+    prepare_signature_in_custom_section(module_bytes, signature_section_name)?;
+    let leaves = merkle_leaves(module_bytes, hash_alg, sections, signature_section_name)?;
+    let data = merkle_signed_data(hash_alg, sections, &leaves);
+    let signature = signer
+        .sign(&data, ad, hash_alg, context)?
+        .with_sections(sections)
+        .with_merkle_leaves(&leaves);
+    store_signature_in_custom_section(module_bytes, &signature, signature_section_name)
 }
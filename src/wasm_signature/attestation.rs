@@ -0,0 +1,87 @@
+use super::super::errors::*;
+use super::super::signature::intoto::*;
+use super::super::signature::*;
+use super::attach::prepare_signature_in_custom_section;
+use super::envelope::check_digests;
+use super::sections::custom_section;
+use super::verify::signature_section_payload;
+use ct_codecs::{Encoder, Hex};
+use std::collections::BTreeMap;
+
+/// Binds a statement to a module, signs it, and stores the resulting DSSE
+/// envelope in a Custom Section. The subject is the content of the module
+/// before that section.
+pub fn attach_attestation(
+    module_bytes: &[u8],
+    signer: &dyn Signer,
+    statement: &Statement,
+    subject_name: &str,
+    hash_alg: HashAlg,
+    key_id: Option<&[u8]>,
+    attestation_section_name: &str,
+) -> Result<Vec<u8>, WError> {
+    prepare_signature_in_custom_section(module_bytes, attestation_section_name)?;
+    let mut digest = BTreeMap::new();
+    digest.insert(
+        digest_name(hash_alg).to_string(),
+        Hex::encode_to_string(hash_alg.hash(module_bytes))
+            .map_err(|_| WError::InternalError("hex"))?,
+    );
+    let mut statement = statement.clone();
+    statement.subject = vec![Subject {
+        name: subject_name.to_string(),
+        digest,
+    }];
+    let envelope = dsse::sign(PAYLOAD_TYPE, &statement.to_json()?, signer, key_id)?;
+    let mut attested_module_bytes = module_bytes.to_vec();
+    attested_module_bytes.extend_from_slice(&custom_section(attestation_section_name, &envelope));
+    Ok(attested_module_bytes)
+}
+
+fn statement_from_envelope(payload_type: &str, payload: &[u8]) -> Result<Statement, WError> {
+    if payload_type != PAYLOAD_TYPE {
+        return Err(WError::ParseError(format!(
+            "Unsupported payload type: {}",
+            payload_type
+        )));
+    }
+    Statement::from_json(payload)
+}
+
+/// Returns the statement stored in a module, without verifying it.
+pub fn extract_attestation(
+    module_bytes: &[u8],
+    attestation_section_name: &str,
+) -> Result<Statement, WError> {
+    let envelope = signature_section_payload(module_bytes, attestation_section_name)?;
+    let (payload_type, payload) = dsse::open(&envelope)?;
+    statement_from_envelope(&payload_type, &payload)
+}
+
+pub fn verify_attestation(
+    module_bytes: &[u8],
+    pk: &PublicKey,
+    attestation_section_name: &str,
+) -> Result<Statement, WError> {
+    let envelope = signature_section_payload(module_bytes, attestation_section_name)?;
+    let section = custom_section(attestation_section_name, &envelope);
+    if !module_bytes.ends_with(&section) {
+        return Err(WError::ParseError(format!(
+            "Custom Section {} must be the last section",
+            attestation_section_name
+        )));
+    }
+    let module_bytes = &module_bytes[..module_bytes.len() - section.len()];
+    let (payload_type, payload) = dsse::verify(&envelope, pk)?;
+    let statement = statement_from_envelope(&payload_type, &payload)?;
+    let mut res = Err(WError::ParseError(
+        "The statement has no subject".to_string(),
+    ));
+    for subject in &statement.subject {
+        res = check_digests(module_bytes, &subject.digest);
+        if res.is_ok() {
+            break;
+        }
+    }
+    res.map(|_| statement)
+}
@@ -0,0 +1,129 @@
+use super::super::errors::*;
+use super::super::signature::cose::*;
+use super::super::signature::*;
+use super::attach::prepare_signature_in_custom_section;
+use super::sections::custom_section;
+use super::verify::signature_section_payload;
+use coset::cbor::value::Value;
+use coset::{
+    CborSerializable, CoseSign1, CoseSign1Builder, HeaderBuilder, Label, TaggedCborSerializable,
+};
+
+const CONTEXT_LABEL: &str = "wasmsign-context";
+const CONTENT_TYPE: &str = "application/wasm";
+
+fn cose_error(e: coset::CoseError) -> WError {
+    WError::ParseError(format!("Invalid COSE structure: {}", e))
+}
+
+/// Signs a module with a COSE_Sign1 structure stored in a Custom Section.
+/// The payload is detached: it is the content of the module before that section.
+pub fn attach_cose_signature(
+    module_bytes: &[u8],
+    signer: &dyn Signer,
+    ad: &AdditionalData,
+    context: Option<&str>,
+    key_id: Option<&[u8]>,
+    signature_section_name: &str,
+) -> Result<Vec<u8>, WError> {
+    prepare_signature_in_custom_section(module_bytes, signature_section_name)?;
+    check_context(context)?;
+    let mut protected = HeaderBuilder::new()
+        .algorithm(cose_algorithm(signer.alg_id())?)
+        .content_type(CONTENT_TYPE.to_string());
+    if let Some(key_id) = key_id {
+        protected = protected.key_id(key_id.to_vec());
+    }
+    if let Some(context) = context.filter(|&context| context != DEFAULT_CONTEXT) {
+        protected = protected.text_value(CONTEXT_LABEL.to_string(), Value::Text(context.to_string()));
+    }
+    let sign1 = CoseSign1Builder::new()
+        .protected(protected.build())
+        .try_create_detached_signature(module_bytes, ad.as_bytes(), |tbs| {
+            signer.sign_prehashed(tbs)
+        })?
+        .build();
+    let mut signed_module_bytes = module_bytes.to_vec();
+    signed_module_bytes.extend_from_slice(&custom_section(
+        signature_section_name,
+        &sign1.to_tagged_vec().map_err(cose_error)?,
+    ));
+    Ok(signed_module_bytes)
+}
+
+pub fn extract_cose_signature(
+    module_bytes: &[u8],
+    signature_section_name: &str,
+) -> Result<CoseSign1, WError> {
+    let payload = signature_section_payload(module_bytes, signature_section_name)?;
+    CoseSign1::from_tagged_slice(&payload)
+        .or_else(|_| CoseSign1::from_slice(&payload))
+        .map_err(cose_error)
+}
+
+pub fn verify_cose_signature(
+    module_bytes: &[u8],
+    ad: &AdditionalData,
+    context: Option<&str>,
+    pk: &PublicKey,
+    signature_section_name: &str,
+) -> Result<(), WError> {
+    // Find the Custom Section with a signature
+
+    let payload = signature_section_payload(module_bytes, signature_section_name)?;
+    let section = custom_section(signature_section_name, &payload);
+    if !module_bytes.ends_with(&section) {
+        return Err(WError::ParseError(format!(
+            "Custom Section {} must be the last section",
+            signature_section_name
+        )));
+    }
+    let signed_module_bytes = &module_bytes[0..module_bytes.len() - section.len()];
+    let sign1 = extract_cose_signature(module_bytes, signature_section_name)?;
+
+    // Check the protected header
+
+    let protected = &sign1.protected.header;
+    if !protected.crit.is_empty() {
+        return Err(WError::ParseError(
+            "Unsupported critical COSE header parameters".to_string(),
+        ));
+    }
+    let alg_id = match &protected.alg {
+        None => return Err(WError::ParseError("Missing COSE algorithm".to_string())),
+        Some(alg) => alg_id_from_cose(alg)?,
+    };
+    if alg_id != pk.alg_id() {
+        return Err(WError::SignatureError(
+            "Signature uses a different scheme than the provided public key",
+        ));
+    }
+    let signature_context = protected.rest.iter().find_map(|(label, value)| match (label, value) {
+        (Label::Text(label), Value::Text(context)) if label == CONTEXT_LABEL => {
+            Some(context.as_str())
+        }
+        _ => None,
+    });
+    if signature_context != context.filter(|&context| context != DEFAULT_CONTEXT) {
+        return Err(WError::ParseError(format!(
+            "Signature was created for the [{}] context",
+            signature_context.unwrap_or(DEFAULT_CONTEXT)
+        )));
+    }
+
+    // Check the signature
+
+    let signature_alg = alg_from_id(alg_id)?;
+    let verifier = |raw: &[u8], tbs: &[u8]| {
+        signature_alg.verify_prehashed(tbs, pk.raw(), &Signature::new(alg_id, raw.to_vec()))
+    };
+    match &sign1.payload {
+        None => sign1.verify_detached_signature(signed_module_bytes, ad.as_bytes(), verifier),
+        Some(payload) if payload == signed_module_bytes => {
+            sign1.verify_signature(ad.as_bytes(), verifier)
+        }
+        Some(_) => Err(WError::SignatureError(
+            "The COSE payload doesn't match the module",
+        )),
+    }
+}
@@ -0,0 +1,136 @@
+use super::super::errors::*;
+use super::super::signature::*;
+use super::attach::prepare_signature_in_custom_section;
+use super::sections::custom_section;
+use super::verify::signature_section_payload;
+use ct_codecs::{Encoder, Hex};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+pub const DIGEST_PAYLOAD_TYPE: &str = "application/vnd.wasmsign.digest+json";
+
+#[derive(Serialize, Deserialize)]
+struct DigestStatement {
+    digest: BTreeMap<String, String>,
+}
+
+fn digest_statement(module_bytes: &[u8], hash_alg: HashAlg) -> Result<Vec<u8>, WError> {
+    let mut digest = BTreeMap::new();
+    digest.insert(
+        hash_alg.name().to_string(),
+        Hex::encode_to_string(hash_alg.hash(module_bytes))
+            .map_err(|_| WError::InternalError("hex"))?,
+    );
+    serde_json::to_vec(&DigestStatement { digest }).map_err(|_| WError::InternalError("json"))
+}
+
+/// Checks that every digest that uses a supported hash function matches the
+/// module. At least one of them must be supported.
+pub fn check_digests(
+    module_bytes: &[u8],
+    digests: &BTreeMap<String, String>,
+) -> Result<(), WError> {
+    let mut checked = false;
+    for (name, expected) in digests {
+        let hash_alg = match HashAlg::from_name(name) {
+            Ok(hash_alg) => hash_alg,
+            Err(_) => continue,
+        };
+        let computed = Hex::encode_to_string(hash_alg.hash(module_bytes))
+            .map_err(|_| WError::InternalError("hex"))?;
+        if !computed.eq_ignore_ascii_case(expected) {
+            return Err(WError::SignatureError(
+                "The module doesn't match the signed digest",
+            ));
+        }
+        checked = true;
+    }
+    match checked {
+        true => Ok(()),
+        false => Err(WError::ParseError(
+            "No supported digest in the signed statement".to_string(),
+        )),
+    }
+}
+
+fn check_digest_statement(module_bytes: &[u8], statement: &[u8]) -> Result<(), WError> {
+    let statement: DigestStatement = serde_json::from_slice(statement)
+        .map_err(|e| WError::ParseError(format!("Invalid digest statement: {}", e)))?;
+    check_digests(module_bytes, &statement.digest)
+}
+
+/// Signs the digest of a module, and returns a DSSE envelope or a compact JWS.
+pub fn sign_envelope(
+    module_bytes: &[u8],
+    signer: &dyn Signer,
+    hash_alg: HashAlg,
+    key_id: Option<&[u8]>,
+    format: EnvelopeFormat,
+) -> Result<Vec<u8>, WError> {
+    let statement = digest_statement(module_bytes, hash_alg)?;
+    match format {
+        EnvelopeFormat::Dsse => dsse::sign(DIGEST_PAYLOAD_TYPE, &statement, signer, key_id),
+        EnvelopeFormat::Jws => {
+            Ok(jws::sign(DIGEST_PAYLOAD_TYPE, &statement, signer, key_id)?.into_bytes())
+        }
+    }
+}
+
+pub fn verify_envelope(
+    module_bytes: &[u8],
+    pk: &PublicKey,
+    envelope: &[u8],
+) -> Result<(), WError> {
+    let (payload_type, statement) = match EnvelopeFormat::detect(envelope) {
+        EnvelopeFormat::Dsse => {
+            let (payload_type, statement) = dsse::verify(envelope, pk)?;
+            (Some(payload_type), statement)
+        }
+        EnvelopeFormat::Jws => {
+            let jws = std::str::from_utf8(envelope)
+                .map_err(|_| WError::ParseError("Invalid JWS encoding".to_string()))?;
+            jws::verify(jws, pk)?
+        }
+    };
+    if payload_type.as_deref() != Some(DIGEST_PAYLOAD_TYPE) {
+        return Err(WError::ParseError(format!(
+            "Unsupported payload type: {}",
+            payload_type.unwrap_or_default()
+        )));
+    }
+    check_digest_statement(module_bytes, &statement)
+}
+
+/// Signs a module and stores the envelope in a Custom Section. The signed
+/// digest is the one of the module before that section.
+pub fn attach_envelope_in_custom_section(
+    module_bytes: &[u8],
+    signer: &dyn Signer,
+    hash_alg: HashAlg,
+    key_id: Option<&[u8]>,
+    format: EnvelopeFormat,
+    signature_section_name: &str,
+) -> Result<Vec<u8>, WError> {
+    prepare_signature_in_custom_section(module_bytes, signature_section_name)?;
+    let envelope = sign_envelope(module_bytes, signer, hash_alg, key_id, format)?;
+    let mut signed_module_bytes = module_bytes.to_vec();
+    signed_module_bytes.extend_from_slice(&custom_section(signature_section_name, &envelope));
+    Ok(signed_module_bytes)
+}
+
+pub fn verify_envelope_in_custom_section(
+    module_bytes: &[u8],
+    pk: &PublicKey,
+    signature_section_name: &str,
+) -> Result<(), WError> {
+    let envelope = signature_section_payload(module_bytes, signature_section_name)?;
+    let section = custom_section(signature_section_name, &envelope);
+    if !module_bytes.ends_with(&section) {
+        return Err(WError::ParseError(format!(
+            "Custom Section {} must be the last section",
+            signature_section_name
+        )));
+    }
+    let module_bytes = &module_bytes[..module_bytes.len() - section.len()];
+    verify_envelope(module_bytes, pk, &envelope)
+}
@@ -0,0 +1,49 @@
+use super::super::errors::*;
+use super::super::signature::tlog::*;
+use super::attach::replace_signature_in_custom_section;
+use super::verify::extract_signature_from_custom_section;
+use super::wasmsign2::strip_custom_section;
+
+/// Adds the inclusion proof of a log entry to the signature stored in a Custom Section.
+pub fn attach_log_proof_in_custom_section(
+    module_bytes: &[u8],
+    log_proof: &InclusionProof,
+    signature_section_name: &str,
+) -> Result<Vec<u8>, WError> {
+    let signature = extract_signature_from_custom_section(module_bytes, signature_section_name)?;
+    replace_signature_in_custom_section(
+        module_bytes,
+        &signature.with_log_proof(Some(log_proof)),
+        signature_section_name,
+    )
+}
+
+/// Verifies that the signing of a module was recorded in a log whose current
+/// head is `head`. `consistency_proof` proves that the tree the entry was
+/// included in is a prefix of that log.
+pub fn verify_log_proof_in_custom_section(
+    module_bytes: &[u8],
+    key_id: Option<&[u8]>,
+    head: &TreeHead,
+    consistency_proof: &[Vec<u8>],
+    signature_section_name: &str,
+) -> Result<InclusionProof, WError> {
+    let signature = extract_signature_from_custom_section(module_bytes, signature_section_name)?;
+    let log_proof = signature
+        .log_proof()
+        .ok_or(WError::SignatureError("The signature was not logged"))?;
+    let unsigned_module_bytes = strip_custom_section(module_bytes, signature_section_name)?;
+    if !log_proof.entry.matches_module(&unsigned_module_bytes) {
+        return Err(WError::SignatureError(
+            "The log entry was recorded for a different module",
+        ));
+    }
+    if key_id.is_some_and(|key_id| key_id != log_proof.entry.key_id.as_slice()) {
+        return Err(WError::SignatureError(
+            "The log entry was recorded for a different key",
+        ));
+    }
+    log_proof.verify()?;
+    verify_consistency(&log_proof.head, head, consistency_proof)?;
+    Ok(log_proof.clone())
+}
@@ -0,0 +1,99 @@
+use super::super::errors::*;
+use super::super::signature::*;
+use super::sections::{read_varuint32, selected_sections};
+
+const MERKLE_MAGIC: &[u8] = b"wasmsign-merkle-v1\0";
+const CODE_SECTION_ID: u8 = 10;
+
+/// Splits the signed part of a module into the chunks that become leaves of
+/// the Merkle tree: the header, every section, and every function body.
+pub fn merkle_chunks<'t>(
+    module_bytes: &'t [u8],
+    sections: &SectionSelection,
+    signature_section_name: &str,
+) -> Result<Vec<(LeafKind, &'t [u8])>, WError> {
+    let mut chunks = vec![(LeafKind::Header, &module_bytes[..8.min(module_bytes.len())])];
+    for section in selected_sections(module_bytes, sections, signature_section_name)? {
+        let payload = section.payload;
+        match (section.id, section.name) {
+            (_, Some(name)) => chunks.push((LeafKind::CustomSection(name), payload)),
+            (CODE_SECTION_ID, None) => {
+                let mut offset = 0;
+                let count = read_varuint32(payload, &mut offset)?;
+                chunks.push((LeafKind::Section(CODE_SECTION_ID), &payload[..offset]));
+                for index in 0..count {
+                    let len = read_varuint32(payload, &mut offset)? as usize;
+                    if payload.len() - offset < len {
+                        return Err(WError::ParseError("Truncated function body".to_string()));
+                    }
+                    chunks.push((LeafKind::Function(index), &payload[offset..offset + len]));
+                    offset += len;
+                }
+                if offset != payload.len() {
+                    return Err(WError::ParseError(
+                        "Trailing data in the code section".to_string(),
+                    ));
+                }
+            }
+            (id, None) => chunks.push((LeafKind::Section(id), payload)),
+        }
+    }
+    Ok(chunks)
+}
+
+pub fn merkle_leaves(
+    module_bytes: &[u8],
+    hash_alg: HashAlg,
+    sections: &SectionSelection,
+    signature_section_name: &str,
+) -> Result<Vec<MerkleLeaf>, WError> {
+    Ok(
+        merkle_chunks(module_bytes, sections, signature_section_name)?
+            .into_iter()
+            .map(|(kind, data)| MerkleLeaf::new(hash_alg, kind, data))
+            .collect(),
+    )
+}
+
+/// The data covered by a Merkle signature: the section selection and the root.
+pub fn merkle_signed_data(
+    hash_alg: HashAlg,
+    sections: &SectionSelection,
+    leaves: &[MerkleLeaf],
+) -> Vec<u8> {
+    let mut data = MERKLE_MAGIC.to_vec();
+    data.extend_from_slice(&sections.to_bytes());
+    data.extend_from_slice(&merkle_root(hash_alg, leaves));
+    data
+}
+
+/// Compares the leaves recorded in a signature with the module, and reports the
+/// first chunk that differs. Only the chunks accepted by `filter` are hashed.
+pub fn check_merkle_leaves(
+    module_bytes: &[u8],
+    signature: &Signature,
+    leaves: &[MerkleLeaf],
+    signature_section_name: &str,
+    filter: &dyn Fn(&LeafKind) -> bool,
+) -> Result<(), WError> {
+    let chunks = merkle_chunks(module_bytes, signature.sections(), signature_section_name)?;
+    for (i, (kind, _)) in chunks.iter().enumerate() {
+        match leaves.get(i) {
+            Some(leaf) if leaf.kind() == kind => {}
+            _ => return Err(WError::SectionMismatch(kind.to_string())),
+        }
+    }
+    if let Some(leaf) = leaves.get(chunks.len()) {
+        return Err(WError::SectionMismatch(leaf.kind().to_string()));
+    }
+    for ((kind, data), leaf) in chunks.into_iter().zip(leaves) {
+        if !filter(&kind) {
+            continue;
+        }
+        let kind_name = kind.to_string();
+        if MerkleLeaf::new(signature.hash_alg(), kind, data).hash() != leaf.hash() {
+            return Err(WError::SectionMismatch(kind_name));
+        }
+    }
+    Ok(())
+}
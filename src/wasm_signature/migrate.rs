@@ -0,0 +1,102 @@
+use super::super::errors::*;
+use byteorder::{ByteOrder, LittleEndian};
+use parity_wasm::elements::*;
+
+fn segment_offset(data_segment: &DataSegment) -> Option<i32> {
+    match data_segment.offset().as_ref().map(|offset| offset.code()) {
+        Some([Instruction::I32Const(offset), Instruction::End]) => Some(*offset),
+        _ => None,
+    }
+}
+
+/// Removes the global, the export and the data segments added to a module
+/// by `prepare_signature`.
+pub fn strip_signature(
+    module_bytes: &[u8],
+    signature_symbol: &str,
+    log: &mut dyn FnMut(&str),
+) -> Result<Vec<u8>, WError> {
+    let mut module: Module = parity_wasm::deserialize_buffer(module_bytes)?;
+
+    // Remove the export
+
+    let global_id = {
+        let export_section = module
+            .export_section_mut()
+            .ok_or_else(|| WError::ParseError("No export section".to_string()))?;
+        let entries = export_section.entries_mut();
+        let index = entries
+            .iter()
+            .position(|export_entry| export_entry.field() == signature_symbol)
+            .ok_or_else(|| {
+                WError::ParseError(format!("Symbol {} not found", signature_symbol))
+            })?;
+        let global_id = match entries[index].internal() {
+            Internal::Global(global_id) => *global_id,
+            _ => {
+                return Err(WError::ParseError(
+                    "Wrong type for the signature global".to_string(),
+                ))
+            }
+        };
+        entries.remove(index);
+        log(&format!("Removed the {} export", signature_symbol));
+        global_id
+    };
+
+    // Remove the global. Only the last global can be removed without
+    // renumbering the others.
+
+    let ref_data_segment_offset = {
+        let global_section = module
+            .global_section_mut()
+            .ok_or_else(|| WError::ParseError("No global section".to_string()))?;
+        let entries = global_section.entries_mut();
+        if global_id as usize + 1 != entries.len() {
+            return Err(WError::ParseError(
+                "The signature global is not the last global".to_string(),
+            ));
+        }
+        let ref_data_segment_offset = match entries[global_id as usize].init_expr().code() {
+            [Instruction::I32Const(offset), Instruction::End] => *offset,
+            _ => return Err(WError::ParseError("Unexpected offset type".to_string())),
+        };
+        entries.pop();
+        log(&format!("Removed global #{}", global_id));
+        ref_data_segment_offset
+    };
+
+    // Remove the data segments holding the address of the signature, and the
+    // signature itself. They are the last two segments.
+
+    {
+        let data_section = module
+            .data_section_mut()
+            .ok_or_else(|| WError::ParseError("No data section".to_string()))?;
+        let entries = data_section.entries_mut();
+        let count = entries.len();
+        if count < 2 || segment_offset(&entries[count - 1]) != Some(ref_data_segment_offset) {
+            return Err(WError::ParseError(
+                "Reference data segment not found".to_string(),
+            ));
+        }
+        let ref_data_segment_value = entries[count - 1].value();
+        if ref_data_segment_value.len() != 4 {
+            return Err(WError::ParseError(
+                "Encoded reference is too short".to_string(),
+            ));
+        }
+        let data_segment_offset = LittleEndian::read_i32(ref_data_segment_value);
+        if segment_offset(&entries[count - 2]) != Some(data_segment_offset) {
+            return Err(WError::ParseError("Data segment not found".to_string()));
+        }
+        let signature_len = entries[count - 2].value().len();
+        entries.truncate(count - 2);
+        log(&format!(
+            "Removed the signature data segment ({} bytes at offset {}) and its reference",
+            signature_len, data_segment_offset
+        ));
+    }
+
+    Ok(parity_wasm::serialize(module)?)
+}
@@ -1,5 +1,23 @@
 mod attach;
+mod attestation;
+mod cose;
+mod envelope;
+mod log;
+mod merkle;
+mod migrate;
+mod sections;
+mod timestamp;
 mod verify;
+mod wasmsign2;
 
 pub(crate) use self::attach::*;
+pub(crate) use self::attestation::*;
+pub(crate) use self::cose::*;
+pub(crate) use self::envelope::*;
+pub(crate) use self::log::*;
+pub(crate) use self::merkle::{merkle_leaves, merkle_signed_data};
+pub(crate) use self::migrate::*;
+pub(crate) use self::sections::signed_data;
+pub(crate) use self::timestamp::*;
 pub(crate) use self::verify::*;
+pub(crate) use self::wasmsign2::*;
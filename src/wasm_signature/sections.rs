@@ -0,0 +1,151 @@
+use super::super::errors::*;
+use super::super::signature::SectionSelection;
+use byteorder::{LittleEndian, WriteBytesExt};
+use std::borrow::Cow;
+use std::ops::Range;
+
+pub fn write_varuint32(bytes: &mut Vec<u8>, mut v: u32) {
+    loop {
+        let byte = (v & 0x7f) as u8;
+        v >>= 7;
+        if v == 0 {
+            bytes.push(byte);
+            return;
+        }
+        bytes.push(byte | 0x80);
+    }
+}
+
+pub fn custom_section(name: &str, payload: &[u8]) -> Vec<u8> {
+    let mut content = vec![];
+    write_varuint32(&mut content, name.len() as u32);
+    content.extend_from_slice(name.as_bytes());
+    content.extend_from_slice(payload);
+    let mut section = vec![0];
+    write_varuint32(&mut section, content.len() as u32);
+    section.extend_from_slice(&content);
+    section
+}
+
+const SELECTION_MAGIC: &[u8] = b"wasmsign-sections-v1\0";
+
+/// A section, as stored in the module
+pub struct RawSection<'t> {
+    pub id: u8,
+    pub name: Option<String>,
+    pub payload: &'t [u8],
+    pub range: Range<usize>,
+}
+
+pub fn read_varuint32(bytes: &[u8], offset: &mut usize) -> Result<u32, WError> {
+    let mut v = 0u32;
+    for shift in (0..35).step_by(7) {
+        let byte = *bytes
+            .get(*offset)
+            .ok_or_else(|| WError::ParseError("Truncated module".to_string()))?;
+        *offset += 1;
+        v |= ((byte & 0x7f) as u32) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(v);
+        }
+    }
+    Err(WError::ParseError("Invalid LEB128 encoding".to_string()))
+}
+
+pub fn raw_sections(module_bytes: &[u8]) -> Result<Vec<RawSection<'_>>, WError> {
+    if module_bytes.len() < 8 || &module_bytes[..4] != b"\0asm" {
+        return Err(WError::ParseError("Not a WebAssembly module".to_string()));
+    }
+    let mut sections = vec![];
+    let mut offset = 8;
+    while offset < module_bytes.len() {
+        let start = offset;
+        let id = module_bytes[offset];
+        offset += 1;
+        let len = read_varuint32(module_bytes, &mut offset)? as usize;
+        if module_bytes.len() - offset < len {
+            return Err(WError::ParseError("Truncated section".to_string()));
+        }
+        let payload = &module_bytes[offset..offset + len];
+        offset += len;
+        let name = match id {
+            0 => {
+                let mut name_offset = 0;
+                let name_len = read_varuint32(payload, &mut name_offset)? as usize;
+                if payload.len() - name_offset < name_len {
+                    return Err(WError::ParseError("Truncated section name".to_string()));
+                }
+                let name = std::str::from_utf8(&payload[name_offset..name_offset + name_len])
+                    .map_err(|_| WError::ParseError("Invalid section name".to_string()))?;
+                Some(name.to_string())
+            }
+            _ => None,
+        };
+        sections.push(RawSection {
+            id,
+            name,
+            payload,
+            range: start..offset,
+        });
+    }
+    Ok(sections)
+}
+
+/// The sections covered by a signature. The signature section itself is never
+/// part of a selection.
+pub fn selected_sections<'t>(
+    module_bytes: &'t [u8],
+    sections: &SectionSelection,
+    signature_section_name: &str,
+) -> Result<Vec<RawSection<'t>>, WError> {
+    let mut raw_sections = raw_sections(module_bytes)?;
+    if let SectionSelection::Standard(custom_section_names) = sections {
+        if custom_section_names
+            .iter()
+            .any(|name| name == signature_section_name)
+        {
+            return Err(WError::UsageError(
+                "The signature section cannot be part of the signed sections",
+            ));
+        }
+        raw_sections.retain(|section| match &section.name {
+            None => true,
+            Some(name) => custom_section_names.contains(name),
+        });
+    }
+    Ok(raw_sections)
+}
+
+/// The data covered by a signature over the selected sections: the selection,
+/// the module header, and the id, length and payload of every selected section,
+/// regardless of how section lengths were encoded.
+fn selected_sections_data(
+    module_bytes: &[u8],
+    sections: &SectionSelection,
+    signature_section_name: &str,
+) -> Result<Vec<u8>, WError> {
+    let mut data = SELECTION_MAGIC.to_vec();
+    data.extend_from_slice(&sections.to_bytes());
+    data.extend_from_slice(&module_bytes[..8.min(module_bytes.len())]);
+    for section in selected_sections(module_bytes, sections, signature_section_name)? {
+        data.push(section.id);
+        data.write_u32::<LittleEndian>(section.payload.len() as u32)?;
+        data.extend_from_slice(section.payload);
+    }
+    Ok(data)
+}
+
+pub fn signed_data<'t>(
+    module_bytes: &'t [u8],
+    sections: &SectionSelection,
+    signature_section_name: &str,
+) -> Result<Cow<'t, [u8]>, WError> {
+    match sections {
+        SectionSelection::All => Ok(Cow::Borrowed(module_bytes)),
+        SectionSelection::Standard(_) => Ok(Cow::Owned(selected_sections_data(
+            module_bytes,
+            sections,
+            signature_section_name,
+        )?)),
+    }
+}
@@ -0,0 +1,37 @@
+use super::super::errors::*;
+use super::super::signature::*;
+use super::attach::replace_signature_in_custom_section;
+use super::verify::extract_signature_from_custom_section;
+
+/// Creates an RFC 3161 time-stamp request over the signature stored in a Custom Section.
+pub fn timestamp_request_for_custom_section(
+    module_bytes: &[u8],
+    signature_section_name: &str,
+) -> Result<Vec<u8>, WError> {
+    let signature = extract_signature_from_custom_section(module_bytes, signature_section_name)?;
+    tsp::request(signature.raw())
+}
+
+/// Adds a time-stamp token to the signature stored in a Custom Section.
+pub fn attach_timestamp_in_custom_section(
+    module_bytes: &[u8],
+    token: &[u8],
+    signature_section_name: &str,
+) -> Result<Vec<u8>, WError> {
+    let signature = extract_signature_from_custom_section(module_bytes, signature_section_name)?;
+    tsp::check_imprint(&tsp::tst_info(token)?, signature.raw())?;
+    replace_signature_in_custom_section(
+        module_bytes,
+        &signature.with_timestamp(Some(token)),
+        signature_section_name,
+    )
+}
+
+/// Verifies the time-stamp token of a signature against the TSA roots, and
+/// returns the time it certifies.
+pub fn verify_timestamp(signature: &Signature, tsa_roots: &[Vec<u8>]) -> Result<u64, WError> {
+    let token = signature.timestamp().ok_or(WError::SignatureError(
+        "The signature has no time-stamp token",
+    ))?;
+    tsp::verify(token, signature.raw(), tsa_roots)
+}
@@ -1,16 +1,15 @@
 use super::super::errors::*;
 use super::super::signature::*;
+use super::merkle::*;
+use super::sections::{custom_section, raw_sections, read_varuint32, signed_data};
 use byteorder::{ByteOrder, LittleEndian};
 use parity_wasm::elements::*;
+use std::borrow::Cow;
 
-pub fn verify_signature(
-    module_bytes: &[u8],
-    ad: Option<&[u8]>,
-    pk: &PublicKey,
+pub fn signature_data_segment_mut<'t>(
+    module: &'t mut Module,
     signature_symbol: &str,
-) -> Result<(), WError> {
-    let module: Module = parity_wasm::deserialize_buffer(module_bytes)?;
-
+) -> Result<&'t mut Vec<u8>, WError> {
     // Get the global ID of the exported name matching the signature symbol
     let global_id = {
         let export_section = module.export_section().expect("No export section");
@@ -116,41 +115,118 @@ pub fn verify_signature(
         data_segment_offset
     };
 
-    // Work on a mutable copy of the module
+    // Find where the signature is stored
+
+    let data_section = module.data_section_mut().expect("No data section");
+    let data_segment = match data_section.entries_mut().iter_mut().find(|data_segment| {
+        let instructions = data_segment
+            .offset()
+            .as_ref()
+            .expect("Invalid offset")
+            .code();
+        if instructions.len() != 2 {
+            return false;
+        }
+        match instructions[1] {
+            Instruction::End => {}
+            _ => return false,
+        }
+        let offset = match instructions[0] {
+            Instruction::I32Const(offset) => offset,
+            _ => return false,
+        };
+        offset == data_segment_offset
+    }) {
+        None => return Err(WError::ParseError("Data segment not found".to_string())),
+        Some(data_segment) => data_segment,
+    };
+    Ok(data_segment.value_mut())
+}
+
+fn check_signature_context(signature: &Signature, context: Option<&str>) -> Result<(), WError> {
+    let context = context.filter(|&context| context != DEFAULT_CONTEXT);
+    if signature.context() != context {
+        return Err(WError::ParseError(format!(
+            "Signature was created for the [{}] context",
+            signature.context().unwrap_or(DEFAULT_CONTEXT)
+        )));
+    }
+    Ok(())
+}
 
-    let mut module = module;
+/// Returns the additional data to verify a signature with. The additional data
+/// embedded in the signature is only used if requested with `AdditionalData::from_signature()`.
+pub fn resolve_ad(signature: &Signature, ad: &AdditionalData) -> Result<AdditionalData, WError> {
+    match signature.embedded_ad() {
+        None => Ok(ad.clone()),
+        Some(EmbeddedAd::Data(data)) if ad.is_from_signature() => {
+            Ok(AdditionalData::new(data.clone()))
+        }
+        Some(EmbeddedAd::Hash(..)) if ad.is_from_signature() => Err(WError::UsageError(
+            "The signature only stores a hash of the additional data, that must be supplied",
+        )),
+        Some(embedded_ad) => match embedded_ad.matches(ad) {
+            true => Ok(ad.clone()),
+            false if ad.is_empty() => Err(WError::UsageError(
+                "The signature was created with additional data, that must be supplied",
+            )),
+            false => Err(WError::AdMismatch),
+        },
+    }
+}
+
+pub fn extract_signature(module_bytes: &[u8], signature_symbol: &str) -> Result<Signature, WError> {
+    let mut module: Module = parity_wasm::deserialize_buffer(module_bytes)?;
+    Signature::from_bytes(signature_data_segment_mut(&mut module, signature_symbol)?)
+}
+
+pub fn signature_section_payload(
+    module_bytes: &[u8],
+    signature_section_name: &str,
+) -> Result<Vec<u8>, WError> {
+    let sections = raw_sections(module_bytes)?;
+    let section = match sections
+        .iter()
+        .find(|section| section.name.as_deref() == Some(signature_section_name))
+    {
+        None => {
+            return Err(WError::ParseError(format!(
+                "Custom Section {} not found",
+                signature_section_name
+            )))
+        }
+        Some(section) => section,
+    };
+    let mut offset = 0;
+    let name_len = read_varuint32(section.payload, &mut offset)? as usize;
+    Ok(section.payload[offset + name_len..].to_vec())
+}
+
+pub fn extract_signature_from_custom_section(
+    module_bytes: &[u8],
+    signature_section_name: &str,
+) -> Result<Signature, WError> {
+    Signature::from_bytes(&signature_section_payload(module_bytes, signature_section_name)?)
+}
+
+pub fn verify_signature(
+    module_bytes: &[u8],
+    ad: &AdditionalData,
+    context: Option<&str>,
+    pk: &PublicKey,
+    signature_symbol: &str,
+) -> Result<(), WError> {
+    let mut module: Module = parity_wasm::deserialize_buffer(module_bytes)?;
 
     // Compute the signature, and also return where it is stored
 
     let (signature, data_segment_value) = {
-        let data_section = module.data_section_mut().expect("No data section");
-        let data_segment = match data_section.entries_mut().iter_mut().find(|data_segment| {
-            let instructions = data_segment
-                .offset()
-                .as_ref()
-                .expect("Invalid offset")
-                .code();
-            if instructions.len() != 2 {
-                return false;
-            }
-            match instructions[1] {
-                Instruction::End => {}
-                _ => return false,
-            }
-            let offset = match instructions[0] {
-                Instruction::I32Const(offset) => offset,
-                _ => return false,
-            };
-            offset == data_segment_offset
-        }) {
-            None => return Err(WError::ParseError("Data segment not found".to_string())),
-            Some(data_segment) => data_segment,
-        };
-        let data_segment_value = data_segment.value_mut();
-        let signature_bytes = data_segment_value.clone();
-        let signature = Signature::from_bytes(&signature_bytes)?;
+        let data_segment_value = signature_data_segment_mut(&mut module, signature_symbol)?;
+        let signature = Signature::from_bytes(data_segment_value)?;
         (signature, data_segment_value)
     };
+    check_signature_context(&signature, context)?;
+    let ad = resolve_ad(&signature, ad)?;
 
     // Check the signature
 
@@ -163,37 +239,56 @@ pub fn verify_signature(
             "Signature uses a different scheme than the provided public key",
         ));
     }
-    signature_alg.verify(&module_bytes, ad, pk.raw(), &signature)
+    signature_alg.verify(&module_bytes, &ad, pk.raw(), &signature)
 }
 
 pub fn verify_signature_in_custom_section(
     module_bytes: &[u8],
-    ad: Option<&[u8]>,
+    ad: &AdditionalData,
+    context: Option<&str>,
     pk: &PublicKey,
     signature_section_name: &str,
+) -> Result<(), WError> {
+    verify_signature_in_custom_section_partial(
+        module_bytes,
+        ad,
+        context,
+        pk,
+        signature_section_name,
+        &|_| true,
+    )
+}
+
+pub fn verify_signature_in_custom_section_partial(
+    module_bytes: &[u8],
+    ad: &AdditionalData,
+    context: Option<&str>,
+    pk: &PublicKey,
+    signature_section_name: &str,
+    filter: &dyn Fn(&LeafKind) -> bool,
 ) -> Result<(), WError> {
     // Find the Custom Section with a signature
 
-    let signature_bytes = {
-        let module: Module = parity_wasm::deserialize_buffer(module_bytes)?;
-        let signature_bytes = match module
-            .custom_sections()
-            .find(|section| section.name() == signature_section_name)
-        {
-            None => {
+    let signature_bytes = signature_section_payload(module_bytes, signature_section_name)?;
+    let signature = Signature::from_bytes(&signature_bytes)?;
+    let module_bytes = match signature.sections() {
+        SectionSelection::All => {
+            let section = custom_section(signature_section_name, &signature_bytes);
+            if !module_bytes.ends_with(&section) {
                 return Err(WError::ParseError(format!(
-                    "Custom Section {} not found",
+                    "Custom Section {} must be the last section",
                     signature_section_name
-                )))
+                )));
             }
-            Some(section) => section.payload(),
-        };
-        signature_bytes.to_vec()
+            &module_bytes[0..module_bytes.len() - section.len()]
+        }
+        _ => module_bytes,
     };
 
     // Check the signature
 
-    let signature = Signature::from_bytes(&signature_bytes)?;
+    check_signature_context(&signature, context)?;
+    let ad = resolve_ad(&signature, ad)?;
     let signature_alg = signature.to_alg()?;
     if signature_alg.alg_id() != pk.alg_id() {
         return Err(WError::SignatureError(
@@ -201,7 +296,26 @@ pub fn verify_signature_in_custom_section(
         ));
     }
 
-    let section_len = 2 + 1 + signature_section_name.len() + Signature::length(&signature_alg);
-    let signed_len = module_bytes.len() - section_len;
-    signature_alg.verify(&module_bytes[0..signed_len], ad, pk.raw(), &signature)
+    let data = match signature.merkle_leaves() {
+        None => signed_data(module_bytes, signature.sections(), signature_section_name)?,
+        Some(leaves) => Cow::Owned(merkle_signed_data(
+            signature.hash_alg(),
+            signature.sections(),
+            leaves,
+        )),
+    };
+    signature_alg.verify(&data, &ad, pk.raw(), &signature)?;
+
+    // Check that the module matches the leaves covered by the signature
+
+    if let Some(leaves) = signature.merkle_leaves() {
+        check_merkle_leaves(
+            module_bytes,
+            &signature,
+            leaves,
+            signature_section_name,
+            filter,
+        )?;
+    }
+    Ok(())
 }
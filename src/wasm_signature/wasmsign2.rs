@@ -0,0 +1,267 @@
+use super::super::errors::*;
+use super::super::signature::*;
+use super::sections::{custom_section, raw_sections, read_varuint32, write_varuint32, RawSection};
+
+pub const WASMSIGN2_SECTION_NAME: &str = "signature";
+const DELIMITER_SECTION_NAME: &str = "signature_delimiter";
+
+const DOMAIN: &[u8] = b"wasmsig";
+const SPEC_VERSION: u8 = 0x01;
+const CONTENT_TYPE_MODULE: u8 = 0x01;
+const HASH_SHA256: u8 = 0x01;
+const HASH_BYTES: usize = 32;
+
+struct SignatureForHashes {
+    key_id: Option<Vec<u8>>,
+    alg_id: u8,
+    signature: Vec<u8>,
+}
+
+struct SignedHashes {
+    hashes: Vec<Vec<u8>>,
+    signatures: Vec<SignatureForHashes>,
+}
+
+fn read_u8(bytes: &mut &[u8]) -> Result<u8, WError> {
+    let mut offset = 0;
+    let v = read_varuint32(bytes, &mut offset)?;
+    *bytes = &bytes[offset..];
+    if v > 0xff {
+        return Err(WError::ParseError("Invalid wasmsign2 signature".to_string()));
+    }
+    Ok(v as u8)
+}
+
+fn read_count(bytes: &mut &[u8]) -> Result<usize, WError> {
+    let mut offset = 0;
+    let v = read_varuint32(bytes, &mut offset)? as usize;
+    *bytes = &bytes[offset..];
+    Ok(v)
+}
+
+fn read_slice<'t>(bytes: &mut &'t [u8]) -> Result<&'t [u8], WError> {
+    let len = read_count(bytes)?;
+    if bytes.len() < len {
+        return Err(WError::ParseError("Truncated wasmsign2 signature".to_string()));
+    }
+    let (s, rest) = bytes.split_at(len);
+    *bytes = rest;
+    Ok(s)
+}
+
+fn write_slice(bytes: &mut Vec<u8>, s: &[u8]) {
+    write_varuint32(bytes, s.len() as u32);
+    bytes.extend_from_slice(s);
+}
+
+impl SignatureForHashes {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        write_slice(&mut bytes, self.key_id.as_deref().unwrap_or_default());
+        bytes.push(self.alg_id);
+        write_slice(&mut bytes, &self.signature);
+        bytes
+    }
+
+    fn from_bytes(mut bytes: &[u8]) -> Result<Self, WError> {
+        let key_id = read_slice(&mut bytes)?;
+        let key_id = match key_id.is_empty() {
+            true => None,
+            false => Some(key_id.to_vec()),
+        };
+        let (&alg_id, mut bytes) = bytes
+            .split_first()
+            .ok_or_else(|| WError::ParseError("Truncated wasmsign2 signature".to_string()))?;
+        let signature = read_slice(&mut bytes)?.to_vec();
+        Ok(SignatureForHashes {
+            key_id,
+            alg_id,
+            signature,
+        })
+    }
+}
+
+impl SignedHashes {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![];
+        write_varuint32(&mut bytes, self.hashes.len() as u32);
+        for hash in &self.hashes {
+            bytes.extend_from_slice(hash);
+        }
+        write_varuint32(&mut bytes, self.signatures.len() as u32);
+        for signature in &self.signatures {
+            write_slice(&mut bytes, &signature.to_bytes());
+        }
+        bytes
+    }
+
+    fn from_bytes(mut bytes: &[u8]) -> Result<Self, WError> {
+        let hashes_count = read_count(&mut bytes)?;
+        if bytes.len() / HASH_BYTES < hashes_count {
+            return Err(WError::ParseError("Truncated wasmsign2 signature".to_string()));
+        }
+        let mut hashes = vec![];
+        for _ in 0..hashes_count {
+            let (hash, rest) = bytes.split_at(HASH_BYTES);
+            hashes.push(hash.to_vec());
+            bytes = rest;
+        }
+        let signatures_count = read_count(&mut bytes)?;
+        let mut signatures = vec![];
+        for _ in 0..signatures_count {
+            signatures.push(SignatureForHashes::from_bytes(read_slice(&mut bytes)?)?);
+        }
+        Ok(SignedHashes { hashes, signatures })
+    }
+}
+
+fn signature_data_to_bytes(signed_hashes_set: &[SignedHashes]) -> Vec<u8> {
+    let mut bytes = vec![SPEC_VERSION, CONTENT_TYPE_MODULE, HASH_SHA256];
+    write_varuint32(&mut bytes, signed_hashes_set.len() as u32);
+    for signed_hashes in signed_hashes_set {
+        write_slice(&mut bytes, &signed_hashes.to_bytes());
+    }
+    bytes
+}
+
+fn signature_data_from_bytes(mut bytes: &[u8]) -> Result<Vec<SignedHashes>, WError> {
+    if read_u8(&mut bytes)? != SPEC_VERSION {
+        return Err(WError::ParseError(
+            "Unsupported wasmsign2 specification version".to_string(),
+        ));
+    }
+    if read_u8(&mut bytes)? != CONTENT_TYPE_MODULE {
+        return Err(WError::ParseError("Unsupported wasmsign2 content type".to_string()));
+    }
+    if read_u8(&mut bytes)? != HASH_SHA256 {
+        return Err(WError::ParseError("Unsupported wasmsign2 hash function".to_string()));
+    }
+    let count = read_count(&mut bytes)?;
+    let mut signed_hashes_set = vec![];
+    for _ in 0..count {
+        signed_hashes_set.push(SignedHashes::from_bytes(read_slice(&mut bytes)?)?);
+    }
+    Ok(signed_hashes_set)
+}
+
+fn section_payload<'t>(section: &RawSection<'t>) -> Result<&'t [u8], WError> {
+    let mut offset = 0;
+    let name_len = read_varuint32(section.payload, &mut offset)? as usize;
+    Ok(&section.payload[offset + name_len..])
+}
+
+/// Splits a module into its wasmsign2 signature header, if there is one, and
+/// the hash of the sections it covers.
+fn parse_module(module_bytes: &[u8]) -> Result<(Option<Vec<SignedHashes>>, Vec<u8>), WError> {
+    let sections = raw_sections(module_bytes)?;
+    let mut sections = sections.iter().peekable();
+    let signed_hashes_set = match sections.peek() {
+        Some(section) if section.name.as_deref() == Some(WASMSIGN2_SECTION_NAME) => Some(
+            signature_data_from_bytes(section_payload(sections.next().unwrap())?)?,
+        ),
+        _ => None,
+    };
+    let mut hasher = hmac_sha256::Hash::new();
+    for section in sections {
+        if section.name.as_deref() == Some(DELIMITER_SECTION_NAME) {
+            return Err(WError::ParseError(
+                "Partial wasmsign2 signatures are not supported".to_string(),
+            ));
+        }
+        hasher.update(&module_bytes[section.range.clone()]);
+    }
+    Ok((signed_hashes_set, hasher.finalize().to_vec()))
+}
+
+fn message(hash: &[u8]) -> Vec<u8> {
+    let mut msg = DOMAIN.to_vec();
+    msg.extend_from_slice(&[SPEC_VERSION, CONTENT_TYPE_MODULE, HASH_SHA256]);
+    msg.extend_from_slice(hash);
+    msg
+}
+
+/// Adds a wasmsign2 signature to a module. If the module already has
+/// wasmsign2 signatures for the same content, the new signature is added to
+/// them.
+pub fn attach_wasmsign2_signature(
+    module_bytes: &[u8],
+    signer: &dyn Signer,
+    key_id: Option<&[u8]>,
+) -> Result<Vec<u8>, WError> {
+    if signer.alg_id() != eddsa::ALG_ID {
+        return Err(WError::UsageError("Only Ed25519 keys can be used with wasmsign2"));
+    }
+    let (signed_hashes_set, hash) = parse_module(module_bytes)?;
+    let signature = SignatureForHashes {
+        key_id: key_id.map(|key_id| key_id.to_vec()),
+        alg_id: wasmsign2::ED25519_PK_ID,
+        signature: signer.sign_prehashed(&message(&hash))?,
+    };
+    let content_start = match signed_hashes_set {
+        None => 8,
+        Some(_) => raw_sections(module_bytes)?[0].range.end,
+    };
+    let mut signed_hashes_set = signed_hashes_set.unwrap_or_default();
+    match signed_hashes_set
+        .iter_mut()
+        .find(|signed_hashes| signed_hashes.hashes.contains(&hash))
+    {
+        Some(signed_hashes) => signed_hashes.signatures.push(signature),
+        None => signed_hashes_set.push(SignedHashes {
+            hashes: vec![hash],
+            signatures: vec![signature],
+        }),
+    }
+
+    // The signature header is the first section of the module
+
+    let mut signed_module_bytes = module_bytes[..8].to_vec();
+    signed_module_bytes.extend_from_slice(&custom_section(
+        WASMSIGN2_SECTION_NAME,
+        &signature_data_to_bytes(&signed_hashes_set),
+    ));
+    signed_module_bytes.extend_from_slice(&module_bytes[content_start..]);
+    Ok(signed_module_bytes)
+}
+
+pub fn verify_wasmsign2_signature(module_bytes: &[u8], pk: &PublicKey) -> Result<(), WError> {
+    if pk.alg_id() != eddsa::ALG_ID {
+        return Err(WError::UsageError("Only Ed25519 keys can be used with wasmsign2"));
+    }
+    let (signed_hashes_set, hash) = parse_module(module_bytes)?;
+    let signed_hashes_set = match signed_hashes_set {
+        None => {
+            return Err(WError::ParseError(format!(
+                "Custom Section {} not found",
+                WASMSIGN2_SECTION_NAME
+            )))
+        }
+        Some(signed_hashes_set) => signed_hashes_set,
+    };
+    let xpk = ed25519_compact::PublicKey::from_slice(pk.raw())?;
+    let msg = message(&hash);
+    let verified = signed_hashes_set
+        .iter()
+        .filter(|signed_hashes| signed_hashes.hashes.contains(&hash))
+        .flat_map(|signed_hashes| signed_hashes.signatures.iter())
+        .filter(|signature| signature.alg_id == wasmsign2::ED25519_PK_ID)
+        .any(|signature| match ed25519_compact::Signature::from_slice(&signature.signature) {
+            Ok(xsignature) => xpk.verify(&msg, &xsignature).is_ok(),
+            Err(_) => false,
+        });
+    match verified {
+        true => Ok(()),
+        false => Err(WError::SignatureError("No valid wasmsign2 signature for this key")),
+    }
+}
+
+/// Removes a Custom Section from a module.
+pub fn strip_custom_section(module_bytes: &[u8], name: &str) -> Result<Vec<u8>, WError> {
+    let mut stripped = module_bytes[..8].to_vec();
+    for section in raw_sections(module_bytes)? {
+        if section.name.as_deref() != Some(name) {
+            stripped.extend_from_slice(&module_bytes[section.range]);
+        }
+    }
+    Ok(stripped)
+}
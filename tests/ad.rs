@@ -0,0 +1,96 @@
+use wasmsign::signature::eddsa::EdDSA;
+use wasmsign::*;
+
+const MODULE: &[u8] = b"\0asm\x01\0\0\0";
+
+fn sign(kp: &KeyPair, ad: &AdditionalData) -> Vec<u8> {
+    sign_custom_section(
+        MODULE,
+        kp,
+        ad,
+        HashAlg::default(),
+        None,
+        &SectionSelection::All,
+        DEFAULT_CUSTOM_SECTION_NAME,
+    )
+    .unwrap()
+}
+
+fn verify(signed: &[u8], kp: &KeyPair, ad: &AdditionalData) -> Result<(), WError> {
+    verify_custom_section(signed, &kp.pk, ad, None, DEFAULT_CUSTOM_SECTION_NAME)
+}
+
+#[test]
+fn embedded_ad_is_only_used_on_request() {
+    let kp = keygen_from_seed(&EdDSA, &[7u8; 32], None).unwrap();
+    let ad = AdditionalData::new(&b"release 1.0"[..]);
+
+    let signed = sign(&kp, &ad.clone().embed(AdEmbedding::Data));
+    verify(&signed, &kp, &ad).unwrap();
+    verify(&signed, &kp, &AdditionalData::from_signature()).unwrap();
+    assert!(verify(&signed, &kp, &AdditionalData::default()).is_err());
+    assert!(matches!(
+        verify(&signed, &kp, &AdditionalData::new(&b"release 2.0"[..])),
+        Err(WError::AdMismatch)
+    ));
+
+    let signed = sign(&kp, &ad.clone().embed(AdEmbedding::Hash));
+    verify(&signed, &kp, &ad).unwrap();
+    assert!(verify(&signed, &kp, &AdditionalData::from_signature()).is_err());
+    assert!(verify(&signed, &kp, &AdditionalData::default()).is_err());
+
+    let signed = sign(&kp, &AdditionalData::default());
+    verify(&signed, &kp, &AdditionalData::from_signature()).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn ad_from_the_environment_is_not_reencoded() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+    use std::process::Command;
+
+    let dir = std::env::temp_dir().join(format!("wasmsign-ad-env-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("module.wasm"), MODULE).unwrap();
+    let wasmsign = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_wasmsign"))
+            .current_dir(&dir)
+            .env("WASMSIGN_AD", OsStr::from_bytes(b"build \xff\xfe"))
+            .args(args)
+            .status()
+            .unwrap()
+            .success()
+    };
+    assert!(wasmsign(&["-G", "-p", "pk", "-s", "sk"]));
+    assert!(wasmsign(&[
+        "-S",
+        "-C",
+        "-p",
+        "pk",
+        "-s",
+        "sk",
+        "-i",
+        "module.wasm",
+        "-o",
+        "signed.wasm",
+        "--ad-env",
+        "WASMSIGN_AD",
+    ]));
+    let verify = |ad_hex: &str| {
+        wasmsign(&[
+            "-V",
+            "-C",
+            "-p",
+            "pk",
+            "-i",
+            "signed.wasm",
+            "--ad-hex",
+            ad_hex,
+        ])
+    };
+    assert!(verify("6275696c6420fffe"));
+    assert!(!verify("6275696c6420efbfbdefbfbd"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
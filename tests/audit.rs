@@ -0,0 +1,139 @@
+use ct_codecs::{Encoder, Hex};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+const MODULE: &[u8] = b"\0asm\x01\0\0\0";
+
+fn work_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("wasmsign-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn wasmsign(dir: &Path, args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_wasmsign"));
+    command.current_dir(dir).args(args);
+    command
+}
+
+fn run(dir: &Path, args: &[&str]) -> Output {
+    wasmsign(dir, args).output().unwrap()
+}
+
+fn sign(dir: &Path, input: &str, output: &str) -> Output {
+    run(
+        dir,
+        &[
+            "-S",
+            "-C",
+            "-p",
+            "pk",
+            "-s",
+            "sk",
+            "-i",
+            input,
+            "-o",
+            output,
+            "--audit-log",
+            "audit.jsonl",
+        ],
+    )
+}
+
+fn check(dir: &Path) -> Output {
+    run(dir, &["--audit", "--audit-log", "audit.jsonl"])
+}
+
+fn entries(dir: &Path) -> Vec<String> {
+    std::fs::read_to_string(dir.join("audit.jsonl"))
+        .unwrap()
+        .lines()
+        .map(String::from)
+        .collect()
+}
+
+fn write_entries(dir: &Path, lines: &[String]) {
+    let mut audit_log = lines.join("\n");
+    audit_log.push('\n');
+    std::fs::write(dir.join("audit.jsonl"), audit_log).unwrap();
+}
+
+fn field(line: &str, name: &str) -> serde_json::Value {
+    serde_json::from_str::<serde_json::Value>(line).unwrap()[name].clone()
+}
+
+#[test]
+fn concurrent_signing_operations_are_chained() {
+    let dir = work_dir("audit-concurrent");
+    assert!(run(&dir, &["-G", "-p", "pk", "-s", "sk"]).status.success());
+    std::fs::write(dir.join("module.wasm"), MODULE).unwrap();
+    let children: Vec<_> = (0..8)
+        .map(|i| {
+            wasmsign(
+                &dir,
+                &[
+                    "-S",
+                    "-C",
+                    "-p",
+                    "pk",
+                    "-s",
+                    "sk",
+                    "-i",
+                    "module.wasm",
+                    "-o",
+                    &format!("signed{}.wasm", i),
+                    "--audit-log",
+                    "audit.jsonl",
+                ],
+            )
+            .spawn()
+            .unwrap()
+        })
+        .collect();
+    for mut child in children {
+        assert!(child.wait().unwrap().success());
+    }
+    let output = check(&dir);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Entries:         8"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn edited_and_removed_entries_break_the_chain() {
+    let dir = work_dir("audit-chain");
+    assert!(run(&dir, &["-G", "-p", "pk", "-s", "sk"]).status.success());
+    std::fs::write(dir.join("module.wasm"), MODULE).unwrap();
+    for i in 0..4 {
+        assert!(sign(&dir, "module.wasm", &format!("signed{}.wasm", i))
+            .status
+            .success());
+    }
+    // Signing in place records the digest of the unsigned module as the input
+    assert!(sign(&dir, "module.wasm", "module.wasm").status.success());
+    let lines = entries(&dir);
+    assert_eq!(lines.len(), 5);
+    let input_digest = Hex::encode_to_string(hmac_sha256::Hash::hash(MODULE)).unwrap();
+    assert_eq!(field(&lines[4], "input_digest"), input_digest.as_str());
+    assert_ne!(field(&lines[4], "output_digest"), input_digest.as_str());
+    assert!(field(&lines[4], "key_fingerprint").is_string());
+    assert!(check(&dir).status.success());
+
+    let mut edited = lines.clone();
+    edited[2] = edited[2].replacen("\"input\":\"", "\"input\":\"other-", 1);
+    assert_ne!(edited[2], lines[2]);
+    write_entries(&dir, &edited);
+    assert!(!check(&dir).status.success());
+    assert!(!sign(&dir, "signed0.wasm", "signed5.wasm").status.success());
+    assert!(!dir.join("signed5.wasm").exists());
+
+    let mut removed = lines.clone();
+    removed.remove(2);
+    write_entries(&dir, &removed);
+    assert!(!check(&dir).status.success());
+
+    write_entries(&dir, &lines);
+    assert!(check(&dir).status.success());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
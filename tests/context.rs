@@ -0,0 +1,51 @@
+use wasmsign::signature::eddsa::EdDSA;
+use wasmsign::*;
+
+const MODULE: &[u8] = b"\0asm\x01\0\0\0";
+
+fn sign(kp: &KeyPair, context: Option<&str>) -> Result<Vec<u8>, WError> {
+    sign_custom_section(
+        MODULE,
+        kp,
+        &AdditionalData::default(),
+        HashAlg::default(),
+        context,
+        &SectionSelection::All,
+        DEFAULT_CUSTOM_SECTION_NAME,
+    )
+}
+
+fn verify(signed: &[u8], kp: &KeyPair, context: Option<&str>) -> Result<(), WError> {
+    verify_custom_section(
+        signed,
+        &kp.pk,
+        &AdditionalData::default(),
+        context,
+        DEFAULT_CUSTOM_SECTION_NAME,
+    )
+}
+
+#[test]
+fn signatures_are_bound_to_their_context() {
+    let kp = keygen_from_seed(&EdDSA, &[7u8; 32], None).unwrap();
+
+    let signed = sign(&kp, Some("production")).unwrap();
+    verify(&signed, &kp, Some("production")).unwrap();
+    assert!(verify(&signed, &kp, Some("staging")).is_err());
+    assert!(verify(&signed, &kp, None).is_err());
+
+    let signed = sign(&kp, None).unwrap();
+    verify(&signed, &kp, None).unwrap();
+    verify(&signed, &kp, Some(DEFAULT_CONTEXT)).unwrap();
+    assert!(verify(&signed, &kp, Some("production")).is_err());
+}
+
+#[test]
+fn contexts_must_be_between_1_and_255_bytes() {
+    let kp = keygen_from_seed(&EdDSA, &[7u8; 32], None).unwrap();
+    assert!(sign(&kp, Some("")).is_err());
+    assert!(sign(&kp, Some(&"x".repeat(256))).is_err());
+    let context = "x".repeat(255);
+    let signed = sign(&kp, Some(&context)).unwrap();
+    verify(&signed, &kp, Some(&context)).unwrap();
+}
@@ -0,0 +1,51 @@
+use wasmsign::coset::{iana, CborSerializable, CoseSign1Builder, HeaderBuilder};
+use wasmsign::signature::eddsa::EdDSA;
+use wasmsign::*;
+
+const EMPTY_MODULE: &[u8] = b"\0asm\x01\0\0\0";
+
+fn leb128(mut v: usize, out: &mut Vec<u8>) {
+    loop {
+        let byte = (v & 0x7f) as u8;
+        v >>= 7;
+        if v == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+#[test]
+fn third_party_cose_signatures_are_verified() {
+    let kp = ed25519_compact::KeyPair::from_seed(ed25519_compact::Seed::new([7u8; 32]));
+    let pk = keygen_from_seed(&EdDSA, &[7u8; 32], None).unwrap().pk;
+
+    // Untagged COSE_Sign1 with an attached payload, produced without wasmsign
+    let sign1 = CoseSign1Builder::new()
+        .protected(HeaderBuilder::new().algorithm(iana::Algorithm::EdDSA).build())
+        .payload(EMPTY_MODULE.to_vec())
+        .create_signature(b"", |tbs| kp.sk.sign(tbs, None).to_vec())
+        .build();
+    let cose = sign1.to_vec().unwrap();
+
+    let mut content = vec![];
+    leb128(DEFAULT_COSE_SECTION_NAME.len(), &mut content);
+    content.extend_from_slice(DEFAULT_COSE_SECTION_NAME.as_bytes());
+    content.extend_from_slice(&cose);
+    let mut module = EMPTY_MODULE.to_vec();
+    module.push(0);
+    leb128(content.len(), &mut module);
+    module.extend_from_slice(&content);
+
+    let ad = AdditionalData::default();
+    verify_cose(&module, &pk, &ad, None, DEFAULT_COSE_SECTION_NAME).unwrap();
+    assert!(verify_cose(
+        &module,
+        &pk,
+        &AdditionalData::new(&b"other"[..]),
+        None,
+        DEFAULT_COSE_SECTION_NAME
+    )
+    .is_err());
+}
@@ -0,0 +1,85 @@
+use ct_codecs::{Encoder, Hex};
+use wasmsign::signature::derivation;
+use wasmsign::signature::eddsa::EdDSA;
+use wasmsign::*;
+
+const EMPTY_MODULE: &[u8] = b"\0asm\x01\0\0\0";
+
+#[test]
+fn deterministic_signatures_are_reproducible() {
+    let key_pair = keygen_from_seed(&EdDSA, &[42u8; 32], None)
+        .unwrap()
+        .deterministic(true);
+    let ad = AdditionalData::new(&b"build 1"[..]);
+    let signed1 = sign_custom_section(
+        EMPTY_MODULE,
+        &key_pair,
+        &ad,
+        HashAlg::default(),
+        None,
+        &SectionSelection::All,
+        DEFAULT_CUSTOM_SECTION_NAME,
+    )
+    .unwrap();
+    let signed2 = sign_custom_section(
+        EMPTY_MODULE,
+        &key_pair,
+        &ad,
+        HashAlg::default(),
+        None,
+        &SectionSelection::All,
+        DEFAULT_CUSTOM_SECTION_NAME,
+    )
+    .unwrap();
+    assert_eq!(signed1, signed2);
+    verify_custom_section(&signed1, &key_pair.pk, &ad, None, DEFAULT_CUSTOM_SECTION_NAME).unwrap();
+}
+
+fn hex(bytes: &[u8]) -> String {
+    Hex::encode_to_string(bytes).unwrap()
+}
+
+#[test]
+fn derived_keys_match_the_slip10_test_vectors() {
+    let seed = [
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e,
+        0x0f,
+    ];
+    let vectors = [
+        (
+            "m",
+            "2b4be7f19ee27bbf30c667b642d5f4aa69fd169872f8fc3059c08ebae2eb19e7",
+            "a4b2856bfec510abab89753fac1ac0e1112364e7d250545963f135f2a33188ed",
+        ),
+        (
+            "m/0'/1'/2'/2'/1000000000'",
+            "8f94d394a8e8fd6b1bc2f3f49f5c47e385281d5c17e65324b0f62483e37e8793",
+            "3c24da049451555d51a7014a37337aa4e12d41e485abccfa46b47dfb2af54b7a",
+        ),
+    ];
+    for (path, sk, pk) in vectors {
+        assert_eq!(hex(&derivation::derive_seed(&seed, path).unwrap()), sk);
+        let key_pair = keygen_from_seed(&EdDSA, &seed, Some(path)).unwrap();
+        assert_eq!(hex(key_pair.pk.raw()), pk);
+    }
+    assert_eq!(
+        derivation::derive_seed(&seed, "m/0h/1h").unwrap(),
+        derivation::derive_seed(&seed, "m/0'/1'").unwrap()
+    );
+    assert!(derivation::derive_seed(&seed, "m/0").is_err());
+}
+
+#[test]
+fn passphrases_are_stretched_with_pbkdf2() {
+    let seed = derivation::seed_from_passphrase(b"correct horse battery staple");
+    assert_eq!(
+        hex(&seed),
+        "a180cf9d2c72d4d38fe11eb9a01bee1e99b12e396b7eae6f2ab75e974f45fae2\
+         fd6364749e2fc3599c91d4fe03cfa3e04009f5faf04b3eb2d276b24ad40858a2"
+    );
+    let key_pair = keygen_from_seed(&EdDSA, &seed, Some("m/44'/0'")).unwrap();
+    assert_eq!(
+        hex(key_pair.pk.raw()),
+        "93a0374015677f8a7218331e741698ed429d8919be6c8819df4fee48d997acb5"
+    );
+}
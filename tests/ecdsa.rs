@@ -0,0 +1,51 @@
+use wasmsign::signature::ecdsa::Ecdsa;
+use wasmsign::*;
+
+const MODULE: &[u8] = b"\0asm\x01\0\0\0";
+
+#[test]
+fn ecdsa_keys_sign_modules() {
+    for curve in [Ecdsa::P256, Ecdsa::P384] {
+        let kp = curve.keygen();
+        assert_eq!(kp.pk.raw().len(), 1 + 2 * curve.field_length());
+
+        // Keys survive a round trip through their serialized form
+        let pk = PublicKey::from_bytes(&kp.pk.to_bytes()).unwrap();
+        let sk = SecretKey::from_bytes(&kp.sk.to_bytes()).unwrap();
+        let kp = KeyPair::new(curve.alg_id(), pk.clone(), sk);
+
+        let ad = AdditionalData::new(b"ecdsa test".to_vec());
+        let signed = sign_custom_section(
+            MODULE,
+            &kp,
+            &ad,
+            HashAlg::Sha256,
+            Some("ecdsa"),
+            &SectionSelection::All,
+            DEFAULT_CUSTOM_SECTION_NAME,
+        )
+        .unwrap();
+        let signature = inspect_custom_section(&signed, DEFAULT_CUSTOM_SECTION_NAME).unwrap();
+        assert_eq!(signature.alg_id(), curve.alg_id());
+        assert_eq!(signature.raw().len(), curve.raw_signature_length());
+        verify_custom_section(
+            &signed,
+            &pk,
+            &ad,
+            Some("ecdsa"),
+            DEFAULT_CUSTOM_SECTION_NAME,
+        )
+        .unwrap();
+
+        let other = curve.keygen();
+        assert!(verify_custom_section(
+            &signed,
+            &other.pk,
+            &ad,
+            Some("ecdsa"),
+            DEFAULT_CUSTOM_SECTION_NAME
+        )
+        .is_err());
+        assert!(curve.keygen_from_seed(&[7u8; 32]).is_err());
+    }
+}
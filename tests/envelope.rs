@@ -0,0 +1,120 @@
+use ct_codecs::{Base64, Base64UrlSafeNoPadding, Decoder, Encoder};
+use wasmsign::signature::eddsa::EdDSA;
+use wasmsign::signature::{dsse, jws};
+use wasmsign::*;
+
+const MODULE: &[u8] = b"\0asm\x01\0\0\0";
+const OTHER_MODULE: &[u8] = b"\0asm\x01\0\0\0\x00\x03\x02id";
+
+fn key_pairs() -> (KeyPair, KeyPair) {
+    (
+        keygen_from_seed(&EdDSA, &[7u8; 32], None).unwrap(),
+        keygen_from_seed(&EdDSA, &[8u8; 32], None).unwrap(),
+    )
+}
+
+fn ed25519_verify(kp: &KeyPair, msg: &[u8], signature: &[u8]) {
+    ed25519_compact::PublicKey::from_slice(kp.pk.raw())
+        .unwrap()
+        .verify(
+            msg,
+            &ed25519_compact::Signature::from_slice(signature).unwrap(),
+        )
+        .unwrap();
+}
+
+#[test]
+fn dsse_envelopes_are_verified() {
+    assert_eq!(
+        dsse::pae("http://example.com/HelloWorld", b"hello world"),
+        b"DSSEv1 29 http://example.com/HelloWorld 11 hello world"
+    );
+    let (kp, other_kp) = key_pairs();
+    let envelope = sign_envelope(MODULE, &kp, HashAlg::Sha256, None, EnvelopeFormat::Dsse).unwrap();
+    verify_envelope(MODULE, &kp.pk, &envelope).unwrap();
+
+    let json: serde_json::Value = serde_json::from_slice(&envelope).unwrap();
+    let payload_type = json["payloadType"].as_str().unwrap();
+    let payload = Base64::decode_to_vec(json["payload"].as_str().unwrap(), None).unwrap();
+    let sig = Base64::decode_to_vec(json["signatures"][0]["sig"].as_str().unwrap(), None).unwrap();
+    ed25519_verify(&kp, &dsse::pae(payload_type, &payload), &sig);
+
+    assert!(verify_envelope(OTHER_MODULE, &kp.pk, &envelope).is_err());
+    assert!(verify_envelope(MODULE, &other_kp.pk, &envelope).is_err());
+
+    // A payload for the other module, with the original signature
+    let other_envelope = sign_envelope(
+        OTHER_MODULE,
+        &kp,
+        HashAlg::Sha256,
+        None,
+        EnvelopeFormat::Dsse,
+    )
+    .unwrap();
+    let mut tampered: serde_json::Value = serde_json::from_slice(&other_envelope).unwrap();
+    tampered["signatures"] = json["signatures"].clone();
+    let tampered = serde_json::to_vec(&tampered).unwrap();
+    assert!(verify_envelope(OTHER_MODULE, &kp.pk, &tampered).is_err());
+}
+
+#[test]
+fn jws_signatures_are_verified() {
+    let (kp, other_kp) = key_pairs();
+    let envelope = sign_envelope(MODULE, &kp, HashAlg::Sha256, None, EnvelopeFormat::Jws).unwrap();
+    verify_envelope(MODULE, &kp.pk, &envelope).unwrap();
+
+    let compact = String::from_utf8(envelope.clone()).unwrap();
+    let parts: Vec<&str> = compact.split('.').collect();
+    assert_eq!(parts.len(), 3);
+    let header: serde_json::Value =
+        serde_json::from_slice(&Base64UrlSafeNoPadding::decode_to_vec(parts[0], None).unwrap())
+            .unwrap();
+    assert_eq!(header["alg"], "EdDSA");
+    let sig = Base64UrlSafeNoPadding::decode_to_vec(parts[2], None).unwrap();
+    ed25519_verify(&kp, format!("{}.{}", parts[0], parts[1]).as_bytes(), &sig);
+
+    assert!(verify_envelope(OTHER_MODULE, &kp.pk, &envelope).is_err());
+    assert!(verify_envelope(MODULE, &other_kp.pk, &envelope).is_err());
+
+    // A payload for the other module, with the original signature
+    let other_envelope = sign_envelope(
+        OTHER_MODULE,
+        &kp,
+        HashAlg::Sha256,
+        None,
+        EnvelopeFormat::Jws,
+    )
+    .unwrap();
+    let other_compact = String::from_utf8(other_envelope).unwrap();
+    let other_parts: Vec<&str> = other_compact.split('.').collect();
+    let tampered = format!("{}.{}.{}", other_parts[0], other_parts[1], parts[2]);
+    assert!(jws::verify(&tampered, &kp.pk).is_err());
+    assert!(verify_envelope(OTHER_MODULE, &kp.pk, tampered.as_bytes()).is_err());
+
+    // Unsigned tokens are rejected
+    let none_header = Base64UrlSafeNoPadding::encode_to_string(br#"{"alg":"none"}"#).unwrap();
+    let unsigned = format!("{}.{}.", none_header, parts[1]);
+    assert!(verify_envelope(MODULE, &kp.pk, unsigned.as_bytes()).is_err());
+}
+
+#[test]
+fn envelopes_in_custom_sections_cover_the_module() {
+    let (kp, _) = key_pairs();
+    for format in [EnvelopeFormat::Dsse, EnvelopeFormat::Jws] {
+        let signed = sign_envelope_custom_section(
+            MODULE,
+            &kp,
+            HashAlg::Sha256,
+            None,
+            format,
+            DEFAULT_CUSTOM_SECTION_NAME,
+        )
+        .unwrap();
+        verify_envelope_custom_section(&signed, &kp.pk, DEFAULT_CUSTOM_SECTION_NAME).unwrap();
+        let mut tampered = OTHER_MODULE.to_vec();
+        tampered.extend_from_slice(&signed[MODULE.len()..]);
+        assert!(
+            verify_envelope_custom_section(&tampered, &kp.pk, DEFAULT_CUSTOM_SECTION_NAME).is_err()
+        );
+    }
+}
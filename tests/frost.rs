@@ -0,0 +1,88 @@
+use wasmsign::signature::eddsa::EdDSA;
+use wasmsign::signature::frost;
+use wasmsign::*;
+
+// A module with a type, a function, its body, and a custom section
+const MODULE: &[u8] = b"\0asm\x01\0\0\0\
+    \x01\x04\x01\x60\x00\x00\
+    \x03\x02\x01\x00\
+    \x0a\x04\x01\x02\x00\x0b\
+    \x00\x07\x05extrax";
+
+fn threshold_sign(
+    request: &SigningRequest,
+    group_pk: &PublicKey,
+    key_shares: &[&frost::KeyShare],
+) -> Result<Signature, WError> {
+    let mut nonces = vec![];
+    let mut commitments = vec![];
+    for key_share in key_shares {
+        let (n, c) = frost::commit(key_share)?;
+        nonces.push(n);
+        commitments.push(c);
+    }
+    let signature_shares = key_shares
+        .iter()
+        .zip(&nonces)
+        .map(|(key_share, n)| frost::sign(request, key_share, n, &commitments))
+        .collect::<Result<Vec<_>, _>>()?;
+    frost::aggregate(request, group_pk, &commitments, &signature_shares)
+}
+
+#[test]
+fn threshold_signatures_are_standard_ed25519_signatures() {
+    let (group_pk, key_shares) = frost::keygen(2, 3).unwrap();
+    let data = b"some data";
+    let ad = AdditionalData::new(&b"build 1"[..]);
+    let context = Some("frost test");
+    let digest = EdDSA
+        .prehash(data, &ad, HashAlg::default(), context)
+        .unwrap();
+    let request = SigningRequest::new(eddsa::ALG_ID, digest.clone()).with_context(context);
+    let signature = threshold_sign(&request, &group_pk, &[&key_shares[0], &key_shares[2]]).unwrap();
+    EdDSA.verify(data, &ad, group_pk.raw(), &signature).unwrap();
+
+    let xpk = ed25519_compact::PublicKey::from_slice(group_pk.raw()).unwrap();
+    let xsignature = ed25519_compact::Signature::from_slice(signature.raw()).unwrap();
+    xpk.verify(&digest, &xsignature).unwrap();
+
+    assert!(threshold_sign(&request, &group_pk, &[&key_shares[1]]).is_err());
+}
+
+#[test]
+fn threshold_signatures_keep_the_request_parameters() {
+    let (group_pk, key_shares) = frost::keygen(2, 3).unwrap();
+    let ad = AdditionalData::new(&b"build 1"[..]).embed(AdEmbedding::Data);
+    let context = Some("frost test");
+    let sections = SectionSelection::standard::<&str>(&[]);
+    let (prepared, request) = prepare_custom_section_merkle(
+        MODULE,
+        eddsa::ALG_ID,
+        &ad,
+        HashAlg::Sha256,
+        context,
+        &sections,
+        DEFAULT_CUSTOM_SECTION_NAME,
+    )
+    .unwrap();
+    let signature = threshold_sign(&request, &group_pk, &[&key_shares[1], &key_shares[2]]).unwrap();
+    assert_eq!(signature.hash_alg(), HashAlg::Sha256);
+    assert_eq!(signature.context(), context);
+    assert!(signature.embedded_ad().unwrap().matches(&ad));
+    assert_eq!(signature.sections(), &sections);
+    assert_eq!(signature.merkle_leaves(), request.merkle_leaves());
+
+    let signed =
+        finalize_custom_section(&prepared, &signature, DEFAULT_CUSTOM_SECTION_NAME).unwrap();
+    verify_custom_section(
+        &signed,
+        &group_pk,
+        &ad,
+        context,
+        DEFAULT_CUSTOM_SECTION_NAME,
+    )
+    .unwrap();
+    assert!(
+        verify_custom_section(&signed, &group_pk, &ad, None, DEFAULT_CUSTOM_SECTION_NAME).is_err()
+    );
+}
@@ -0,0 +1,69 @@
+use std::process::Command;
+use wasmsign::signature::eddsa::EdDSA;
+use wasmsign::*;
+
+const MODULE: &[u8] = b"\0asm\x01\0\0\0";
+
+/// The prehash of the original format: the context, the AD length, the AD and the data.
+fn legacy_prehash(hash_alg: HashAlg, data: &[u8]) -> Vec<u8> {
+    let mut hasher = hash_alg.hasher();
+    hasher.update(DEFAULT_CONTEXT.as_bytes());
+    hasher.update(&0u32.to_le_bytes());
+    hasher.update(data);
+    hasher.finalize()
+}
+
+#[test]
+fn the_hash_function_is_part_of_the_prehash() {
+    let ad = AdditionalData::default();
+    assert_eq!(
+        EdDSA.prehash(MODULE, &ad, HashAlg::Sha512, None).unwrap(),
+        legacy_prehash(HashAlg::Sha512, MODULE)
+    );
+    for hash_alg in [HashAlg::Sha256, HashAlg::Sha3_256, HashAlg::Blake3] {
+        assert_ne!(
+            EdDSA.prehash(MODULE, &ad, hash_alg, None).unwrap(),
+            legacy_prehash(hash_alg, MODULE)
+        );
+    }
+}
+
+#[test]
+fn verifiers_can_require_a_hash_function() {
+    let dir = std::env::temp_dir().join(format!("wasmsign-hash-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("module.wasm"), MODULE).unwrap();
+    let wasmsign = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_wasmsign"))
+            .current_dir(&dir)
+            .args(args)
+            .status()
+            .unwrap()
+            .success()
+    };
+    assert!(wasmsign(&["-G", "-p", "pk", "-s", "sk"]));
+    assert!(wasmsign(&[
+        "-S",
+        "-C",
+        "--hash",
+        "sha256",
+        "-p",
+        "pk",
+        "-s",
+        "sk",
+        "-i",
+        "module.wasm",
+        "-o",
+        "signed.wasm"
+    ]));
+    let verify = |extra: &[&str]| {
+        let mut args = vec!["-V", "-C", "-p", "pk", "-i", "signed.wasm"];
+        args.extend_from_slice(extra);
+        wasmsign(&args)
+    };
+    assert!(verify(&[]));
+    assert!(verify(&["--hash", "sha256"]));
+    assert!(!verify(&["--hash", "sha512"]));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,104 @@
+use ct_codecs::{Encoder, Hex};
+use std::collections::BTreeMap;
+use wasmsign::signature::eddsa::EdDSA;
+use wasmsign::signature::{dsse, intoto};
+use wasmsign::*;
+
+const MODULE: &[u8] = b"\0asm\x01\0\0\0";
+const OTHER_MODULE: &[u8] = b"\0asm\x01\0\0\0\x00\x03\x02id";
+
+fn provenance() -> intoto::Statement {
+    intoto::Statement::slsa_provenance(
+        "https://example.com/builder",
+        "https://example.com/build-type",
+        &[],
+    )
+}
+
+/// Appends an attestation for a statement with the given subject digests.
+fn attest_digests(kp: &KeyPair, digests: &[(&str, String)]) -> Vec<u8> {
+    let mut statement = provenance();
+    statement.subject = vec![intoto::Subject {
+        name: "module.wasm".to_string(),
+        digest: digests
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect::<BTreeMap<_, _>>(),
+    }];
+    let envelope = dsse::sign(
+        intoto::PAYLOAD_TYPE,
+        &statement.to_json().unwrap(),
+        kp,
+        None,
+    )
+    .unwrap();
+    let name = DEFAULT_ATTESTATION_SECTION_NAME.as_bytes();
+    let mut payload = vec![name.len() as u8];
+    payload.extend_from_slice(name);
+    payload.extend_from_slice(&envelope);
+    let mut module = MODULE.to_vec();
+    module.push(0x00);
+    let mut len = payload.len();
+    while len >= 0x80 {
+        module.push((len & 0x7f) as u8 | 0x80);
+        len >>= 7;
+    }
+    module.push(len as u8);
+    module.extend_from_slice(&payload);
+    module
+}
+
+fn verify(kp: &KeyPair, module: &[u8]) -> Result<intoto::Statement, WError> {
+    verify_attestation(module, &kp.pk, DEFAULT_ATTESTATION_SECTION_NAME)
+}
+
+#[test]
+fn attestations_are_bound_to_the_module_digest() {
+    let kp = keygen_from_seed(&EdDSA, &[7u8; 32], None).unwrap();
+    let other_kp = keygen_from_seed(&EdDSA, &[8u8; 32], None).unwrap();
+    let attested = attest(
+        MODULE,
+        &kp,
+        &provenance(),
+        "module.wasm",
+        HashAlg::Sha256,
+        None,
+        DEFAULT_ATTESTATION_SECTION_NAME,
+    )
+    .unwrap();
+    let statement = verify(&kp, &attested).unwrap();
+    assert_eq!(statement.predicate_type, intoto::SLSA_PROVENANCE_TYPE);
+    assert_eq!(statement.subject[0].name, "module.wasm");
+    assert_eq!(
+        statement.subject[0].digest["sha256"],
+        Hex::encode_to_string(hmac_sha256::Hash::hash(MODULE)).unwrap()
+    );
+    assert!(verify(&other_kp, &attested).is_err());
+
+    let mut tampered = OTHER_MODULE.to_vec();
+    tampered.extend_from_slice(&attested[MODULE.len()..]);
+    assert!(verify(&kp, &tampered).is_err());
+}
+
+#[test]
+fn every_supported_subject_digest_must_match() {
+    let kp = keygen_from_seed(&EdDSA, &[7u8; 32], None).unwrap();
+    let sha256 = Hex::encode_to_string(hmac_sha256::Hash::hash(MODULE)).unwrap();
+    let other_sha256 = Hex::encode_to_string(hmac_sha256::Hash::hash(OTHER_MODULE)).unwrap();
+    let other_sha512 = Hex::encode_to_string(hmac_sha512::Hash::hash(OTHER_MODULE)).unwrap();
+
+    verify(&kp, &attest_digests(&kp, &[("sha256", sha256.clone())])).unwrap();
+    verify(
+        &kp,
+        &attest_digests(&kp, &[("sha256", sha256.to_uppercase())]),
+    )
+    .unwrap();
+    assert!(verify(&kp, &attest_digests(&kp, &[("sha256", other_sha256)])).is_err());
+    assert!(verify(
+        &kp,
+        &attest_digests(&kp, &[("sha256", sha256.clone()), ("sha512", other_sha512)]),
+    )
+    .is_err());
+    assert!(verify(&kp, &attest_digests(&kp, &[("md5", sha256)])).is_err());
+    assert!(verify(&kp, &attest_digests(&kp, &[])).is_err());
+}
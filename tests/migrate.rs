@@ -0,0 +1,58 @@
+use wasmsign::signature::eddsa::EdDSA;
+use wasmsign::*;
+
+/// A module with a memory, a global, an export and a single data segment.
+const MODULE: &[u8] = &[
+    0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+    0x05, 0x03, 0x01, 0x00, 0x01, // memory section
+    0x06, 0x06, 0x01, 0x7f, 0x00, 0x41, 0x00, 0x0b, // global section
+    0x07, 0x07, 0x01, 0x03, b'm', b'e', b'm', 0x02, 0x00, // export section
+    0x0b, 0x0a, 0x01, 0x00, 0x41, 0x00, 0x0b, 0x04, b't', b'e', b's', b't', // data section
+];
+
+fn migrate(signed: &[u8], kp: &KeyPair, context: Option<&str>) -> Result<Vec<u8>, WError> {
+    wasmsign::migrate(
+        signed,
+        &kp.pk,
+        &AdditionalData::default(),
+        context,
+        DEFAULT_SYMBOL_NAME,
+        kp,
+        DEFAULT_CUSTOM_SECTION_NAME,
+    )
+    .map(|(migrated, _)| migrated)
+}
+
+#[test]
+fn migration_verifies_with_the_callers_context() {
+    let kp = keygen_from_seed(&EdDSA, &[7u8; 32], None).unwrap();
+    let ad = AdditionalData::default();
+    let context = Some("deployment-a");
+
+    let signed = sign(
+        MODULE,
+        &kp,
+        &ad,
+        HashAlg::default(),
+        context,
+        DEFAULT_SYMBOL_NAME,
+    )
+    .unwrap();
+    assert!(migrate(&signed, &kp, None).is_err());
+    assert!(migrate(&signed, &kp, Some("deployment-b")).is_err());
+    let migrated = migrate(&signed, &kp, context).unwrap();
+    verify_custom_section(&migrated, &kp.pk, &ad, context, DEFAULT_CUSTOM_SECTION_NAME).unwrap();
+
+    let signed = sign(
+        MODULE,
+        &kp,
+        &ad,
+        HashAlg::default(),
+        None,
+        DEFAULT_SYMBOL_NAME,
+    )
+    .unwrap();
+    assert!(migrate(&signed, &kp, context).is_err());
+    let migrated = migrate(&signed, &kp, None).unwrap();
+    verify_custom_section(&migrated, &kp.pk, &ad, None, DEFAULT_CUSTOM_SECTION_NAME).unwrap();
+}
@@ -0,0 +1,141 @@
+use std::process::Command;
+use wasmsign::signature::eddsa::{self, EdDSA};
+use wasmsign::*;
+
+/// A module with a memory, a global, an export and a single data segment.
+const MODULE: &[u8] = &[
+    0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+    0x05, 0x03, 0x01, 0x00, 0x01, // memory section
+    0x06, 0x06, 0x01, 0x7f, 0x00, 0x41, 0x00, 0x0b, // global section
+    0x07, 0x07, 0x01, 0x03, b'm', b'e', b'm', 0x02, 0x00, // export section
+    0x0b, 0x0a, 0x01, 0x00, 0x41, 0x00, 0x0b, 0x04, b't', b'e', b's', b't', // data section
+];
+
+#[test]
+fn prepared_modules_verify_once_finalized() {
+    let kp = keygen_from_seed(&EdDSA, &[7u8; 32], None).unwrap();
+    let ad = AdditionalData::new(b"offline".to_vec());
+    let context = Some("offline test");
+
+    // The signature is stored in a data segment, zeroed until finalization
+    let (prepared, request) = prepare(
+        MODULE,
+        eddsa::ALG_ID,
+        &ad,
+        HashAlg::Sha256,
+        context,
+        DEFAULT_SYMBOL_NAME,
+    )
+    .unwrap();
+    let request = SigningRequest::from_bytes(&request.to_bytes()).unwrap();
+    let signature = sign_digest(&request, &kp).unwrap();
+    let signature = Signature::from_bytes(&signature.to_bytes()).unwrap();
+    let signed = finalize(&prepared, &signature, DEFAULT_SYMBOL_NAME).unwrap();
+    verify(&signed, &kp.pk, &ad, context, DEFAULT_SYMBOL_NAME).unwrap();
+
+    let sections = SectionSelection::standard(&["name"]);
+    let (prepared, request) = prepare_custom_section(
+        MODULE,
+        eddsa::ALG_ID,
+        &ad,
+        HashAlg::Sha256,
+        context,
+        &sections,
+        DEFAULT_CUSTOM_SECTION_NAME,
+    )
+    .unwrap();
+    let signature = sign_digest(&request, &kp).unwrap();
+    let signed =
+        finalize_custom_section(&prepared, &signature, DEFAULT_CUSTOM_SECTION_NAME).unwrap();
+    assert_eq!(
+        inspect_custom_section(&signed, DEFAULT_CUSTOM_SECTION_NAME)
+            .unwrap()
+            .sections(),
+        &sections
+    );
+    verify_custom_section(&signed, &kp.pk, &ad, context, DEFAULT_CUSTOM_SECTION_NAME).unwrap();
+}
+
+#[test]
+fn signatures_for_other_requests_are_rejected() {
+    let kp = keygen_from_seed(&EdDSA, &[7u8; 32], None).unwrap();
+    let ad = AdditionalData::default();
+    let prepare = |module: &[u8]| {
+        wasmsign::prepare(
+            module,
+            eddsa::ALG_ID,
+            &ad,
+            HashAlg::default(),
+            None,
+            DEFAULT_SYMBOL_NAME,
+        )
+        .unwrap()
+    };
+    let (prepared, _) = prepare(MODULE);
+    let mut other_module = MODULE.to_vec();
+    *other_module.last_mut().unwrap() = b'x';
+    let (_, other_request) = prepare(&other_module);
+    let other_signature = sign_digest(&other_request, &kp).unwrap();
+    let signed = finalize(&prepared, &other_signature, DEFAULT_SYMBOL_NAME).unwrap();
+    assert!(verify(&signed, &kp.pk, &ad, None, DEFAULT_SYMBOL_NAME).is_err());
+
+    // Given the public key, the finalize command refuses to write the module
+    let dir = std::env::temp_dir().join(format!("wasmsign-offline-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join("module.wasm"), MODULE).unwrap();
+    std::fs::write(dir.join("other.wasm"), &other_module).unwrap();
+    let wasmsign = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_wasmsign"))
+            .current_dir(&dir)
+            .args(args)
+            .status()
+            .unwrap()
+            .success()
+    };
+    assert!(wasmsign(&["-G", "-p", "pk", "-s", "sk"]));
+    for (input, name) in [("module.wasm", "module"), ("other.wasm", "other")] {
+        let prepared = format!("{}.prepared", name);
+        let request = format!("{}.request", name);
+        let signature = format!("{}.sig", name);
+        assert!(wasmsign(&[
+            "--prepare",
+            "-p",
+            "pk",
+            "-i",
+            input,
+            "-o",
+            &prepared,
+            "--request",
+            &request
+        ]));
+        assert!(wasmsign(&[
+            "--sign-digest",
+            "-p",
+            "pk",
+            "-s",
+            "sk",
+            "--request",
+            &request,
+            "--signature",
+            &signature
+        ]));
+    }
+    let finalize = |signature: &str, output: &str| {
+        wasmsign(&[
+            "--finalize",
+            "-p",
+            "pk",
+            "-i",
+            "module.prepared",
+            "--signature",
+            signature,
+            "-o",
+            output,
+        ])
+    };
+    assert!(!finalize("other.sig", "rejected.wasm"));
+    assert!(!dir.join("rejected.wasm").exists());
+    assert!(finalize("module.sig", "signed.wasm"));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
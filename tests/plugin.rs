@@ -0,0 +1,78 @@
+#![cfg(unix)]
+
+use ct_codecs::{Encoder, Hex};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use wasmsign::signature::eddsa::EdDSA;
+use wasmsign::signature::plugin::PluginSigner;
+use wasmsign::*;
+
+const MODULE: &[u8] = b"\0asm\x01\0\0\0";
+
+/// A plugin answering with a fixed key, that only signs the expected digest.
+fn write_plugin(dir: &Path, alg_id: &str, pk: &[u8], digest: &[u8], signature: &[u8]) -> PathBuf {
+    let script = format!(
+        "#!/bin/sh\n\
+         read version\n\
+         read request\n\
+         [ \"$version\" = \"wasmsign-signer 1\" ] || exit 1\n\
+         case \"$request\" in\n\
+         \"request info\")\n\
+         echo \"alg-id {alg_id}\"\n\
+         echo \"public-key {pk}\"\n\
+         ;;\n\
+         \"request sign\")\n\
+         read alg_id\n\
+         read digest\n\
+         if [ \"$alg_id\" = \"alg-id {alg_id}\" ] && [ \"$digest\" = \"digest {digest}\" ]; then\n\
+         echo \"signature {signature}\"\n\
+         else\n\
+         echo \"error unexpected request\"\n\
+         fi\n\
+         ;;\n\
+         esac\n",
+        alg_id = alg_id,
+        pk = Hex::encode_to_string(pk).unwrap(),
+        digest = Hex::encode_to_string(digest).unwrap(),
+        signature = Hex::encode_to_string(signature).unwrap(),
+    );
+    let path = dir.join(format!("plugin-{}", alg_id));
+    std::fs::write(&path, script).unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
+
+#[test]
+fn plugins_sign_modules() {
+    let dir = std::env::temp_dir().join(format!("wasmsign-plugin-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let kp = keygen_from_seed(&EdDSA, &[7u8; 32], None).unwrap();
+    let ad = AdditionalData::default();
+    let digest = EdDSA
+        .prehash(MODULE, &ad, HashAlg::default(), None)
+        .unwrap();
+    let signature = kp.sign_prehashed(&digest).unwrap();
+
+    let plugin = write_plugin(&dir, "00000002", kp.pk.raw(), &digest, &signature);
+    let signer = PluginSigner::new(&plugin, vec![]).unwrap();
+    assert_eq!(signer.alg_id(), kp.pk.alg_id());
+    assert_eq!(signer.public_key().unwrap().raw(), kp.pk.raw());
+    let signed = sign_custom_section(
+        MODULE,
+        &signer,
+        &ad,
+        HashAlg::default(),
+        None,
+        &SectionSelection::All,
+        DEFAULT_CUSTOM_SECTION_NAME,
+    )
+    .unwrap();
+    verify_custom_section(&signed, &kp.pk, &ad, None, DEFAULT_CUSTOM_SECTION_NAME).unwrap();
+
+    // The plugin reports errors, and the algorithms it doesn't support
+    assert!(signer.sign_prehashed(b"other digest").is_err());
+    let plugin = write_plugin(&dir, "ffffffff", kp.pk.raw(), &digest, &signature);
+    assert!(PluginSigner::new(&plugin, vec![]).is_err());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,198 @@
+use wasmsign::signature::eddsa::EdDSA;
+use wasmsign::*;
+
+const HEADER: &[u8] = b"\0asm\x01\0\0\0";
+const TYPE_SECTION: &[u8] = b"\x01\x04\x01\x60\x00\x00";
+const FUNCTION_SECTION: &[u8] = b"\x03\x02\x01\x00";
+const CODE_SECTION: &[u8] = b"\x0a\x04\x01\x02\x00\x0b";
+const CODE_SECTION_WITH_NOP: &[u8] = b"\x0a\x05\x01\x03\x00\x01\x0b";
+const NAME_SECTION: &[u8] = b"\x00\x07\x04name\x00\x00";
+const OTHER_NAME_SECTION: &[u8] = b"\x00\x07\x04name\x00\x01";
+const DEBUG_SECTION: &[u8] = b"\x00\x07\x05debug\x01";
+const OTHER_DEBUG_SECTION: &[u8] = b"\x00\x08\x05debug\x02\x03";
+
+fn module(sections: &[&[u8]]) -> Vec<u8> {
+    let mut module = HEADER.to_vec();
+    for section in sections {
+        module.extend_from_slice(section);
+    }
+    module
+}
+
+/// Signs a module with only the `name` Custom Section selected, and returns the signature section.
+fn signature_section(kp: &KeyPair, sections: &[&[u8]]) -> Vec<u8> {
+    let module = module(sections);
+    let signed = sign_custom_section(
+        &module,
+        kp,
+        &AdditionalData::default(),
+        HashAlg::default(),
+        None,
+        &SectionSelection::standard(&["name"]),
+        DEFAULT_CUSTOM_SECTION_NAME,
+    )
+    .unwrap();
+    assert!(signed.starts_with(&module));
+    signed[module.len()..].to_vec()
+}
+
+fn verify(kp: &KeyPair, sections: &[&[u8]], signature_section: &[u8]) -> Result<(), WError> {
+    let mut signed = module(sections);
+    signed.extend_from_slice(signature_section);
+    verify_custom_section(
+        &signed,
+        &kp.pk,
+        &AdditionalData::default(),
+        None,
+        DEFAULT_CUSTOM_SECTION_NAME,
+    )
+}
+
+#[test]
+fn only_selected_custom_sections_are_signed() {
+    let kp = keygen_from_seed(&EdDSA, &[7u8; 32], None).unwrap();
+    let signed_sections = [
+        TYPE_SECTION,
+        FUNCTION_SECTION,
+        CODE_SECTION,
+        NAME_SECTION,
+        DEBUG_SECTION,
+    ];
+    let signature = signature_section(&kp, &signed_sections);
+    verify(&kp, &signed_sections, &signature).unwrap();
+
+    // Unselected Custom Sections can be stripped, changed or added
+    verify(
+        &kp,
+        &[TYPE_SECTION, FUNCTION_SECTION, CODE_SECTION, NAME_SECTION],
+        &signature,
+    )
+    .unwrap();
+    verify(
+        &kp,
+        &[
+            TYPE_SECTION,
+            FUNCTION_SECTION,
+            CODE_SECTION,
+            OTHER_DEBUG_SECTION,
+            NAME_SECTION,
+            DEBUG_SECTION,
+        ],
+        &signature,
+    )
+    .unwrap();
+
+    // Selected Custom Sections and non-custom sections can't
+    assert!(verify(
+        &kp,
+        &[TYPE_SECTION, FUNCTION_SECTION, CODE_SECTION, DEBUG_SECTION],
+        &signature,
+    )
+    .is_err());
+    assert!(verify(
+        &kp,
+        &[
+            TYPE_SECTION,
+            FUNCTION_SECTION,
+            CODE_SECTION,
+            OTHER_NAME_SECTION,
+            DEBUG_SECTION,
+        ],
+        &signature,
+    )
+    .is_err());
+    assert!(verify(
+        &kp,
+        &[
+            TYPE_SECTION,
+            FUNCTION_SECTION,
+            CODE_SECTION_WITH_NOP,
+            NAME_SECTION,
+            DEBUG_SECTION,
+        ],
+        &signature,
+    )
+    .is_err());
+}
+
+const TWO_FUNCTIONS_SECTION: &[u8] = b"\x03\x03\x02\x00\x00";
+const TWO_BODIES_SECTION: &[u8] = b"\x0a\x07\x02\x02\x00\x0b\x02\x00\x0b";
+const TWO_BODIES_SECTION_WITH_NOP: &[u8] = b"\x0a\x08\x02\x02\x00\x0b\x03\x00\x01\x0b";
+const TWO_TYPES_SECTION: &[u8] = b"\x01\x07\x02\x60\x00\x00\x60\x00\x00";
+
+fn merkle_signed(kp: &KeyPair) -> Vec<u8> {
+    sign_custom_section_merkle(
+        &module(&[
+            TYPE_SECTION,
+            TWO_FUNCTIONS_SECTION,
+            TWO_BODIES_SECTION,
+            NAME_SECTION,
+        ]),
+        kp,
+        &AdditionalData::default(),
+        HashAlg::default(),
+        None,
+        &SectionSelection::All,
+        DEFAULT_CUSTOM_SECTION_NAME,
+    )
+    .unwrap()
+}
+
+/// Replaces a section of a signed module with a section of the same id.
+fn replace(module: &[u8], section: &[u8], other_section: &[u8]) -> Vec<u8> {
+    let start = module
+        .windows(section.len())
+        .position(|window| window == section)
+        .unwrap();
+    [
+        &module[..start],
+        other_section,
+        &module[start + section.len()..],
+    ]
+    .concat()
+}
+
+fn mismatch(kp: &KeyPair, module: &[u8]) -> String {
+    match verify_custom_section(
+        module,
+        &kp.pk,
+        &AdditionalData::default(),
+        None,
+        DEFAULT_CUSTOM_SECTION_NAME,
+    ) {
+        Err(WError::SectionMismatch(kind)) => kind,
+        res => panic!("Expected a section mismatch, got {:?}", res),
+    }
+}
+
+#[test]
+fn merkle_signatures_report_the_modified_part() {
+    let kp = keygen_from_seed(&EdDSA, &[7u8; 32], None).unwrap();
+    let signed = merkle_signed(&kp);
+    verify_custom_section(
+        &signed,
+        &kp.pk,
+        &AdditionalData::default(),
+        None,
+        DEFAULT_CUSTOM_SECTION_NAME,
+    )
+    .unwrap();
+
+    let modified = replace(&signed, TWO_BODIES_SECTION, TWO_BODIES_SECTION_WITH_NOP);
+    assert_eq!(mismatch(&kp, &modified), "function body #1");
+    verify_custom_section_partial(
+        &modified,
+        &kp.pk,
+        &AdditionalData::default(),
+        None,
+        DEFAULT_CUSTOM_SECTION_NAME,
+        &|kind| *kind != LeafKind::Function(1),
+    )
+    .unwrap();
+    let modified = replace(&signed, NAME_SECTION, OTHER_NAME_SECTION);
+    assert_eq!(mismatch(&kp, &modified), "custom section [name]");
+    let modified = replace(&signed, TYPE_SECTION, TWO_TYPES_SECTION);
+    assert_eq!(mismatch(&kp, &modified), "type section (id 1)");
+    let modified = replace(&signed, NAME_SECTION, DEBUG_SECTION);
+    assert_eq!(mismatch(&kp, &modified), "custom section [debug]");
+}
@@ -0,0 +1,46 @@
+use wasmsign::signature::eddsa::EdDSA;
+use wasmsign::signature::shamir::*;
+use wasmsign::*;
+
+fn combine(shares: &[&SecretKeyShare]) -> Result<SecretKey, WError> {
+    let shares: Vec<_> = shares
+        .iter()
+        .map(|share| SecretKeyShare::from_bytes(&share.to_bytes()).unwrap())
+        .collect();
+    combine_secret_key_shares(&shares)
+}
+
+#[test]
+fn any_threshold_of_shares_recovers_the_key() {
+    let kp = keygen_from_seed(&EdDSA, &[7u8; 32], None).unwrap();
+    let shares = split_secret_key(&kp.sk, 3, 5).unwrap();
+    assert_eq!(shares.len(), 5);
+    assert!(shares.iter().all(|share| share.threshold() == 3));
+
+    for i in 0..5 {
+        for j in i + 1..5 {
+            assert!(combine(&[&shares[i], &shares[j]]).is_err());
+            for k in j + 1..5 {
+                let sk = combine(&[&shares[k], &shares[i], &shares[j]]).unwrap();
+                assert_eq!(sk.raw(), kp.sk.raw());
+            }
+        }
+    }
+    let sk = combine(&[&shares[4], &shares[0], &shares[2], &shares[1]]).unwrap();
+    assert_eq!(sk.raw(), kp.sk.raw());
+    let sk = combine(&shares.iter().collect::<Vec<_>>()).unwrap();
+    assert_eq!(sk.raw(), kp.sk.raw());
+}
+
+#[test]
+fn shares_must_be_distinct_and_from_the_same_key() {
+    let kp = keygen_from_seed(&EdDSA, &[7u8; 32], None).unwrap();
+    let other_kp = keygen_from_seed(&EdDSA, &[8u8; 32], None).unwrap();
+    let shares = split_secret_key(&kp.sk, 2, 3).unwrap();
+    let other_shares = split_secret_key(&other_kp.sk, 2, 3).unwrap();
+    assert!(combine(&[&shares[0], &shares[0]]).is_err());
+    assert!(combine(&[&shares[0], &other_shares[1]]).is_err());
+    assert!(combine(&[]).is_err());
+    assert!(split_secret_key(&kp.sk, 0, 3).is_err());
+    assert!(split_secret_key(&kp.sk, 4, 3).is_err());
+}
@@ -0,0 +1,237 @@
+use ct_codecs::{Base64, Encoder, Hex};
+use rcgen::{
+    BasicConstraints, CertificateParams, CustomExtension, DnType, ExtendedKeyUsagePurpose, IsCa,
+    KeyPair, KeyUsagePurpose, SanType, PKCS_ECDSA_P384_SHA384,
+};
+use ring::rand::SystemRandom;
+use ring::signature::{EcdsaKeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+use serde_json::json;
+use std::convert::{TryFrom, TryInto};
+use wasmsign::*;
+
+const MODULE: &[u8] = b"\0asm\x01\0\0\0";
+const IDENTITY: &str = "dev@example.com";
+const ISSUER: &str = "https://accounts.example.com";
+const INTEGRATED_TIME: u64 = 1_700_000_000;
+
+fn b64(bin: &[u8]) -> String {
+    Base64::encode_to_string(bin).unwrap()
+}
+
+fn sha256(parts: &[&[u8]]) -> Vec<u8> {
+    let mut hasher = hmac_sha256::Hash::new();
+    for part in parts {
+        hasher.update(part);
+    }
+    hasher.finalize().to_vec()
+}
+
+fn ca_params(name: &str) -> CertificateParams {
+    let mut params = CertificateParams::new(vec![]).unwrap();
+    params.distinguished_name.push(DnType::CommonName, name);
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    params.key_usages = vec![KeyUsagePurpose::KeyCertSign];
+    params
+}
+
+fn p256_key(rng: &SystemRandom) -> (EcdsaKeyPair, KeyPair) {
+    let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, rng).unwrap();
+    let key_pair =
+        EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), rng).unwrap();
+    (key_pair, KeyPair::try_from(pkcs8.as_ref()).unwrap())
+}
+
+/// Builds a bundle for `MODULE`, signed by `identity` authenticated by `issuer`, and a trusted root for it.
+fn bundle_and_trusted_root(identity: &str, issuer: &str) -> (Vec<u8>, Vec<u8>) {
+    let rng = SystemRandom::new();
+
+    let root_key = KeyPair::generate_for(&PKCS_ECDSA_P384_SHA384).unwrap();
+    let root = ca_params("Test root").self_signed(&root_key).unwrap();
+    let intermediate_key = KeyPair::generate_for(&PKCS_ECDSA_P384_SHA384).unwrap();
+    let intermediate = ca_params("Test intermediate")
+        .signed_by(&intermediate_key, &root, &root_key)
+        .unwrap();
+
+    let (signing_key, leaf_key) = p256_key(&rng);
+    let mut params = CertificateParams::new(vec![]).unwrap();
+    params.subject_alt_names = vec![SanType::Rfc822Name(identity.try_into().unwrap())];
+    params.extended_key_usages = vec![ExtendedKeyUsagePurpose::CodeSigning];
+    params.key_usages = vec![KeyUsagePurpose::DigitalSignature];
+    params.custom_extensions = vec![CustomExtension::from_oid_content(
+        &[1, 3, 6, 1, 4, 1, 57264, 1, 1],
+        issuer.as_bytes().to_vec(),
+    )];
+    let leaf = params
+        .signed_by(&leaf_key, &intermediate, &intermediate_key)
+        .unwrap();
+    let signature = signing_key.sign(&rng, MODULE).unwrap();
+
+    // A log with 3 entries, the last one being ours
+
+    let (log_key, log_key_pair) = p256_key(&rng);
+    let log_spki = log_key_pair.public_key_der();
+    let log_id = sha256(&[&log_spki]);
+    let leaf_pem = format!(
+        "-----BEGIN CERTIFICATE-----\n{}\n-----END CERTIFICATE-----\n",
+        b64(leaf.der())
+    );
+    let body = serde_json::to_vec(&json!({
+        "apiVersion": "0.0.1",
+        "kind": "hashedrekord",
+        "spec": {
+            "data": { "hash": { "algorithm": "sha256", "value": Hex::encode_to_string(sha256(&[MODULE])).unwrap() } },
+            "signature": { "content": b64(signature.as_ref()), "publicKey": { "content": b64(leaf_pem.as_bytes()) } },
+        },
+    }))
+    .unwrap();
+    let leaf_hashes = [
+        sha256(&[&[0], b"entry 0"]),
+        sha256(&[&[0], b"entry 1"]),
+        sha256(&[&[0], &body]),
+    ];
+    let left = sha256(&[&[1], &leaf_hashes[0], &leaf_hashes[1]]);
+    let root_hash = sha256(&[&[1], &left, &leaf_hashes[2]]);
+    let note = format!("rekor.example - 1\n3\n{}\n", b64(&root_hash));
+    let mut note_signature = log_id[..4].to_vec();
+    note_signature.extend_from_slice(log_key.sign(&rng, note.as_bytes()).unwrap().as_ref());
+    let checkpoint = format!(
+        "{}\n\u{2014} rekor.example {}\n",
+        note,
+        b64(&note_signature)
+    );
+    let set_payload = serde_json::to_vec(&json!({
+        "body": b64(&body),
+        "integratedTime": INTEGRATED_TIME,
+        "logID": Hex::encode_to_string(&log_id).unwrap(),
+        "logIndex": 2,
+    }))
+    .unwrap();
+    let set = log_key.sign(&rng, &set_payload).unwrap();
+
+    let bundle = json!({
+        "mediaType": "application/vnd.dev.sigstore.bundle.v0.3+json",
+        "verificationMaterial": {
+            "certificate": { "rawBytes": b64(leaf.der()) },
+            "tlogEntries": [{
+                "logIndex": "2",
+                "logId": { "keyId": b64(&log_id) },
+                "kindVersion": { "kind": "hashedrekord", "version": "0.0.1" },
+                "integratedTime": INTEGRATED_TIME.to_string(),
+                "inclusionPromise": { "signedEntryTimestamp": b64(set.as_ref()) },
+                "inclusionProof": {
+                    "logIndex": "2",
+                    "rootHash": b64(&root_hash),
+                    "treeSize": "3",
+                    "hashes": [b64(&left)],
+                    "checkpoint": { "envelope": checkpoint },
+                },
+                "canonicalizedBody": b64(&body),
+            }],
+        },
+        "messageSignature": {
+            "messageDigest": { "algorithm": "SHA2_256", "digest": b64(&sha256(&[MODULE])) },
+            "signature": b64(signature.as_ref()),
+        },
+    });
+    let trusted_root = json!({
+        "mediaType": "application/vnd.dev.sigstore.trustedroot+json;version=0.1",
+        "tlogs": [{
+            "baseUrl": "https://rekor.example",
+            "hashAlgorithm": "SHA2_256",
+            "publicKey": {
+                "rawBytes": b64(&log_spki),
+                "keyDetails": "PKIX_ECDSA_P256_SHA_256",
+                "validFor": { "start": "2021-01-01T00:00:00.000Z" },
+            },
+            "logId": { "keyId": b64(&log_id) },
+        }],
+        "certificateAuthorities": [{
+            "certChain": { "certificates": [
+                { "rawBytes": b64(intermediate.der()) },
+                { "rawBytes": b64(root.der()) },
+            ] },
+            "validFor": { "start": "2021-01-01T00:00:00Z" },
+        }],
+    });
+    (
+        serde_json::to_vec(&bundle).unwrap(),
+        serde_json::to_vec(&trusted_root).unwrap(),
+    )
+}
+
+#[test]
+fn sigstore_bundles_are_verified_offline() {
+    let (bundle, trusted_root) = bundle_and_trusted_root(IDENTITY, ISSUER);
+
+    let verified =
+        verify_sigstore_bundle(MODULE, &bundle, &trusted_root, IDENTITY, ISSUER).unwrap();
+    assert_eq!(verified.log_index, 2);
+    assert_eq!(verified.integrated_time, INTEGRATED_TIME);
+
+    assert!(verify_sigstore_bundle(
+        b"\0asm\x01\0\0\0\0",
+        &bundle,
+        &trusted_root,
+        IDENTITY,
+        ISSUER
+    )
+    .is_err());
+
+    // A root that trusts the CA but not the log
+    let (_, other_trusted_root) = bundle_and_trusted_root(IDENTITY, ISSUER);
+    let mut mixed: serde_json::Value = serde_json::from_slice(&trusted_root).unwrap();
+    let other: serde_json::Value = serde_json::from_slice(&other_trusted_root).unwrap();
+    mixed["tlogs"] = other["tlogs"].clone();
+    let mixed = serde_json::to_vec(&mixed).unwrap();
+    assert!(verify_sigstore_bundle(MODULE, &bundle, &mixed, IDENTITY, ISSUER).is_err());
+}
+
+#[test]
+fn sigstore_bundles_from_other_signers_are_rejected() {
+    let (bundle, trusted_root) = bundle_and_trusted_root("other@example.com", ISSUER);
+    assert!(verify_sigstore_bundle(MODULE, &bundle, &trusted_root, IDENTITY, ISSUER).is_err());
+    verify_sigstore_bundle(MODULE, &bundle, &trusted_root, "other@example.com", ISSUER).unwrap();
+
+    let (bundle, trusted_root) = bundle_and_trusted_root(IDENTITY, "https://other.example.com");
+    assert!(verify_sigstore_bundle(MODULE, &bundle, &trusted_root, IDENTITY, ISSUER).is_err());
+}
+
+#[test]
+fn sigstore_verification_requires_an_identity_and_an_issuer() {
+    let dir = std::env::temp_dir().join(format!("wasmsign-sigstore-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let (bundle, trusted_root) = bundle_and_trusted_root(IDENTITY, ISSUER);
+    std::fs::write(dir.join("module.wasm"), MODULE).unwrap();
+    std::fs::write(dir.join("bundle.json"), bundle).unwrap();
+    std::fs::write(dir.join("trusted_root.json"), trusted_root).unwrap();
+    let verify = |extra: &[&str]| {
+        let mut args = vec![
+            "-V",
+            "-i",
+            "module.wasm",
+            "--sigstore-bundle",
+            "bundle.json",
+            "--trusted-root",
+            "trusted_root.json",
+        ];
+        args.extend_from_slice(extra);
+        std::process::Command::new(env!("CARGO_BIN_EXE_wasmsign"))
+            .current_dir(&dir)
+            .args(&args)
+            .output()
+            .unwrap()
+            .status
+            .success()
+    };
+    assert!(!verify(&[]));
+    assert!(!verify(&["--certificate-identity", IDENTITY]));
+    assert!(!verify(&["--certificate-oidc-issuer", ISSUER]));
+    assert!(verify(&[
+        "--certificate-identity",
+        IDENTITY,
+        "--certificate-oidc-issuer",
+        ISSUER
+    ]));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,233 @@
+use cms::cert::{CertificateChoices, IssuerAndSerialNumber};
+use cms::content_info::{CmsVersion, ContentInfo};
+use cms::signed_data::{
+    CertificateSet, EncapsulatedContentInfo, SignedData, SignerIdentifier, SignerInfo, SignerInfos,
+};
+use der::asn1::{Any, GeneralizedTime, Int, ObjectIdentifier, OctetString, SetOfVec};
+use der::{Decode, Encode};
+use rcgen::{
+    date_time_ymd, BasicConstraints, Certificate, CertificateParams, DnType,
+    ExtendedKeyUsagePurpose, IsCa, KeyPair, KeyUsagePurpose, PKCS_ECDSA_P256_SHA256,
+};
+use ring::rand::SystemRandom;
+use ring::signature::{EcdsaKeyPair, ECDSA_P256_SHA256_ASN1_SIGNING};
+use std::convert::TryFrom;
+use std::time::Duration;
+use wasmsign::signature::eddsa::EdDSA;
+use wasmsign::tsp::*;
+use wasmsign::*;
+use x509_cert::attr::Attribute;
+use x509_cert::spki::AlgorithmIdentifierOwned;
+
+const MODULE: &[u8] = b"\0asm\x01\0\0\0";
+const SEED: [u8; 32] = [7u8; 32];
+const ECDSA_WITH_SHA256: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.4.3.2");
+const GEN_TIME: u64 = 1_590_969_600; // 2020-06-01, while the signer certificate was valid
+
+fn ca(name: &str) -> (Certificate, KeyPair) {
+    let key = KeyPair::generate_for(&PKCS_ECDSA_P256_SHA256).unwrap();
+    let mut params = CertificateParams::new(vec![]).unwrap();
+    params.distinguished_name.push(DnType::CommonName, name);
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    params.key_usages = vec![KeyUsagePurpose::KeyCertSign];
+    (params.self_signed(&key).unwrap(), key)
+}
+
+/// A code-signing certificate for the key derived from `SEED`, that expired in 2021.
+fn signer_certificate(issuer: &Certificate, issuer_key: &KeyPair) -> Vec<u8> {
+    let mut pkcs8 = vec![
+        0x30, 0x2e, 0x02, 0x01, 0x00, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x04, 0x22, 0x04,
+        0x20,
+    ];
+    pkcs8.extend_from_slice(&SEED);
+    let key = KeyPair::try_from(pkcs8.as_slice()).unwrap();
+    let mut params = CertificateParams::new(vec![]).unwrap();
+    params
+        .distinguished_name
+        .push(DnType::CommonName, "Test signer");
+    params.extended_key_usages = vec![ExtendedKeyUsagePurpose::CodeSigning];
+    params.not_before = date_time_ymd(2020, 1, 1);
+    params.not_after = date_time_ymd(2021, 1, 1);
+    params
+        .signed_by(&key, issuer, issuer_key)
+        .unwrap()
+        .der()
+        .to_vec()
+}
+
+/// A local Time Stamping Authority.
+struct Tsa {
+    key: EcdsaKeyPair,
+    certificate: x509_cert::Certificate,
+}
+
+impl Tsa {
+    fn new(issuer: &Certificate, issuer_key: &KeyPair) -> Self {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &rng).unwrap();
+        let key = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref(), &rng)
+            .unwrap();
+        let key_pair = KeyPair::try_from(pkcs8.as_ref()).unwrap();
+        let mut params = CertificateParams::new(vec![]).unwrap();
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "Test TSA");
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::TimeStamping];
+        let certificate = params.signed_by(&key_pair, issuer, issuer_key).unwrap();
+        Tsa {
+            key,
+            certificate: x509_cert::Certificate::from_der(certificate.der()).unwrap(),
+        }
+    }
+
+    fn respond(&self, request: &[u8], status: u8) -> Vec<u8> {
+        let request = TimeStampReq::from_der(request).unwrap();
+        let tst_info = TstInfo {
+            version: 1,
+            policy: ObjectIdentifier::new_unwrap("1.2.3.4"),
+            message_imprint: request.message_imprint,
+            serial_number: Int::new(&[1]).unwrap(),
+            gen_time: GeneralizedTime::from_unix_duration(Duration::from_secs(GEN_TIME)).unwrap(),
+            accuracy: None,
+            ordering: false,
+            nonce: request.nonce,
+            tsa: None,
+            extensions: None,
+        };
+        let econtent = tst_info.to_der().unwrap();
+        let content_digest = ring::digest::digest(&ring::digest::SHA256, &econtent);
+        let attribute = |oid, value| Attribute {
+            oid,
+            values: SetOfVec::try_from(vec![value]).unwrap(),
+        };
+        let signed_attrs = SetOfVec::try_from(vec![
+            attribute(ID_CONTENT_TYPE, Any::encode_from(&ID_CT_TST_INFO).unwrap()),
+            attribute(
+                ID_MESSAGE_DIGEST,
+                Any::encode_from(&OctetString::new(content_digest.as_ref()).unwrap()).unwrap(),
+            ),
+        ])
+        .unwrap();
+        let signature = self
+            .key
+            .sign(&SystemRandom::new(), &signed_attrs.to_der().unwrap())
+            .unwrap();
+        let sha256 = AlgorithmIdentifierOwned {
+            oid: ID_SHA256,
+            parameters: None,
+        };
+        let signer_info = SignerInfo {
+            version: CmsVersion::V1,
+            sid: SignerIdentifier::IssuerAndSerialNumber(IssuerAndSerialNumber {
+                issuer: self.certificate.tbs_certificate.issuer.clone(),
+                serial_number: self.certificate.tbs_certificate.serial_number.clone(),
+            }),
+            digest_alg: sha256.clone(),
+            signed_attrs: Some(signed_attrs),
+            signature_algorithm: AlgorithmIdentifierOwned {
+                oid: ECDSA_WITH_SHA256,
+                parameters: None,
+            },
+            signature: OctetString::new(signature.as_ref()).unwrap(),
+            unsigned_attrs: None,
+        };
+        let signed_data = SignedData {
+            version: CmsVersion::V3,
+            digest_algorithms: SetOfVec::try_from(vec![sha256]).unwrap(),
+            encap_content_info: EncapsulatedContentInfo {
+                econtent_type: ID_CT_TST_INFO,
+                econtent: Some(Any::encode_from(&OctetString::new(econtent).unwrap()).unwrap()),
+            },
+            certificates: Some(CertificateSet(
+                SetOfVec::try_from(vec![CertificateChoices::Certificate(
+                    self.certificate.clone(),
+                )])
+                .unwrap(),
+            )),
+            crls: None,
+            signer_infos: SignerInfos(SetOfVec::try_from(vec![signer_info]).unwrap()),
+        };
+        TimeStampResp {
+            status: PkiStatusInfo {
+                status,
+                status_string: None,
+                fail_info: None,
+            },
+            time_stamp_token: Some(ContentInfo {
+                content_type: ID_SIGNED_DATA,
+                content: Any::encode_from(&signed_data).unwrap(),
+            }),
+        }
+        .to_der()
+        .unwrap()
+    }
+}
+
+fn sign_with_chain(certificates: Vec<Vec<u8>>) -> Vec<u8> {
+    let kp = keygen_from_seed(&EdDSA, &SEED, None).unwrap();
+    let signer = x509::CertifiedSigner::new(Box::new(kp), certificates).unwrap();
+    sign_custom_section(
+        MODULE,
+        &signer,
+        &AdditionalData::default(),
+        HashAlg::default(),
+        None,
+        &SectionSelection::All,
+        DEFAULT_CUSTOM_SECTION_NAME,
+    )
+    .unwrap()
+}
+
+fn verify_with_roots(
+    module: &[u8],
+    roots: &x509::TrustedRoots,
+    tsa_roots: Option<&x509::TrustedRoots>,
+) -> Result<PublicKey, WError> {
+    verify_custom_section_with_roots(
+        module,
+        roots,
+        tsa_roots,
+        &AdditionalData::default(),
+        None,
+        DEFAULT_CUSTOM_SECTION_NAME,
+    )
+}
+
+#[test]
+fn timestamps_keep_expired_certificates_valid() {
+    let (root, root_key) = ca("Test root");
+    let (tsa_root, tsa_root_key) = ca("Test TSA root");
+    let roots = x509::TrustedRoots::new(vec![root.der().to_vec()]);
+    let tsa_roots = x509::TrustedRoots::new(vec![tsa_root.der().to_vec()]);
+    let tsa = Tsa::new(&tsa_root, &tsa_root_key);
+
+    let signed = sign_with_chain(vec![signer_certificate(&root, &root_key)]);
+    assert!(verify_with_roots(&signed, &roots, None).is_err());
+    assert!(verify_with_roots(&signed, &roots, Some(&tsa_roots)).is_err());
+
+    let request = timestamp_request(&signed, DEFAULT_CUSTOM_SECTION_NAME).unwrap();
+    let response = tsa.respond(&request, 0);
+    let timestamped = attach_timestamp(&signed, &response, DEFAULT_CUSTOM_SECTION_NAME).unwrap();
+    assert_eq!(
+        verify_timestamp(&timestamped, &tsa_roots, DEFAULT_CUSTOM_SECTION_NAME).unwrap(),
+        GEN_TIME
+    );
+    verify_with_roots(&timestamped, &roots, Some(&tsa_roots)).unwrap();
+    assert!(verify_with_roots(&timestamped, &roots, None).is_err());
+    assert!(verify_with_roots(&timestamped, &roots, Some(&roots)).is_err());
+
+    // Rejected requests, and tokens issued for another signature
+    assert!(attach_timestamp(
+        &signed,
+        &tsa.respond(&request, 2),
+        DEFAULT_CUSTOM_SECTION_NAME
+    )
+    .is_err());
+    let other_request = tsp::request(b"another signature").unwrap();
+    assert!(attach_timestamp(
+        &signed,
+        &tsa.respond(&other_request, 0),
+        DEFAULT_CUSTOM_SECTION_NAME
+    )
+    .is_err());
+}
@@ -0,0 +1,158 @@
+use std::path::PathBuf;
+use wasmsign::signature::eddsa::EdDSA;
+use wasmsign::tlog::*;
+use wasmsign::*;
+
+const MODULE: &[u8] = b"\0asm\x01\0\0\0";
+
+fn head(leaf_hashes: &[Vec<u8>]) -> TreeHead {
+    TreeHead {
+        size: leaf_hashes.len() as u64,
+        root_hash: root_hash(leaf_hashes),
+    }
+}
+
+fn log_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("wasmsign-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+#[test]
+fn proofs_are_verified_for_all_tree_sizes() {
+    let leaf_hashes: Vec<Vec<u8>> = (0u8..20).map(|i| leaf_hash(&[i])).collect();
+    for size in 1..=leaf_hashes.len() {
+        let tree = &leaf_hashes[..size];
+        for index in 0..size {
+            let root = root_from_inclusion_proof(
+                index as u64,
+                size as u64,
+                tree[index].clone(),
+                &inclusion_path(tree, index),
+            )
+            .unwrap();
+            assert_eq!(root, root_hash(tree));
+        }
+        for old_size in 0..=size {
+            let old = head(&tree[..old_size]);
+            let proof = consistency_proof(tree, old_size);
+            verify_consistency(&old, &head(tree), &proof).unwrap();
+            if old_size > 0 && old_size < size {
+                let mut forked = tree[..old_size].to_vec();
+                forked[0] = leaf_hash(b"forked");
+                assert!(verify_consistency(&head(&forked), &head(tree), &proof).is_err());
+            }
+        }
+    }
+}
+
+#[test]
+fn logged_signatures_are_verified() {
+    let dir = log_dir("tlog");
+    let kp = keygen_from_seed(&EdDSA, &[7u8; 32], None).unwrap();
+    let key_id = kp.pk.key_id();
+    let log = TransparencyLog::create(&dir).unwrap();
+    log.append(&LogEntry::new(b"another module", None, 1).unwrap())
+        .unwrap();
+
+    let signed = sign_custom_section(
+        MODULE,
+        &kp,
+        &AdditionalData::default(),
+        HashAlg::default(),
+        None,
+        &SectionSelection::All,
+        DEFAULT_CUSTOM_SECTION_NAME,
+    )
+    .unwrap();
+    let entry = LogEntry::new(MODULE, Some(&key_id), 2).unwrap();
+    let log_proof = log.append(&entry).unwrap();
+    let logged = attach_log_proof(&signed, &log_proof, DEFAULT_CUSTOM_SECTION_NAME).unwrap();
+    verify_custom_section(
+        &logged,
+        &kp.pk,
+        &AdditionalData::default(),
+        None,
+        DEFAULT_CUSTOM_SECTION_NAME,
+    )
+    .unwrap();
+
+    // The log keeps growing after the module was signed
+    let logged_head = log.head().unwrap();
+    for i in 0..5 {
+        log.append(&LogEntry::new(&[i], None, 3).unwrap()).unwrap();
+    }
+    let head = log.head().unwrap();
+    let proof = log.consistency_proof(logged_head.size).unwrap();
+    let verified = verify_log_proof(
+        &logged,
+        Some(&key_id),
+        &head,
+        &proof,
+        DEFAULT_CUSTOM_SECTION_NAME,
+    )
+    .unwrap();
+    assert_eq!(verified.index, 1);
+    assert_eq!(verified.entry, entry);
+
+    assert!(verify_log_proof(
+        &logged,
+        Some(&[0u8; KEY_ID_BYTES]),
+        &head,
+        &proof,
+        DEFAULT_CUSTOM_SECTION_NAME
+    )
+    .is_err());
+    assert!(verify_log_proof(&logged, None, &head, &[], DEFAULT_CUSTOM_SECTION_NAME).is_err());
+    assert!(verify_log_proof(&signed, None, &head, &proof, DEFAULT_CUSTOM_SECTION_NAME).is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn concurrent_appends_return_valid_proofs() {
+    let dir = log_dir("tlog-concurrent");
+    TransparencyLog::create(&dir).unwrap();
+    let threads: Vec<_> = (0u8..4)
+        .map(|t| {
+            let dir = dir.clone();
+            std::thread::spawn(move || {
+                let log = TransparencyLog::open(&dir).unwrap();
+                (0u8..10)
+                    .map(|i| {
+                        log.append(&LogEntry::new(&[t, i], None, 1).unwrap())
+                            .unwrap()
+                    })
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect();
+    let proofs: Vec<_> = threads
+        .into_iter()
+        .flat_map(|thread| thread.join().unwrap())
+        .collect();
+
+    let leaf_hashes: Vec<Vec<u8>> = TransparencyLog::open(&dir)
+        .unwrap()
+        .entries()
+        .unwrap()
+        .iter()
+        .map(|entry| entry.leaf_hash())
+        .collect();
+    assert_eq!(leaf_hashes.len(), proofs.len());
+    let mut indices: Vec<_> = proofs.iter().map(|proof| proof.index).collect();
+    indices.sort_unstable();
+    assert_eq!(indices, (0..proofs.len() as u64).collect::<Vec<_>>());
+    for proof in &proofs {
+        assert_eq!(proof.head, head(&leaf_hashes[..proof.head.size as usize]));
+        let root = root_from_inclusion_proof(
+            proof.index,
+            proof.head.size,
+            proof.entry.leaf_hash(),
+            &proof.hashes,
+        )
+        .unwrap();
+        assert_eq!(root, proof.head.root_hash);
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,72 @@
+use wasmsign::signature::eddsa::EdDSA;
+use wasmsign::signature::wasmsign2;
+use wasmsign::*;
+
+const MODULE: &[u8] = b"\0asm\x01\0\0\0\
+    \x01\x04\x01\x60\x00\x00\
+    \x03\x02\x01\x00\
+    \x0a\x04\x01\x02\x00\x0b";
+
+#[test]
+fn wasmsign2_keys_round_trip() {
+    let kp = keygen_from_seed(&EdDSA, &[7u8; 32], None).unwrap();
+    let pk_bytes = wasmsign2::public_key_to_bytes(&kp.pk).unwrap();
+    let sk_bytes = wasmsign2::secret_key_to_bytes(&kp.sk).unwrap();
+    assert_eq!(pk_bytes.len(), 33);
+    assert_eq!(pk_bytes[0], wasmsign2::ED25519_PK_ID);
+    assert_eq!(sk_bytes.len(), 65);
+    assert_eq!(sk_bytes[0], wasmsign2::ED25519_SK_ID);
+    assert!(wasmsign2::is_public_key(&pk_bytes) && !wasmsign2::is_secret_key(&pk_bytes));
+    assert!(wasmsign2::is_secret_key(&sk_bytes) && !wasmsign2::is_public_key(&sk_bytes));
+
+    let pk = wasmsign2::public_key_from_bytes(&pk_bytes).unwrap();
+    assert_eq!(pk.raw(), kp.pk.raw());
+    let kp2 = wasmsign2::key_pair_from_bytes(&sk_bytes).unwrap();
+    assert_eq!(kp2.pk.raw(), kp.pk.raw());
+    assert_eq!(kp2.sk.raw(), kp.sk.raw());
+    assert!(wasmsign2::public_key_from_bytes(&sk_bytes).is_err());
+    assert!(wasmsign2::key_pair_from_bytes(&pk_bytes).is_err());
+}
+
+#[test]
+fn wasmsign2_signatures_follow_the_specification() {
+    let kp = keygen_from_seed(&EdDSA, &[7u8; 32], None)
+        .unwrap()
+        .deterministic(true);
+    let signed = sign_wasmsign2(MODULE, &kp, None).unwrap();
+
+    // The signature header is the first section, and covers the hash of all the others
+    let mut msg = b"wasmsig\x01\x01\x01".to_vec();
+    msg.extend_from_slice(&hmac_sha256::Hash::hash(&MODULE[8..]));
+    let signature = ed25519_compact::SecretKey::from_slice(kp.sk.raw())
+        .unwrap()
+        .sign(&msg, None);
+    let mut signature_for_hashes = vec![0x00, wasmsign2::ED25519_PK_ID, 64];
+    signature_for_hashes.extend_from_slice(signature.as_ref());
+    let mut signed_hashes = vec![1];
+    signed_hashes.extend_from_slice(&hmac_sha256::Hash::hash(&MODULE[8..]));
+    signed_hashes.extend_from_slice(&[1, signature_for_hashes.len() as u8]);
+    signed_hashes.extend_from_slice(&signature_for_hashes);
+    let mut payload = b"\x09signature\x01\x01\x01\x01".to_vec();
+    payload.push(signed_hashes.len() as u8);
+    payload.extend_from_slice(&signed_hashes);
+    let mut expected = MODULE[..8].to_vec();
+    expected.extend_from_slice(&[0x00, payload.len() as u8]);
+    expected.extend_from_slice(&payload);
+    expected.extend_from_slice(&MODULE[8..]);
+    assert_eq!(signed, expected);
+    verify_wasmsign2(&signed, &kp.pk).unwrap();
+
+    // A second key adds its signature to the same header
+    let kp2 = keygen_from_seed(&EdDSA, &[8u8; 32], None).unwrap();
+    let signed2 = sign_wasmsign2(&signed, &kp2, Some(b"key 2")).unwrap();
+    verify_wasmsign2(&signed2, &kp.pk).unwrap();
+    verify_wasmsign2(&signed2, &kp2.pk).unwrap();
+    assert!(verify_wasmsign2(&signed, &kp2.pk).is_err());
+
+    // Any change to the content is detected
+    let mut tampered = signed2.clone();
+    *tampered.last_mut().unwrap() = 0x0f;
+    assert!(verify_wasmsign2(&tampered, &kp.pk).is_err());
+    assert!(verify_wasmsign2(MODULE, &kp.pk).is_err());
+}
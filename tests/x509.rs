@@ -0,0 +1,108 @@
+use rcgen::{
+    date_time_ymd, BasicConstraints, Certificate, CertificateParams, DnType,
+    ExtendedKeyUsagePurpose, IsCa, KeyPair, KeyUsagePurpose, PKCS_ECDSA_P256_SHA256,
+};
+use std::convert::TryFrom;
+use wasmsign::signature::eddsa::EdDSA;
+use wasmsign::*;
+
+const MODULE: &[u8] = b"\0asm\x01\0\0\0";
+const SEED: [u8; 32] = [7u8; 32];
+
+fn ca(name: &str) -> (Certificate, KeyPair) {
+    let key = KeyPair::generate_for(&PKCS_ECDSA_P256_SHA256).unwrap();
+    let mut params = CertificateParams::new(vec![]).unwrap();
+    params.distinguished_name.push(DnType::CommonName, name);
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    params.key_usages = vec![KeyUsagePurpose::KeyCertSign];
+    (params.self_signed(&key).unwrap(), key)
+}
+
+/// An Ed25519 certificate for the key derived from `SEED`.
+fn leaf(issuer: &Certificate, issuer_key: &KeyPair, code_signing: bool, expired: bool) -> Vec<u8> {
+    let mut pkcs8 = vec![
+        0x30, 0x2e, 0x02, 0x01, 0x00, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x04, 0x22, 0x04,
+        0x20,
+    ];
+    pkcs8.extend_from_slice(&SEED);
+    let key = KeyPair::try_from(pkcs8.as_slice()).unwrap();
+    let mut params = CertificateParams::new(vec![]).unwrap();
+    params
+        .distinguished_name
+        .push(DnType::CommonName, "Test signer");
+    params.key_usages = vec![KeyUsagePurpose::DigitalSignature];
+    if code_signing {
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::CodeSigning];
+    }
+    if expired {
+        params.not_before = date_time_ymd(2020, 1, 1);
+        params.not_after = date_time_ymd(2021, 1, 1);
+    }
+    params
+        .signed_by(&key, issuer, issuer_key)
+        .unwrap()
+        .der()
+        .to_vec()
+}
+
+fn sign_with_signer(signer: &dyn Signer) -> Vec<u8> {
+    sign_custom_section(
+        MODULE,
+        signer,
+        &AdditionalData::default(),
+        HashAlg::default(),
+        None,
+        &SectionSelection::All,
+        DEFAULT_CUSTOM_SECTION_NAME,
+    )
+    .unwrap()
+}
+
+fn sign_with_chain(certificates: Vec<Vec<u8>>) -> Vec<u8> {
+    let kp = keygen_from_seed(&EdDSA, &SEED, None).unwrap();
+    sign_with_signer(&x509::CertifiedSigner::new(Box::new(kp), certificates).unwrap())
+}
+
+fn verify_with_roots(module: &[u8], root: &Certificate) -> Result<PublicKey, WError> {
+    let roots = x509::TrustedRoots::new(vec![root.der().to_vec()]);
+    verify_custom_section_with_roots(
+        module,
+        &roots,
+        None,
+        &AdditionalData::default(),
+        None,
+        DEFAULT_CUSTOM_SECTION_NAME,
+    )
+}
+
+#[test]
+fn certified_signatures_are_verified_against_roots() {
+    let (root, root_key) = ca("Test root");
+    let (other_root, _) = ca("Other root");
+    let pk = keygen_from_seed(&EdDSA, &SEED, None).unwrap().pk;
+
+    let signed = sign_with_chain(vec![leaf(&root, &root_key, true, false)]);
+    let signature = inspect_custom_section(&signed, DEFAULT_CUSTOM_SECTION_NAME).unwrap();
+    assert_eq!(signature.certificates().len(), 1);
+    assert_eq!(verify_with_roots(&signed, &root).unwrap().raw(), pk.raw());
+    assert!(verify_with_roots(&signed, &other_root).is_err());
+
+    let signed = sign_with_chain(vec![leaf(&root, &root_key, false, false)]);
+    assert!(verify_with_roots(&signed, &root).is_err());
+
+    let signed = sign_with_chain(vec![leaf(&root, &root_key, true, true)]);
+    assert!(verify_with_roots(&signed, &root).is_err());
+
+    let signed = sign_with_signer(&keygen_from_seed(&EdDSA, &SEED, None).unwrap());
+    assert!(verify_with_roots(&signed, &root).is_err());
+}
+
+#[test]
+fn certificates_must_match_the_signing_key() {
+    let (root, root_key) = ca("Test root");
+    let certificate = leaf(&root, &root_key, true, false);
+    let other_kp = keygen_from_seed(&EdDSA, &[8u8; 32], None).unwrap();
+    assert!(x509::CertifiedSigner::new(Box::new(other_kp), vec![certificate]).is_err());
+    let kp = keygen_from_seed(&EdDSA, &SEED, None).unwrap();
+    assert!(x509::CertifiedSigner::new(Box::new(kp), vec![]).is_err());
+}